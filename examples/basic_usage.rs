@@ -78,6 +78,7 @@ Hello {{capitalize user_name}}! Let's work with {{upper language}} today."#;
         storage_dir: None, // Use default
         validate_templates: true,
         max_prompt_length: 1000, // Smaller limit for demo
+        ..Config::default()
     };
 
     let mut custom_manager = PromptManager::with_config(config)?;
@@ -93,6 +94,3 @@ Hello {{capitalize user_name}}! Let's work with {{upper language}} today."#;
     println!("\n=== Demo Complete ===");
     Ok(())
 }
-
-// Helper to add tracing subscriber dependency
-use tracing_subscriber;
\ No newline at end of file
@@ -0,0 +1,178 @@
+//! Prompt manager for async applications
+
+use crate::async_storage::{AsyncFileStorage, AsyncPromptStorage};
+use crate::batch::BatchReport;
+use crate::error::Result;
+use crate::search::{SearchHit, SearchQuery};
+use crate::storage::{PromptInfo, ReconcileReport};
+use crate::template::TemplateEngine;
+use crate::Config;
+use std::collections::HashMap;
+
+/// Async interface for prompt management
+///
+/// Mirrors the core of [`PromptManager`](crate::PromptManager): storage
+/// calls are awaited instead of blocking, while template rendering runs
+/// inline since it does no I/O. Prompts are validated per `config` on save
+/// and render.
+pub struct AsyncPromptManager<S: AsyncPromptStorage = AsyncFileStorage> {
+    storage: S,
+    template_engine: TemplateEngine,
+    config: Config,
+}
+
+impl AsyncPromptManager<AsyncFileStorage> {
+    /// Create a manager over file storage configured like [`PromptManager::with_config`](crate::PromptManager::with_config)
+    pub async fn with_config(config: Config) -> Result<Self> {
+        let storage = AsyncFileStorage::with_options(config.resolved_storage_dir()?, config.file_storage_options()).await?;
+        Ok(Self::with_storage(storage, config))
+    }
+}
+
+impl<S: AsyncPromptStorage> AsyncPromptManager<S> {
+    /// Create a manager over a custom async storage backend
+    pub fn with_storage(storage: S, config: Config) -> Self {
+        let mut template_engine = TemplateEngine::new();
+        template_engine.set_strict_variables(config.strict_variables);
+        Self { storage, template_engine, config }
+    }
+
+    /// Load the default system prompt
+    pub async fn load_default(&self) -> Result<String> {
+        self.storage.load_default().await
+    }
+
+    /// Save a new default system prompt
+    pub async fn save_default(&self, prompt: &str) -> Result<()> {
+        let prompt = prompt.trim();
+        self.config.validate_prompt(prompt)?;
+        self.storage.save_default(prompt).await
+    }
+
+    /// Load a named prompt
+    pub async fn load_prompt(&self, name: &str) -> Result<String> {
+        self.storage.load_prompt(name).await
+    }
+
+    /// Save a named prompt
+    pub async fn save_prompt(&self, name: &str, prompt: &str) -> Result<()> {
+        let prompt = prompt.trim();
+        self.config.validate_prompt(prompt)?;
+        self.storage.save_prompt(name, prompt).await
+    }
+
+    /// Save several prompts, attempting every entry
+    ///
+    /// Entries that fail validation or can't be written are listed in the
+    /// report's `failed`.
+    pub async fn save_prompts(&self, entries: &[(String, String)]) -> Result<BatchReport> {
+        let mut report = BatchReport::default();
+        let mut prepared = Vec::new();
+        for (name, prompt) in entries {
+            let prompt = prompt.trim();
+            match self.config.validate_prompt(prompt) {
+                Ok(()) => prepared.push((name.clone(), prompt.to_string())),
+                Err(e) => report.failed.push((name.clone(), e.to_string())),
+            }
+        }
+
+        let results = self.storage.save_prompts(&prepared).await?;
+        for ((name, _), result) in prepared.into_iter().zip(results) {
+            match result {
+                Ok(()) => report.succeeded.push(name),
+                Err(e) => report.failed.push((name, e.to_string())),
+            }
+        }
+        Ok(report)
+    }
+
+    /// List all named prompts
+    pub async fn list_prompts(&self) -> Result<Vec<String>> {
+        self.storage.list_prompts().await
+    }
+
+    /// Delete a named prompt
+    pub async fn delete_prompt(&self, name: &str) -> Result<()> {
+        self.storage.delete_prompt(name).await
+    }
+
+    /// Check if a named prompt exists
+    pub async fn prompt_exists(&self, name: &str) -> bool {
+        self.storage.prompt_exists(name).await
+    }
+
+    /// Get prompt metadata (size, modification time, etc.)
+    pub async fn get_prompt_info(&self, name: &str) -> Result<PromptInfo> {
+        self.storage.get_prompt_info(name).await
+    }
+
+    /// List the timestamps of a prompt's archived versions, oldest first
+    pub async fn list_versions(&self, name: &str) -> Result<Vec<u128>> {
+        self.storage.list_versions(name).await
+    }
+
+    /// Load an archived version of a prompt
+    pub async fn load_version(&self, name: &str, timestamp: u128) -> Result<String> {
+        self.storage.load_version(name, timestamp).await
+    }
+
+    /// Search prompt content line by line
+    pub async fn search(&self, query: &SearchQuery) -> Result<Vec<SearchHit>> {
+        self.storage.search(query).await
+    }
+
+    /// Bring metadata in line with prompts stored outside the API
+    pub async fn reconcile(&self) -> Result<ReconcileReport> {
+        self.storage.reconcile().await
+    }
+
+    /// Remove metadata entries for prompts whose content no longer exists
+    pub async fn vacuum(&self) -> Result<usize> {
+        self.storage.vacuum().await
+    }
+
+    /// Render a template with variables
+    pub fn render_template(&self, template: &str, variables: &HashMap<String, String>) -> Result<String> {
+        if self.config.validate_templates {
+            self.template_engine.validate_template(template)?;
+        }
+
+        let rendered = self.template_engine.render(template, variables)?;
+        self.config.validate_prompt(&rendered)?;
+        Ok(rendered)
+    }
+
+    /// Load a named prompt and render it with variables
+    pub async fn render_prompt(&self, name: &str, variables: &HashMap<String, String>) -> Result<String> {
+        let template = self.storage.load_prompt(name).await?;
+        self.render_template(&template, variables)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_async_manager_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config {
+            storage_dir: Some(temp_dir.path().to_path_buf()),
+            ..Config::default()
+        };
+        let manager = AsyncPromptManager::with_config(config).await.unwrap();
+
+        manager.save_prompt("greeting", "Hello {{name}}").await.unwrap();
+        assert_eq!(manager.load_prompt("greeting").await.unwrap(), "Hello {{name}}");
+        assert_eq!(manager.list_prompts().await.unwrap(), vec!["greeting"]);
+
+        let mut vars = HashMap::new();
+        vars.insert("name".to_string(), "Ada".to_string());
+        assert_eq!(manager.render_prompt("greeting", &vars).await.unwrap(), "Hello Ada");
+
+        assert!(manager.save_prompt("empty", "   ").await.is_err());
+        manager.delete_prompt("greeting").await.unwrap();
+        assert!(!manager.prompt_exists("greeting").await);
+    }
+}
@@ -0,0 +1,477 @@
+//! Async prompt storage for use inside Tokio runtimes
+
+use crate::error::{PromptError, Result};
+use crate::search::{SearchHit, SearchQuery};
+use crate::storage::{FileStorage, FileStorageOptions, PromptInfo, PromptStorage, ReconcileReport};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Async counterpart of [`PromptStorage`]
+///
+/// Covers every method of the synchronous trait, with the same error
+/// semantics and the same defaults for optional features. Returned
+/// futures are `Send` so they can be awaited in multi-threaded handlers.
+pub trait AsyncPromptStorage: Send + Sync {
+    /// Load the default system prompt
+    fn load_default(&self) -> impl Future<Output = Result<String>> + Send;
+
+    /// Save the default system prompt
+    fn save_default(&self, prompt: &str) -> impl Future<Output = Result<()>> + Send;
+
+    /// Load the default prompt stored for a profile, if any
+    fn load_profile_default(&self, _profile: &str) -> impl Future<Output = Result<Option<String>>> + Send {
+        async { Ok(None) }
+    }
+
+    /// Save the default prompt for a profile
+    fn save_profile_default(&self, profile: &str, _prompt: &str) -> impl Future<Output = Result<()>> + Send {
+        let error = PromptError::Storage(format!("Cannot save profile '{}': profiles are not supported by this storage", profile));
+        async { Err(error) }
+    }
+
+    /// List profiles that have a stored default, sorted
+    fn list_profiles(&self) -> impl Future<Output = Result<Vec<String>>> + Send {
+        async { Ok(Vec::new()) }
+    }
+
+    /// Load a named prompt
+    fn load_prompt(&self, name: &str) -> impl Future<Output = Result<String>> + Send;
+
+    /// Load a named prompt exactly as stored, without trimming
+    fn load_prompt_raw(&self, name: &str) -> impl Future<Output = Result<String>> + Send {
+        self.load_prompt(name)
+    }
+
+    /// Save a named prompt
+    fn save_prompt(&self, name: &str, prompt: &str) -> impl Future<Output = Result<()>> + Send;
+
+    /// List all available named prompts
+    fn list_prompts(&self) -> impl Future<Output = Result<Vec<String>>> + Send;
+
+    /// Delete a named prompt
+    fn delete_prompt(&self, name: &str) -> impl Future<Output = Result<()>> + Send;
+
+    /// Save several prompts, returning a result per entry in order
+    ///
+    /// Error semantics match [`PromptStorage::save_prompts`].
+    fn save_prompts(&self, entries: &[(String, String)]) -> impl Future<Output = Result<Vec<Result<()>>>> + Send {
+        async move {
+            let mut results = Vec::with_capacity(entries.len());
+            for (name, prompt) in entries {
+                results.push(self.save_prompt(name, prompt).await);
+            }
+            Ok(results)
+        }
+    }
+
+    /// Delete several prompts, returning a result per name in order
+    fn delete_prompts(&self, names: &[String]) -> impl Future<Output = Result<Vec<Result<()>>>> + Send {
+        async move {
+            let mut results = Vec::with_capacity(names.len());
+            for name in names {
+                results.push(self.delete_prompt(name).await);
+            }
+            Ok(results)
+        }
+    }
+
+    /// Check if a prompt exists
+    fn prompt_exists(&self, name: &str) -> impl Future<Output = bool> + Send;
+
+    /// Get prompt metadata
+    fn get_prompt_info(&self, name: &str) -> impl Future<Output = Result<PromptInfo>> + Send;
+
+    /// Get prompt metadata from the stored content rather than cached metadata
+    fn refresh_prompt_info(&self, name: &str) -> impl Future<Output = Result<PromptInfo>> + Send {
+        self.get_prompt_info(name)
+    }
+
+    /// Set the content type recorded for a prompt
+    fn set_content_type(&self, name: &str, content_type: &str) -> impl Future<Output = Result<()>> + Send;
+
+    /// Exchange the content of two prompts
+    fn swap_prompts(&self, a: &str, b: &str) -> impl Future<Output = Result<()>> + Send;
+
+    /// Rename a prompt, keeping its metadata
+    fn rename_prompt(&self, old: &str, new: &str) -> impl Future<Output = Result<()>> + Send;
+
+    /// Copy a prompt's content to a new name with fresh metadata
+    fn copy_prompt(&self, source: &str, dest: &str, overwrite: bool) -> impl Future<Output = Result<()>> + Send;
+
+    /// Replace the tags on a prompt
+    fn set_tags(&self, name: &str, tags: Vec<String>) -> impl Future<Output = Result<()>> + Send;
+
+    /// Replace the example variable sets stored with a prompt
+    fn set_examples(&self, name: &str, examples: Vec<HashMap<String, String>>) -> impl Future<Output = Result<()>> + Send;
+
+    /// Delete all but the most recent `keep` historical versions of a prompt
+    fn prune_history(&self, _name: &str, _keep: usize) -> impl Future<Output = Result<usize>> + Send {
+        async { Ok(0) }
+    }
+
+    /// Get the on-disk size of a prompt's history in bytes
+    fn history_size(&self, _name: &str) -> impl Future<Output = Result<u64>> + Send {
+        async { Ok(0) }
+    }
+
+    /// List the timestamps of a prompt's historical versions, oldest first
+    fn list_versions(&self, _name: &str) -> impl Future<Output = Result<Vec<u128>>> + Send {
+        async { Ok(Vec::new()) }
+    }
+
+    /// Load the content of a historical version of a prompt
+    fn load_version(&self, name: &str, timestamp: u128) -> impl Future<Output = Result<String>> + Send {
+        let error = PromptError::PromptNotFound(format!("{} (version {})", name, timestamp));
+        async { Err(error) }
+    }
+
+    /// Remove metadata entries for prompts whose content no longer exists
+    fn vacuum(&self) -> impl Future<Output = Result<usize>> + Send {
+        async { Ok(0) }
+    }
+
+    /// Search prompt content line by line
+    ///
+    /// The default loads and scans every prompt in name order.
+    fn search(&self, query: &SearchQuery) -> impl Future<Output = Result<Vec<SearchHit>>> + Send {
+        async move {
+            let mut hits = Vec::new();
+            for name in self.list_prompts().await? {
+                let content = self.load_prompt(&name).await?;
+                hits.extend(crate::search::search_content(&name, &content, query));
+            }
+            Ok(hits)
+        }
+    }
+
+    /// Bring metadata in line with prompts stored outside the API
+    fn reconcile(&self) -> impl Future<Output = Result<ReconcileReport>> + Send {
+        async { Ok(ReconcileReport::default()) }
+    }
+
+    /// Delete leftover temporary and backup files, returning their paths
+    fn clean_temp_files(&self) -> impl Future<Output = Result<Vec<PathBuf>>> + Send {
+        async { Ok(Vec::new()) }
+    }
+}
+
+/// File-based async storage
+///
+/// Runs [`FileStorage`] on Tokio's blocking pool, the same way `tokio::fs`
+/// runs file operations, so the layout, options, history archiving, and
+/// error semantics are exactly those of the synchronous storage and both
+/// can share a directory. Changes are serialized within one instance.
+#[derive(Clone)]
+pub struct AsyncFileStorage {
+    inner: Arc<FileStorage>,
+    write_lock: Arc<Mutex<()>>,
+}
+
+impl AsyncFileStorage {
+    /// Open a directory with default options
+    pub async fn with_directory<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        Self::with_options(dir, FileStorageOptions::default()).await
+    }
+
+    /// Open a directory with custom options, as [`FileStorage::with_options`] does
+    pub async fn with_options<P: AsRef<Path>>(dir: P, options: FileStorageOptions) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        let inner = blocking(move || FileStorage::with_options(dir, options)).await?;
+        Ok(Self {
+            inner: Arc::new(inner),
+            write_lock: Arc::new(Mutex::new(())),
+        })
+    }
+
+    /// Run a read-only storage call on the blocking pool
+    async fn read<T, F>(&self, call: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&FileStorage) -> Result<T> + Send + 'static,
+    {
+        let inner = Arc::clone(&self.inner);
+        blocking(move || call(&inner)).await
+    }
+
+    /// Run a modifying storage call on the blocking pool, one at a time
+    async fn write<T, F>(&self, call: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&FileStorage) -> Result<T> + Send + 'static,
+    {
+        let inner = Arc::clone(&self.inner);
+        let write_lock = Arc::clone(&self.write_lock);
+        blocking(move || {
+            let _guard = write_lock.lock().unwrap_or_else(|e| e.into_inner());
+            call(&inner)
+        })
+        .await
+    }
+}
+
+impl AsyncPromptStorage for AsyncFileStorage {
+    async fn load_default(&self) -> Result<String> {
+        self.read(|storage| storage.load_default()).await
+    }
+
+    async fn save_default(&self, prompt: &str) -> Result<()> {
+        let prompt = prompt.to_string();
+        self.write(move |storage| storage.save_default(&prompt)).await
+    }
+
+    async fn load_profile_default(&self, profile: &str) -> Result<Option<String>> {
+        let profile = profile.to_string();
+        self.read(move |storage| storage.load_profile_default(&profile)).await
+    }
+
+    async fn save_profile_default(&self, profile: &str, prompt: &str) -> Result<()> {
+        let (profile, prompt) = (profile.to_string(), prompt.to_string());
+        self.write(move |storage| storage.save_profile_default(&profile, &prompt)).await
+    }
+
+    async fn list_profiles(&self) -> Result<Vec<String>> {
+        self.read(|storage| storage.list_profiles()).await
+    }
+
+    async fn load_prompt(&self, name: &str) -> Result<String> {
+        let name = name.to_string();
+        self.read(move |storage| storage.load_prompt(&name)).await
+    }
+
+    async fn load_prompt_raw(&self, name: &str) -> Result<String> {
+        let name = name.to_string();
+        self.read(move |storage| storage.load_prompt_raw(&name)).await
+    }
+
+    async fn save_prompt(&self, name: &str, prompt: &str) -> Result<()> {
+        let (name, prompt) = (name.to_string(), prompt.to_string());
+        self.write(move |storage| storage.save_prompt(&name, &prompt)).await
+    }
+
+    async fn list_prompts(&self) -> Result<Vec<String>> {
+        self.read(|storage| storage.list_prompts()).await
+    }
+
+    async fn delete_prompt(&self, name: &str) -> Result<()> {
+        let name = name.to_string();
+        self.write(move |storage| storage.delete_prompt(&name)).await
+    }
+
+    async fn save_prompts(&self, entries: &[(String, String)]) -> Result<Vec<Result<()>>> {
+        let entries = entries.to_vec();
+        self.write(move |storage| storage.save_prompts(&entries)).await
+    }
+
+    async fn delete_prompts(&self, names: &[String]) -> Result<Vec<Result<()>>> {
+        let names = names.to_vec();
+        self.write(move |storage| storage.delete_prompts(&names)).await
+    }
+
+    async fn prompt_exists(&self, name: &str) -> bool {
+        let name = name.to_string();
+        self.read(move |storage| Ok(storage.prompt_exists(&name))).await.unwrap_or(false)
+    }
+
+    async fn get_prompt_info(&self, name: &str) -> Result<PromptInfo> {
+        let name = name.to_string();
+        self.read(move |storage| storage.get_prompt_info(&name)).await
+    }
+
+    async fn refresh_prompt_info(&self, name: &str) -> Result<PromptInfo> {
+        let name = name.to_string();
+        self.read(move |storage| storage.refresh_prompt_info(&name)).await
+    }
+
+    async fn set_content_type(&self, name: &str, content_type: &str) -> Result<()> {
+        let (name, content_type) = (name.to_string(), content_type.to_string());
+        self.write(move |storage| storage.set_content_type(&name, &content_type)).await
+    }
+
+    async fn swap_prompts(&self, a: &str, b: &str) -> Result<()> {
+        let (a, b) = (a.to_string(), b.to_string());
+        self.write(move |storage| storage.swap_prompts(&a, &b)).await
+    }
+
+    async fn rename_prompt(&self, old: &str, new: &str) -> Result<()> {
+        let (old, new) = (old.to_string(), new.to_string());
+        self.write(move |storage| storage.rename_prompt(&old, &new)).await
+    }
+
+    async fn copy_prompt(&self, source: &str, dest: &str, overwrite: bool) -> Result<()> {
+        let (source, dest) = (source.to_string(), dest.to_string());
+        self.write(move |storage| storage.copy_prompt(&source, &dest, overwrite)).await
+    }
+
+    async fn set_tags(&self, name: &str, tags: Vec<String>) -> Result<()> {
+        let name = name.to_string();
+        self.write(move |storage| storage.set_tags(&name, tags)).await
+    }
+
+    async fn set_examples(&self, name: &str, examples: Vec<HashMap<String, String>>) -> Result<()> {
+        let name = name.to_string();
+        self.write(move |storage| storage.set_examples(&name, examples)).await
+    }
+
+    async fn prune_history(&self, name: &str, keep: usize) -> Result<usize> {
+        let name = name.to_string();
+        self.write(move |storage| storage.prune_history(&name, keep)).await
+    }
+
+    async fn history_size(&self, name: &str) -> Result<u64> {
+        let name = name.to_string();
+        self.read(move |storage| storage.history_size(&name)).await
+    }
+
+    async fn list_versions(&self, name: &str) -> Result<Vec<u128>> {
+        let name = name.to_string();
+        self.read(move |storage| storage.list_versions(&name)).await
+    }
+
+    async fn load_version(&self, name: &str, timestamp: u128) -> Result<String> {
+        let name = name.to_string();
+        self.read(move |storage| storage.load_version(&name, timestamp)).await
+    }
+
+    async fn vacuum(&self) -> Result<usize> {
+        self.write(|storage| storage.vacuum()).await
+    }
+
+    async fn search(&self, query: &SearchQuery) -> Result<Vec<SearchHit>> {
+        let query = query.clone();
+        self.read(move |storage| {
+            if let Some(hits) = storage.search_native(&query) {
+                return hits;
+            }
+            let mut hits = Vec::new();
+            for name in storage.list_prompts()? {
+                let content = storage.load_prompt(&name)?;
+                hits.extend(crate::search::search_content(&name, &content, &query));
+            }
+            Ok(hits)
+        })
+        .await
+    }
+
+    async fn reconcile(&self) -> Result<ReconcileReport> {
+        self.write(|storage| storage.reconcile()).await
+    }
+
+    async fn clean_temp_files(&self) -> Result<Vec<PathBuf>> {
+        self.write(|storage| storage.clean_temp_files()).await
+    }
+}
+
+/// Run a blocking storage call without stalling the async executor
+async fn blocking<T, F>(call: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(call).await
+        .map_err(|e| PromptError::Storage(format!("Failed to run storage task: {}", e)))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::content_hash;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_async_file_storage_basic_operations() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = AsyncFileStorage::with_directory(temp_dir.path()).await.unwrap();
+
+        assert!(!storage.load_default().await.unwrap().is_empty());
+        storage.save_default("Async default").await.unwrap();
+        assert_eq!(storage.load_default().await.unwrap(), "Async default");
+
+        storage.save_prompt("greeting", "Hello there").await.unwrap();
+        assert_eq!(storage.load_prompt("greeting").await.unwrap(), "Hello there");
+        assert!(storage.prompt_exists("greeting").await);
+        assert_eq!(storage.list_prompts().await.unwrap(), vec!["greeting"]);
+
+        let info = storage.get_prompt_info("greeting").await.unwrap();
+        assert_eq!(info.size, 11);
+        assert_eq!(info.content_hash, Some(content_hash("Hello there")));
+
+        storage.delete_prompt("greeting").await.unwrap();
+        assert!(!storage.prompt_exists("greeting").await);
+        assert!(storage.list_prompts().await.unwrap().is_empty());
+        assert!(matches!(storage.load_prompt("greeting").await, Err(PromptError::PromptNotFound(_))));
+        assert!(matches!(storage.delete_prompt("greeting").await, Err(PromptError::PromptNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_async_storage_shares_layout_with_file_storage() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = AsyncFileStorage::with_directory(temp_dir.path()).await.unwrap();
+        storage.save_prompt("shared", "Written async").await.unwrap();
+        storage.set_tags("shared", vec!["async".to_string()]).await.unwrap();
+        storage.rename_prompt("shared", "renamed").await.unwrap();
+
+        let sync_storage = FileStorage::with_directory(temp_dir.path()).unwrap();
+        assert_eq!(sync_storage.load_prompt("renamed").unwrap(), "Written async");
+        assert_eq!(sync_storage.get_prompt_info("renamed").unwrap().tags, vec!["async"]);
+    }
+
+    #[tokio::test]
+    async fn test_async_storage_honors_file_storage_options() {
+        let temp_dir = TempDir::new().unwrap();
+        let options = FileStorageOptions {
+            keep_versions: 2,
+            extension: "md".to_string(),
+            namespace_separator: Some('/'),
+            ..FileStorageOptions::default()
+        };
+        let storage = AsyncFileStorage::with_options(temp_dir.path(), options.clone()).await.unwrap();
+        for version in 1..=4 {
+            storage.save_prompt("team/notes", &format!("Version {}", version)).await.unwrap();
+        }
+        assert!(temp_dir.path().join("team").join("notes.md").exists());
+
+        let versions = storage.list_versions("team/notes").await.unwrap();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(storage.load_version("team/notes", versions[0]).await.unwrap(), "Version 2");
+        assert!(storage.history_size("team/notes").await.unwrap() > 0);
+
+        // A sync storage with the same options sees the async side's versions
+        let sync_storage = FileStorage::with_options(temp_dir.path(), options).unwrap();
+        sync_storage.save_prompt("team/notes", "Version 5").unwrap();
+        assert_eq!(storage.list_versions("team/notes").await.unwrap().len(), 2);
+        assert_eq!(storage.load_prompt("team/notes").await.unwrap(), "Version 5");
+    }
+
+    #[tokio::test]
+    async fn test_async_storage_extended_operations() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = AsyncFileStorage::with_directory(temp_dir.path()).await.unwrap();
+
+        storage.save_profile_default("dev", "Dev default").await.unwrap();
+        assert_eq!(storage.list_profiles().await.unwrap(), vec!["dev"]);
+        assert_eq!(storage.load_profile_default("dev").await.unwrap().as_deref(), Some("Dev default"));
+
+        let entries = vec![
+            ("alpha".to_string(), "Find the needle".to_string()),
+            ("beta".to_string(), "Nothing here".to_string()),
+        ];
+        let results = storage.save_prompts(&entries).await.unwrap();
+        assert!(results.iter().all(|result| result.is_ok()));
+
+        let hits = storage.search(&SearchQuery::new("NEEDLE")).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].name, "alpha");
+
+        std::fs::write(temp_dir.path().join("gamma.txt"), "Added by hand").unwrap();
+        assert_eq!(storage.reconcile().await.unwrap().added, vec!["gamma"]);
+        std::fs::remove_file(temp_dir.path().join("gamma.txt")).unwrap();
+        assert_eq!(storage.vacuum().await.unwrap(), 1);
+
+        let results = storage.delete_prompts(&["alpha".to_string(), "missing".to_string()]).await.unwrap();
+        assert!(results[0].is_ok());
+        assert!(matches!(results[1], Err(PromptError::PromptNotFound(_))));
+        assert_eq!(storage.list_prompts().await.unwrap(), vec!["beta"]);
+    }
+}
@@ -0,0 +1,17 @@
+//! Bulk prompt operations
+
+/// Outcome of a bulk save or delete
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchReport {
+    /// Prompts the operation succeeded for, in input order
+    pub succeeded: Vec<String>,
+    /// Prompts the operation failed for, with the reason
+    pub failed: Vec<(String, String)>,
+}
+
+impl BatchReport {
+    /// Check if every item succeeded
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
@@ -0,0 +1,68 @@
+//! Portable JSON bundles of prompt libraries
+
+use serde::{Deserialize, Serialize};
+
+/// Current bundle format version
+pub const BUNDLE_VERSION: &str = "1";
+
+/// A prompt library serialized for sharing
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PromptBundle {
+    /// Bundle format version
+    pub version: String,
+    /// Default prompt, if exported
+    #[serde(default)]
+    pub default: Option<String>,
+    /// Named prompts, excluding partials
+    #[serde(default)]
+    pub prompts: Vec<BundleEntry>,
+    /// Partials, named without the `partials/` prefix
+    #[serde(default)]
+    pub partials: Vec<BundleEntry>,
+    /// Code helpers the bundled templates use, which the importer must provide
+    #[serde(default)]
+    pub helpers: Vec<String>,
+}
+
+impl PromptBundle {
+    /// Create an empty bundle at the current format version
+    pub fn new() -> Self {
+        Self {
+            version: BUNDLE_VERSION.to_string(),
+            ..Self::default()
+        }
+    }
+}
+
+/// A single prompt in a bundle
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleEntry {
+    /// Prompt name
+    pub name: String,
+    /// Prompt content
+    pub content: String,
+    /// Tags attached to the prompt
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Creation time in Unix seconds
+    #[serde(default)]
+    pub created_at: Option<u64>,
+    /// Last modification time in Unix seconds
+    #[serde(default)]
+    pub modified_at: Option<u64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundle_defaults_missing_sections() {
+        let bundle: PromptBundle =
+            serde_json::from_str(r#"{"version": "1", "prompts": [{"name": "a", "content": "A"}]}"#).unwrap();
+        assert_eq!(bundle.prompts[0].name, "a");
+        assert!(bundle.prompts[0].tags.is_empty());
+        assert!(bundle.partials.is_empty());
+        assert!(bundle.helpers.is_empty());
+    }
+}
@@ -0,0 +1,81 @@
+//! Constraints on template variable values
+
+use crate::error::{PromptError, Result};
+use std::collections::HashMap;
+
+/// A rule a template variable value must satisfy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Constraint {
+    /// Value must be one of the listed options
+    OneOf(Vec<String>),
+    /// Value must be at most this many characters
+    MaxLen(usize),
+    /// Value must match the regular expression (requires the `regex` feature)
+    Regex(String),
+}
+
+impl Constraint {
+    /// Check a variable value against this constraint
+    pub fn check(&self, variable: &str, value: &str) -> Result<()> {
+        match self {
+            Self::OneOf(options) => {
+                if !options.iter().any(|option| option == value) {
+                    return Err(PromptError::ConstraintViolation(format!(
+                        "Variable '{}' must be one of [{}], got '{}'",
+                        variable,
+                        options.join(", "),
+                        value
+                    )));
+                }
+            }
+            Self::MaxLen(max) => {
+                let len = value.chars().count();
+                if len > *max {
+                    return Err(PromptError::ConstraintViolation(format!(
+                        "Variable '{}' exceeds maximum length of {} characters ({} given)",
+                        variable, max, len
+                    )));
+                }
+            }
+            Self::Regex(pattern) => check_regex(variable, value, pattern)?,
+        }
+        Ok(())
+    }
+}
+
+/// Check all provided variables against their constraints
+///
+/// Variables without a constraint, and constraints without a provided
+/// value, are ignored.
+pub fn check_all(
+    constraints: &HashMap<String, Constraint>,
+    variables: &HashMap<String, String>,
+) -> Result<()> {
+    for (variable, constraint) in constraints {
+        if let Some(value) = variables.get(variable) {
+            constraint.check(variable, value)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "regex")]
+fn check_regex(variable: &str, value: &str, pattern: &str) -> Result<()> {
+    let re = regex::Regex::new(pattern)
+        .map_err(|e| PromptError::Config(format!("Invalid regex for variable '{}': {}", variable, e)))?;
+    if !re.is_match(value) {
+        return Err(PromptError::ConstraintViolation(format!(
+            "Variable '{}' must match pattern '{}', got '{}'",
+            variable, pattern, value
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "regex"))]
+fn check_regex(variable: &str, _value: &str, _pattern: &str) -> Result<()> {
+    Err(PromptError::Config(format!(
+        "Regex constraint on variable '{}' requires the `regex` feature",
+        variable
+    )))
+}
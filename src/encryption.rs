@@ -0,0 +1,318 @@
+//! Encryption at rest for any prompt storage backend
+
+use crate::error::{PromptError, Result};
+use crate::search::{SearchHit, SearchQuery};
+use crate::storage::{PromptInfo, PromptStorage, ReconcileReport, content_hash};
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, rand_core::RngCore};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use argon2::Argon2;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tracing::debug;
+
+/// Marker at the start of every encrypted value
+const ENCRYPTED_PREFIX: &str = "gamecode-enc:v1:";
+
+/// Length of the random salt the key is derived with
+const SALT_LEN: usize = 16;
+
+/// Length of the AES-GCM nonce
+const NONCE_LEN: usize = 12;
+
+/// Storage wrapper that encrypts prompt content before it reaches `inner`
+///
+/// Content is sealed with AES-256-GCM under a key derived from the
+/// passphrase with Argon2id. Each stored value carries its own salt and
+/// a fresh random nonce, encoded as base64 text after a version marker,
+/// so any backend that stores strings can hold it. Names, tags, and other
+/// metadata stay in plaintext. Prompt info reports the size and hash of
+/// the decrypted content, so reading it decrypts the prompt.
+///
+/// Values without the marker are returned unchanged, so prompts saved
+/// before encryption was enabled (including a factory default) still
+/// load; they are encrypted the next time they are saved.
+pub struct EncryptedStorage<S: PromptStorage> {
+    inner: S,
+    passphrase: Vec<u8>,
+    salt: [u8; SALT_LEN],
+    ciphers: Mutex<HashMap<[u8; SALT_LEN], Aes256Gcm>>,
+}
+
+impl<S: PromptStorage> EncryptedStorage<S> {
+    /// Wrap a storage backend, deriving the encryption key from `passphrase`
+    pub fn new(inner: S, passphrase: &str) -> Result<Self> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let passphrase = passphrase.as_bytes().to_vec();
+        let cipher = Self::derive_cipher(&passphrase, &salt)?;
+        Ok(Self {
+            inner,
+            passphrase,
+            salt,
+            ciphers: Mutex::new(HashMap::from([(salt, cipher)])),
+        })
+    }
+
+    /// Get the wrapped storage backend
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Derive the AES-256-GCM cipher for a passphrase and salt
+    fn derive_cipher(passphrase: &[u8], salt: &[u8; SALT_LEN]) -> Result<Aes256Gcm> {
+        let mut key = Key::<Aes256Gcm>::default();
+        Argon2::default()
+            .hash_password_into(passphrase, salt, &mut key)
+            .map_err(|e| PromptError::Encryption(format!("Failed to derive key: {}", e)))?;
+        Ok(Aes256Gcm::new(&key))
+    }
+
+    /// Get the cipher for a salt, deriving it on first use
+    ///
+    /// Values written by other instances carry their own salt, and key
+    /// derivation is deliberately slow, so derived ciphers are cached.
+    fn cipher(&self, salt: &[u8; SALT_LEN]) -> Result<Aes256Gcm> {
+        let mut ciphers = self.ciphers.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(cipher) = ciphers.get(salt) {
+            return Ok(cipher.clone());
+        }
+        let cipher = Self::derive_cipher(&self.passphrase, salt)?;
+        ciphers.insert(*salt, cipher.clone());
+        Ok(cipher)
+    }
+
+    /// Encrypt content into the stored text form
+    fn encrypt(&self, what: &str, plaintext: &str) -> Result<String> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self.cipher(&self.salt)?
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| PromptError::Encryption(format!("Failed to encrypt {}", what)))?;
+
+        let mut sealed = Vec::with_capacity(SALT_LEN + NONCE_LEN + ciphertext.len());
+        sealed.extend_from_slice(&self.salt);
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        Ok(format!("{}{}", ENCRYPTED_PREFIX, STANDARD.encode(sealed)))
+    }
+
+    /// Decrypt stored text, passing through values that were never encrypted
+    fn decrypt(&self, what: &str, stored: String) -> Result<String> {
+        let Some(encoded) = stored.strip_prefix(ENCRYPTED_PREFIX) else {
+            debug!("Loaded unencrypted {}", what);
+            return Ok(stored);
+        };
+        let corrupted = || PromptError::Encryption(format!("Failed to decrypt {}: data is corrupted", what));
+        let sealed = STANDARD.decode(encoded).map_err(|_| corrupted())?;
+        if sealed.len() < SALT_LEN + NONCE_LEN {
+            return Err(corrupted());
+        }
+
+        let (salt, rest) = sealed.split_at(SALT_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        let salt: [u8; SALT_LEN] = salt.try_into().map_err(|_| corrupted())?;
+        let plaintext = self.cipher(&salt)?
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| PromptError::Encryption(format!(
+                "Failed to decrypt {}: wrong passphrase or corrupted data",
+                what
+            )))?;
+        String::from_utf8(plaintext).map_err(|_| corrupted())
+    }
+
+    /// Replace the ciphertext size and hash in `info` with those of the plaintext
+    fn plaintext_info(&self, mut info: PromptInfo) -> Result<PromptInfo> {
+        let content = self.load_prompt_raw(&info.name)?;
+        info.size = content.len() as u64;
+        info.content_hash = Some(content_hash(&content));
+        Ok(info)
+    }
+}
+
+impl<S: PromptStorage> PromptStorage for EncryptedStorage<S> {
+    fn load_default(&self) -> Result<String> {
+        self.decrypt("default prompt", self.inner.load_default()?)
+    }
+
+    fn save_default(&self, prompt: &str) -> Result<()> {
+        self.inner.save_default(&self.encrypt("default prompt", prompt)?)
+    }
+
+    fn load_profile_default(&self, profile: &str) -> Result<Option<String>> {
+        let what = format!("default prompt of profile '{}'", profile);
+        self.inner.load_profile_default(profile)?
+            .map(|stored| self.decrypt(&what, stored))
+            .transpose()
+    }
+
+    fn save_profile_default(&self, profile: &str, prompt: &str) -> Result<()> {
+        let what = format!("default prompt of profile '{}'", profile);
+        self.inner.save_profile_default(profile, &self.encrypt(&what, prompt)?)
+    }
+
+    fn list_profiles(&self) -> Result<Vec<String>> {
+        self.inner.list_profiles()
+    }
+
+    fn load_prompt(&self, name: &str) -> Result<String> {
+        self.decrypt(&format!("prompt '{}'", name), self.inner.load_prompt(name)?)
+    }
+
+    fn load_prompt_raw(&self, name: &str) -> Result<String> {
+        self.decrypt(&format!("prompt '{}'", name), self.inner.load_prompt_raw(name)?)
+    }
+
+    fn save_prompt(&self, name: &str, prompt: &str) -> Result<()> {
+        self.inner.save_prompt(name, &self.encrypt(&format!("prompt '{}'", name), prompt)?)
+    }
+
+    fn list_prompts(&self) -> Result<Vec<String>> {
+        self.inner.list_prompts()
+    }
+
+    fn delete_prompt(&self, name: &str) -> Result<()> {
+        self.inner.delete_prompt(name)
+    }
+
+    fn save_prompts(&self, entries: &[(String, String)]) -> Result<Vec<Result<()>>> {
+        let sealed = entries.iter()
+            .map(|(name, prompt)| Ok((name.clone(), self.encrypt(&format!("prompt '{}'", name), prompt)?)))
+            .collect::<Result<Vec<_>>>()?;
+        self.inner.save_prompts(&sealed)
+    }
+
+    fn delete_prompts(&self, names: &[String]) -> Result<Vec<Result<()>>> {
+        self.inner.delete_prompts(names)
+    }
+
+    fn prompt_exists(&self, name: &str) -> bool {
+        self.inner.prompt_exists(name)
+    }
+
+    fn get_prompt_info(&self, name: &str) -> Result<PromptInfo> {
+        self.plaintext_info(self.inner.get_prompt_info(name)?)
+    }
+
+    fn refresh_prompt_info(&self, name: &str) -> Result<PromptInfo> {
+        self.plaintext_info(self.inner.refresh_prompt_info(name)?)
+    }
+
+    fn set_content_type(&self, name: &str, content_type: &str) -> Result<()> {
+        self.inner.set_content_type(name, content_type)
+    }
+
+    fn swap_prompts(&self, a: &str, b: &str) -> Result<()> {
+        self.inner.swap_prompts(a, b)
+    }
+
+    fn rename_prompt(&self, old: &str, new: &str) -> Result<()> {
+        self.inner.rename_prompt(old, new)
+    }
+
+    fn copy_prompt(&self, source: &str, dest: &str, overwrite: bool) -> Result<()> {
+        self.inner.copy_prompt(source, dest, overwrite)
+    }
+
+    fn set_tags(&self, name: &str, tags: Vec<String>) -> Result<()> {
+        self.inner.set_tags(name, tags)
+    }
+
+    fn set_examples(&self, name: &str, examples: Vec<HashMap<String, String>>) -> Result<()> {
+        self.inner.set_examples(name, examples)
+    }
+
+    fn prune_history(&self, name: &str, keep: usize) -> Result<usize> {
+        self.inner.prune_history(name, keep)
+    }
+
+    fn history_size(&self, name: &str) -> Result<u64> {
+        self.inner.history_size(name)
+    }
+
+    fn list_versions(&self, name: &str) -> Result<Vec<u128>> {
+        self.inner.list_versions(name)
+    }
+
+    fn load_version(&self, name: &str, timestamp: u128) -> Result<String> {
+        let what = format!("version {} of prompt '{}'", timestamp, name);
+        self.decrypt(&what, self.inner.load_version(name, timestamp)?)
+    }
+
+    fn vacuum(&self) -> Result<usize> {
+        self.inner.vacuum()
+    }
+
+    // The inner backend could only search ciphertext
+    fn search_native(&self, _query: &SearchQuery) -> Option<Result<Vec<SearchHit>>> {
+        None
+    }
+
+    fn reconcile(&self) -> Result<ReconcileReport> {
+        self.inner.reconcile()
+    }
+
+    fn clean_temp_files(&self) -> Result<Vec<PathBuf>> {
+        self.inner.clean_temp_files()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::FileStorage;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_encrypted_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = EncryptedStorage::new(FileStorage::with_directory(temp_dir.path()).unwrap(), "correct horse").unwrap();
+
+        storage.save_prompt("secret", "Proprietary instructions").unwrap();
+        assert_eq!(storage.load_prompt("secret").unwrap(), "Proprietary instructions");
+        assert_eq!(storage.list_prompts().unwrap(), vec!["secret"]);
+
+        let on_disk = fs::read_to_string(temp_dir.path().join("secret.txt")).unwrap();
+        assert!(on_disk.starts_with(ENCRYPTED_PREFIX));
+        assert!(!on_disk.contains("Proprietary"));
+
+        // A fresh nonce per save means identical content never repeats on disk
+        storage.save_prompt("twin", "Proprietary instructions").unwrap();
+        assert_ne!(fs::read_to_string(temp_dir.path().join("twin.txt")).unwrap(), on_disk);
+
+        // The factory default was written before encryption and still loads
+        assert!(!storage.load_default().unwrap().is_empty());
+        storage.save_default("Encrypted default").unwrap();
+        assert_eq!(storage.load_default().unwrap(), "Encrypted default");
+
+        // Another instance with the same passphrase decrypts with the stored salt
+        let reopened = EncryptedStorage::new(FileStorage::with_directory(temp_dir.path()).unwrap(), "correct horse").unwrap();
+        assert_eq!(reopened.load_prompt("secret").unwrap(), "Proprietary instructions");
+    }
+
+    #[test]
+    fn test_wrong_passphrase_is_an_error() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = EncryptedStorage::new(FileStorage::with_directory(temp_dir.path()).unwrap(), "correct horse").unwrap();
+        storage.save_prompt("secret", "Proprietary instructions").unwrap();
+
+        let wrong = EncryptedStorage::new(FileStorage::with_directory(temp_dir.path()).unwrap(), "battery staple").unwrap();
+        let err = wrong.load_prompt("secret").unwrap_err();
+        assert!(matches!(err, PromptError::Encryption(ref msg) if msg.contains("wrong passphrase")), "{}", err);
+    }
+
+    #[test]
+    fn test_verify_integrity_over_encrypted_storage() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = EncryptedStorage::new(FileStorage::with_directory(temp_dir.path()).unwrap(), "correct horse").unwrap();
+        let mut manager = crate::PromptManager::with_storage(Box::new(storage), crate::Config::default());
+        manager.save_prompt("secret", "Hello world").unwrap();
+
+        let info = manager.get_prompt_info("secret").unwrap();
+        assert_eq!(info.size, 11);
+        assert_eq!(info.content_hash, Some(content_hash("Hello world")));
+        assert!(manager.verify_integrity().unwrap().is_empty());
+    }
+}
@@ -23,15 +23,40 @@ pub enum PromptError {
     #[error("Prompt not found: {0}")]
     PromptNotFound(String),
 
+    #[error("Prompt already exists: {0}")]
+    PromptAlreadyExists(String),
+
     #[error("Invalid prompt: {0}")]
     InvalidPrompt(String),
 
+    /// Prompt name that is empty, `.` or `..`, or would resolve outside the prompts directory
+    #[error("Invalid prompt name: {0}")]
+    InvalidName(String),
+
     #[error("Storage error: {0}")]
     Storage(String),
 
+    /// Encrypting or decrypting stored content failed, e.g. on a wrong passphrase
+    #[error("Encryption error: {0}")]
+    Encryption(String),
+
     #[error("Configuration error: {0}")]
     Config(String),
 
     #[error("Template validation error: {0}")]
     TemplateValidation(String),
+
+    /// Syntax error with its 1-based position, or 0,0 when unknown
+    #[error("Template syntax error at line {line}, column {column}: {message}")]
+    TemplateSyntax {
+        message: String,
+        line: usize,
+        column: usize,
+    },
+
+    #[error("Invalid variable names: {}", .0.join(", "))]
+    InvalidVariableNames(Vec<String>),
+
+    #[error("Constraint violation: {0}")]
+    ConstraintViolation(String),
 }
\ No newline at end of file
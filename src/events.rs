@@ -0,0 +1,125 @@
+//! Change notifications for prompts
+
+use std::sync::{Arc, Mutex, Weak};
+
+/// Callback invoked with each change event
+pub type ChangeCallback = Box<dyn Fn(&PromptChangeEvent) + Send + Sync>;
+
+/// A change to a stored prompt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromptChangeEvent {
+    /// Name of the affected prompt (the new name for renames)
+    pub name: String,
+    /// What happened to it
+    pub kind: ChangeKind,
+}
+
+impl PromptChangeEvent {
+    /// Check if this event affects the named prompt
+    pub fn affects(&self, name: &str) -> bool {
+        self.name == name || matches!(&self.kind, ChangeKind::Renamed { from } if from == name)
+    }
+}
+
+/// Kinds of prompt changes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// A new prompt was saved
+    Created,
+    /// An existing prompt's content changed
+    Modified,
+    /// A prompt was deleted
+    Deleted,
+    /// A prompt was renamed from another name
+    Renamed { from: String },
+    /// The default prompt file changed on disk (the event name is empty)
+    DefaultChanged,
+    /// The metadata file changed on disk (the event name is empty)
+    MetadataChanged,
+}
+
+struct Observer {
+    id: u64,
+    filter: Option<String>,
+    callback: ChangeCallback,
+}
+
+#[derive(Default)]
+struct Observers {
+    next_id: u64,
+    observers: Vec<Observer>,
+}
+
+/// Registry of change observers
+#[derive(Clone, Default)]
+pub struct ChangeNotifier {
+    inner: Arc<Mutex<Observers>>,
+}
+
+impl ChangeNotifier {
+    /// Create an empty notifier
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a callback, optionally limited to events affecting one prompt
+    pub fn subscribe(&self, filter: Option<&str>, callback: ChangeCallback) -> WatchHandle {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let id = inner.next_id;
+        inner.next_id += 1;
+        inner.observers.push(Observer {
+            id,
+            filter: filter.map(str::to_string),
+            callback,
+        });
+
+        WatchHandle {
+            id,
+            observers: Arc::downgrade(&self.inner),
+        }
+    }
+
+    /// Deliver an event to every matching observer
+    ///
+    /// Callbacks run while the registry is locked, so they must not
+    /// subscribe or drop handles themselves.
+    pub fn notify(&self, event: &PromptChangeEvent) {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        for observer in &inner.observers {
+            if observer.filter.as_deref().is_none_or(|name| event.affects(name)) {
+                (observer.callback)(event);
+            }
+        }
+    }
+
+    /// Number of registered observers
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner()).observers.len()
+    }
+
+    /// Check if no observers are registered
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Subscription handle; dropping it unsubscribes the callback
+#[must_use = "dropping the handle unsubscribes the callback"]
+pub struct WatchHandle {
+    id: u64,
+    observers: Weak<Mutex<Observers>>,
+}
+
+impl WatchHandle {
+    /// Stop receiving events
+    pub fn unsubscribe(self) {}
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        if let Some(observers) = self.observers.upgrade() {
+            let mut inner = observers.lock().unwrap_or_else(|e| e.into_inner());
+            inner.observers.retain(|observer| observer.id != self.id);
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! Exporting prompt libraries to other formats
+
+use std::time::SystemTime;
+
+/// Maximum number of characters in an export preview
+pub const PREVIEW_LENGTH: usize = 80;
+
+/// Single-line preview of prompt content
+pub fn preview(content: &str) -> String {
+    let flattened = content.split_whitespace().collect::<Vec<_>>().join(" ");
+    match flattened.char_indices().nth(PREVIEW_LENGTH) {
+        Some((end, _)) => format!("{}...", &flattened[..end]),
+        None => flattened,
+    }
+}
+
+/// Seconds since the Unix epoch for a timestamp
+pub fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Top-level layout of a JSON export
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExportShape {
+    /// `[{"id": ..., ...}, ...]`
+    #[default]
+    ArrayOfObjects,
+    /// `{"<name>": {...}, ...}`, without the id field in each object
+    Map,
+}
+
+/// Field names and shape for exporting prompts to another tool's JSON schema
+///
+/// Optional fields are omitted from the export when `None`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportMapping {
+    /// Array of objects or a map keyed by prompt name
+    pub shape: ExportShape,
+    /// Field holding the prompt name
+    pub id_field: String,
+    /// Field holding the prompt content
+    pub body_field: String,
+    /// Field repeating the prompt name as a display title
+    pub title_field: Option<String>,
+    /// Field holding the tags array
+    pub tags_field: Option<String>,
+    /// Field holding the size in bytes
+    pub size_field: Option<String>,
+    /// Field holding the creation time in Unix seconds
+    pub created_field: Option<String>,
+    /// Field holding the modification time in Unix seconds
+    pub modified_field: Option<String>,
+}
+
+impl Default for ExportMapping {
+    fn default() -> Self {
+        Self {
+            shape: ExportShape::default(),
+            id_field: "name".to_string(),
+            body_field: "content".to_string(),
+            title_field: None,
+            tags_field: None,
+            size_field: None,
+            created_field: None,
+            modified_field: None,
+        }
+    }
+}
+
+impl ExportMapping {
+    /// Build the JSON export for prompts paired with their content
+    pub fn apply(&self, prompts: &[(crate::storage::PromptInfo, String)]) -> serde_json::Value {
+        use serde_json::{Map, Value};
+
+        let object_for = |info: &crate::storage::PromptInfo, content: &str| {
+            let mut object = Map::new();
+            if self.shape == ExportShape::ArrayOfObjects {
+                object.insert(self.id_field.clone(), Value::from(info.name.as_str()));
+            }
+            if let Some(field) = &self.title_field {
+                object.insert(field.clone(), Value::from(info.name.as_str()));
+            }
+            object.insert(self.body_field.clone(), Value::from(content));
+            if let Some(field) = &self.tags_field {
+                object.insert(field.clone(), Value::from(info.tags.clone()));
+            }
+            if let Some(field) = &self.size_field {
+                object.insert(field.clone(), Value::from(info.size));
+            }
+            if let Some(field) = &self.created_field {
+                object.insert(field.clone(), Value::from(unix_seconds(info.created_at)));
+            }
+            if let Some(field) = &self.modified_field {
+                object.insert(field.clone(), Value::from(unix_seconds(info.modified_at)));
+            }
+            Value::Object(object)
+        };
+
+        match self.shape {
+            ExportShape::ArrayOfObjects => prompts
+                .iter()
+                .map(|(info, content)| object_for(info, content))
+                .collect(),
+            ExportShape::Map => prompts
+                .iter()
+                .map(|(info, content)| (info.name.clone(), object_for(info, content)))
+                .collect::<Map<_, _>>()
+                .into(),
+        }
+    }
+}
+
+/// Write prompt metadata and previews as CSV with a header row
+///
+/// Tags are joined with `;` and timestamps are Unix seconds.
+#[cfg(feature = "csv")]
+pub fn metadata_csv(rows: &[(crate::storage::PromptInfo, String)]) -> crate::error::Result<String> {
+    use crate::error::PromptError;
+
+    let to_error = |e: csv::Error| PromptError::Storage(format!("Failed to write CSV: {}", e));
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer
+        .write_record(["name", "size", "tags", "created_at", "modified_at", "preview"])
+        .map_err(to_error)?;
+
+    for (info, preview) in rows {
+        writer
+            .write_record([
+                info.name.clone(),
+                info.size.to_string(),
+                info.tags.join(";"),
+                unix_seconds(info.created_at).to_string(),
+                unix_seconds(info.modified_at).to_string(),
+                preview.clone(),
+            ])
+            .map_err(to_error)?;
+    }
+
+    let bytes = writer
+        .into_inner()
+        .map_err(|e| PromptError::Storage(format!("Failed to write CSV: {}", e)))?;
+    String::from_utf8(bytes).map_err(|e| PromptError::Storage(format!("CSV is not valid UTF-8: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preview_truncates_and_flattens() {
+        assert_eq!(preview("Line one\n  line two"), "Line one line two");
+
+        let long = "word ".repeat(40);
+        let short = preview(&long);
+        assert_eq!(short.chars().count(), PREVIEW_LENGTH + 3);
+        assert!(short.ends_with("..."));
+    }
+}
@@ -0,0 +1,79 @@
+//! Source formats for stored prompts
+
+use serde::Serialize;
+
+/// Format a prompt is authored in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptFormat {
+    /// Plain text, delivered as-is
+    #[default]
+    PlainText,
+    /// Markdown, stripped to plain text when the `markdown` feature is enabled
+    Markdown,
+}
+
+impl PromptFormat {
+    /// Convert prompt content in this format to plain text
+    pub fn to_plain_text(self, text: &str) -> String {
+        match self {
+            Self::PlainText => text.to_string(),
+            #[cfg(feature = "markdown")]
+            Self::Markdown => strip_markdown(text),
+            #[cfg(not(feature = "markdown"))]
+            Self::Markdown => {
+                tracing::debug!("Markdown support not enabled, returning prompt unchanged");
+                text.to_string()
+            }
+        }
+    }
+}
+
+/// Strip Markdown syntax, keeping the text and paragraph structure
+#[cfg(feature = "markdown")]
+pub fn strip_markdown(text: &str) -> String {
+    use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+
+    let mut output = String::new();
+    for event in Parser::new(text) {
+        match event {
+            Event::Text(text) | Event::Code(text) => output.push_str(&text),
+            Event::SoftBreak | Event::HardBreak => output.push('\n'),
+            Event::Start(Tag::Item) => output.push_str("- "),
+            Event::End(TagEnd::Item) => output.push('\n'),
+            Event::End(TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::CodeBlock | TagEnd::List(_)) => {
+                output.push_str("\n\n")
+            }
+            _ => {}
+        }
+    }
+
+    // Collapse runs of blank lines left by nested blocks
+    let mut collapsed = String::new();
+    for line in output.trim().lines() {
+        if line.trim().is_empty() && collapsed.ends_with("\n\n") {
+            continue;
+        }
+        collapsed.push_str(line);
+        collapsed.push('\n');
+    }
+    collapsed.trim_end().to_string()
+}
+
+#[cfg(all(test, feature = "markdown"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_markdown() {
+        let markdown = "# Role\n\nYou are **helpful** and *kind*.\n\n## Rules\n\n- Be `concise`\n- Be accurate";
+        let plain = PromptFormat::Markdown.to_plain_text(markdown);
+        assert_eq!(plain, "Role\n\nYou are helpful and kind.\n\nRules\n\n- Be concise\n- Be accurate");
+    }
+
+    #[test]
+    fn test_plain_text_unchanged() {
+        let text = "# Not a heading to strip\n\n**kept**";
+        assert_eq!(PromptFormat::PlainText.to_plain_text(text), text);
+    }
+}
@@ -0,0 +1,89 @@
+//! Importing prompts from outside the library
+
+use crate::error::{PromptError, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Outcome of a bulk import
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    /// Prompts saved to the library
+    pub imported: Vec<String>,
+    /// Prompts left alone because they already exist
+    pub skipped: Vec<String>,
+    /// Prompts that could not be imported, with the reason
+    pub failed: Vec<(String, String)>,
+    /// Helpers the imported prompts use that aren't registered
+    pub missing_helpers: Vec<String>,
+}
+
+impl ImportReport {
+    /// Check if every prompt was imported or skipped without error
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// Find every `*.txt` file under a directory, paired with its prompt name
+///
+/// Files in nested directories are namespaced by their relative path, so
+/// `team/review.txt` becomes `team/review`. Results are sorted by name.
+pub fn text_files(dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let mut files = Vec::new();
+    collect_text_files(dir, "", &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+fn collect_text_files(dir: &Path, namespace: &str, files: &mut Vec<(String, PathBuf)>) -> Result<()> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| PromptError::Storage(format!("Failed to read {}: {}", dir.display(), e)))?;
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            // Directory names are used whole, so `team.v2` stays distinct from `team.v3`
+            let Some(dir_name) = path.file_name().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            collect_text_files(&path, &format!("{}{}/", namespace, dir_name), files)?;
+        } else if path.extension().is_some_and(|ext| ext == "txt") {
+            let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            files.push((format!("{}{}", namespace, stem), path));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_text_files_namespaces_nested_dirs() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir_all(temp_dir.path().join("team").join("qa")).unwrap();
+        fs::write(temp_dir.path().join("root.txt"), "a").unwrap();
+        fs::write(temp_dir.path().join("notes.md"), "b").unwrap();
+        fs::write(temp_dir.path().join("team").join("qa").join("triage.txt"), "c").unwrap();
+
+        let names: Vec<String> = text_files(temp_dir.path()).unwrap().into_iter().map(|(n, _)| n).collect();
+        assert_eq!(names, vec!["root", "team/qa/triage"]);
+    }
+
+    #[test]
+    fn test_text_files_keeps_dotted_dir_names() {
+        let temp_dir = TempDir::new().unwrap();
+        for dir in ["team.v2", "team.v3"] {
+            fs::create_dir(temp_dir.path().join(dir)).unwrap();
+            fs::write(temp_dir.path().join(dir).join("review.txt"), dir).unwrap();
+        }
+
+        let names: Vec<String> = text_files(temp_dir.path()).unwrap().into_iter().map(|(n, _)| n).collect();
+        assert_eq!(names, vec!["team.v2/review", "team.v3/review"]);
+    }
+}
@@ -0,0 +1,225 @@
+//! Resolving `{{> name}}` includes and `{{embed "name"}}` tags against stored prompts
+
+use crate::error::{PromptError, Result};
+use std::collections::HashMap;
+
+/// Prefix of the variables that carry embedded content into a render
+const EMBED_VARIABLE_PREFIX: &str = "__embed_";
+
+/// A prompt or partial resolved while rendering
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEntry {
+    /// Name of the stored prompt
+    pub name: String,
+    /// Include depth, with the rendered prompt at 0
+    pub depth: usize,
+}
+
+/// Every prompt and partial that contributed to a render, in resolution order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RenderTrace {
+    /// Resolved prompts in the order they were reached
+    pub entries: Vec<TraceEntry>,
+}
+
+impl RenderTrace {
+    /// Names of the resolved prompts in order
+    pub fn names(&self) -> Vec<&str> {
+        self.entries.iter().map(|entry| entry.name.as_str()).collect()
+    }
+}
+
+/// Inline `{{> name}}` includes, recording each resolved prompt in the trace
+///
+/// `resolve` maps an include name to the stored prompt's name and content,
+/// or `None` to leave the tag for handlebars to resolve. `root` is the name
+/// of the prompt being expanded and is recorded at depth 0. Cyclic includes
+/// are an error.
+pub fn expand<F>(root: &str, template: &str, resolve: &mut F, trace: &mut RenderTrace) -> Result<String>
+where
+    F: FnMut(&str) -> Result<Option<(String, String)>>,
+{
+    let mut stack = Vec::new();
+    expand_at(root, template, 0, resolve, trace, &mut stack)
+}
+
+fn expand_at<F>(
+    name: &str,
+    template: &str,
+    depth: usize,
+    resolve: &mut F,
+    trace: &mut RenderTrace,
+    stack: &mut Vec<String>,
+) -> Result<String>
+where
+    F: FnMut(&str) -> Result<Option<(String, String)>>,
+{
+    if stack.iter().any(|seen| seen == name) {
+        stack.push(name.to_string());
+        return Err(PromptError::InvalidPrompt(format!("Cyclic include: {}", stack.join(" -> "))));
+    }
+    stack.push(name.to_string());
+    trace.entries.push(TraceEntry { name: name.to_string(), depth });
+
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{>") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        expanded.push_str(&rest[..start]);
+        let tag = &rest[start..start + end + 2];
+        let include = tag[3..tag.len() - 2].trim();
+
+        match resolve(include)? {
+            Some((resolved, content)) => {
+                expanded.push_str(&expand_at(&resolved, &content, depth + 1, resolve, trace, stack)?);
+            }
+            None => expanded.push_str(tag),
+        }
+        rest = &rest[start + end + 2..];
+    }
+    expanded.push_str(rest);
+
+    stack.pop();
+    Ok(expanded)
+}
+
+/// Reject caller variables that use the prefix reserved for embedded content
+///
+/// Otherwise a caller's `__embed_0` and an embed's generated variable would
+/// overwrite each other, depending on which was inserted last.
+pub fn check_reserved_variables(variables: &HashMap<String, String>) -> Result<()> {
+    let mut reserved: Vec<String> = variables.keys()
+        .filter(|name| name.starts_with(EMBED_VARIABLE_PREFIX))
+        .cloned()
+        .collect();
+    if reserved.is_empty() {
+        return Ok(());
+    }
+    reserved.sort();
+    Err(PromptError::InvalidVariableNames(reserved))
+}
+
+/// Replace `{{embed "name"}}` tags with the raw content of the named prompts
+///
+/// Each tag becomes a triple-stash reference to a generated variable whose
+/// value is the embedded content, so handlebars inserts it verbatim without
+/// interpreting it. Embeds inside embedded content are expanded in place.
+/// Returns the rewritten template and the variables to render it with;
+/// cyclic embeds are an error.
+pub fn expand_embeds<F>(root: &str, template: &str, load: &mut F) -> Result<(String, HashMap<String, String>)>
+where
+    F: FnMut(&str) -> Result<String>,
+{
+    let mut stack = vec![root.to_string()];
+    let mut variables = HashMap::new();
+    let expanded = replace_embeds(template, |name| {
+        let content = embedded_content(name, load, &mut stack)?;
+        let variable = format!("{}{}", EMBED_VARIABLE_PREFIX, variables.len());
+        variables.insert(variable.clone(), content);
+        Ok(format!("{{{{{{{}}}}}}}", variable))
+    })?;
+    Ok((expanded, variables))
+}
+
+fn embedded_content<F>(name: &str, load: &mut F, stack: &mut Vec<String>) -> Result<String>
+where
+    F: FnMut(&str) -> Result<String>,
+{
+    if stack.iter().any(|seen| seen == name) {
+        stack.push(name.to_string());
+        return Err(PromptError::InvalidPrompt(format!("Cyclic embed: {}", stack.join(" -> "))));
+    }
+    stack.push(name.to_string());
+    let content = load(name)?;
+    let expanded = replace_embeds(&content, |nested| embedded_content(nested, load, stack))?;
+    stack.pop();
+    Ok(expanded)
+}
+
+/// Replace each well-formed `{{embed "name"}}` tag with the result of `replace`
+fn replace_embeds<F>(template: &str, mut replace: F) -> Result<String>
+where
+    F: FnMut(&str) -> Result<String>,
+{
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{embed") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        let tag = &rest[start..start + end + 2];
+        let argument = &tag["{{embed".len()..tag.len() - 2];
+        let name = argument
+            .trim()
+            .strip_prefix('"')
+            .and_then(|quoted| quoted.strip_suffix('"'))
+            .filter(|_| argument.starts_with(char::is_whitespace));
+
+        expanded.push_str(&rest[..start]);
+        match name {
+            Some(name) => expanded.push_str(&replace(name)?),
+            None => expanded.push_str(tag),
+        }
+        rest = &rest[start + end + 2..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolver(prompts: &HashMap<&str, &str>) -> impl FnMut(&str) -> Result<Option<(String, String)>> {
+        move |name| Ok(prompts.get(name).map(|content| (name.to_string(), content.to_string())))
+    }
+
+    #[test]
+    fn test_expand_nested_includes() {
+        let prompts = HashMap::from([("a", "A[{{> b}}]"), ("b", "B"), ("c", "C{{> b}}")]);
+        let mut trace = RenderTrace::default();
+
+        let expanded = expand("root", "{{> a}} {{> c}} {{> unknown}}", &mut resolver(&prompts), &mut trace).unwrap();
+        assert_eq!(expanded, "A[B] CB {{> unknown}}");
+        assert_eq!(trace.names(), vec!["root", "a", "b", "c", "b"]);
+        assert_eq!(trace.entries.iter().map(|e| e.depth).collect::<Vec<_>>(), vec![0, 1, 2, 1, 2]);
+    }
+
+    #[test]
+    fn test_expand_detects_cycles() {
+        let prompts = HashMap::from([("a", "{{> b}}"), ("b", "{{> a}}")]);
+        let err = expand("a", "{{> b}}", &mut resolver(&prompts), &mut RenderTrace::default()).unwrap_err();
+        assert!(err.to_string().contains("a -> b -> a"));
+    }
+
+    #[test]
+    fn test_expand_embeds_keeps_content_raw() {
+        let prompts = HashMap::from([("examples", "Use {{x}} [{{embed \"inner\"}}]"), ("inner", "{{#if y}}")]);
+        let mut load = |name: &str| Ok(prompts[name].to_string());
+
+        let (expanded, variables) = expand_embeds("root", "A {{embed \"examples\"}} {{embedded}}", &mut load).unwrap();
+        assert_eq!(expanded, "A {{{__embed_0}}} {{embedded}}");
+        assert_eq!(variables["__embed_0"], "Use {{x}} [{{#if y}}]");
+    }
+
+    #[test]
+    fn test_expand_embeds_detects_cycles() {
+        let prompts = HashMap::from([("a", "{{embed \"b\"}}"), ("b", "{{embed \"a\"}}")]);
+        let mut load = |name: &str| Ok(prompts[name].to_string());
+        let err = expand_embeds("a", "{{embed \"b\"}}", &mut load).unwrap_err();
+        assert!(err.to_string().contains("a -> b -> a"));
+    }
+
+    #[test]
+    fn test_reserved_embed_variables_rejected() {
+        let mut variables = HashMap::new();
+        variables.insert("name".to_string(), "Ada".to_string());
+        assert!(check_reserved_variables(&variables).is_ok());
+
+        variables.insert("__embed_0".to_string(), "Spoofed".to_string());
+        let err = check_reserved_variables(&variables).unwrap_err();
+        assert!(matches!(err, PromptError::InvalidVariableNames(ref names) if names == &["__embed_0"]));
+    }
+}
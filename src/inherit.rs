@@ -0,0 +1,98 @@
+//! Prompt inheritance with overridable named blocks
+//!
+//! A base prompt marks overridable sections with
+//! `{{#block "name"}}default{{/block}}`. A child prompt supplies
+//! replacements with `{{#override "name"}}...{{/override}}`; blocks the
+//! child doesn't override keep their defaults. Blocks of the same kind
+//! cannot be nested.
+
+use crate::error::{PromptError, Result};
+use std::collections::HashMap;
+
+/// A named section found in a prompt
+struct Section<'a> {
+    name: &'a str,
+    /// Byte range of the whole section including its tags
+    outer: std::ops::Range<usize>,
+    /// Content between the opening and closing tags
+    inner: &'a str,
+}
+
+/// Find every `{{#tag "name"}}...{{/tag}}` section in text
+fn find_sections<'a>(text: &'a str, tag: &str) -> Result<Vec<Section<'a>>> {
+    let open = format!("{{{{#{} ", tag);
+    let close = format!("{{{{/{}}}}}", tag);
+    let mut sections = Vec::new();
+    let mut pos = 0;
+
+    while let Some(offset) = text[pos..].find(&open) {
+        let start = pos + offset;
+        let name_start = start + open.len();
+        let tag_end = text[name_start..]
+            .find("}}")
+            .map(|i| name_start + i)
+            .ok_or_else(|| PromptError::TemplateValidation(format!("Unterminated {} tag", tag)))?;
+        let name = text[name_start..tag_end].trim().trim_matches(|c| c == '"' || c == '\'');
+        if name.is_empty() {
+            return Err(PromptError::TemplateValidation(format!("{} tag is missing a name", tag)));
+        }
+
+        let inner_start = tag_end + 2;
+        let inner_end = text[inner_start..]
+            .find(&close)
+            .map(|i| inner_start + i)
+            .ok_or_else(|| PromptError::TemplateValidation(format!("Unclosed {} \"{}\"", tag, name)))?;
+        let end = inner_end + close.len();
+
+        sections.push(Section {
+            name,
+            outer: start..end,
+            inner: &text[inner_start..inner_end],
+        });
+        pos = end;
+    }
+
+    Ok(sections)
+}
+
+/// Apply a child's overrides to a base prompt's blocks
+///
+/// Returns the base with every block replaced by the child's override or,
+/// when not overridden, by the block's default content.
+pub fn extend(base: &str, child: &str) -> Result<String> {
+    let overrides: HashMap<&str, &str> = find_sections(child, "override")?
+        .into_iter()
+        .map(|section| (section.name, section.inner))
+        .collect();
+
+    let mut output = String::with_capacity(base.len());
+    let mut pos = 0;
+    for block in find_sections(base, "block")? {
+        output.push_str(&base[pos..block.outer.start]);
+        output.push_str(overrides.get(block.name).copied().unwrap_or(block.inner));
+        pos = block.outer.end;
+    }
+    output.push_str(&base[pos..]);
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extend_overrides_and_inherits() {
+        let base = "Intro.\n{{#block \"rules\"}}Base rules.{{/block}}\n{{#block \"tone\"}}Be formal.{{/block}}";
+        let child = "{{#override \"rules\"}}Child rules.{{/override}}";
+
+        let extended = extend(base, child).unwrap();
+        assert_eq!(extended, "Intro.\nChild rules.\nBe formal.");
+    }
+
+    #[test]
+    fn test_unclosed_block_is_error() {
+        let result = extend("{{#block \"rules\"}}never closed", "");
+        assert!(matches!(result, Err(PromptError::TemplateValidation(_))));
+    }
+}
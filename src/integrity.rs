@@ -0,0 +1,45 @@
+//! Integrity checks for stored prompts
+
+use std::time::SystemTime;
+
+/// A problem found while verifying stored prompts against their metadata
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntegrityIssue {
+    /// Name of the affected prompt
+    pub name: String,
+    /// What kind of mismatch was found
+    pub kind: IntegrityIssueKind,
+}
+
+/// Kinds of integrity problems
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntegrityIssueKind {
+    /// Content hash differs from the hash recorded in metadata
+    HashMismatch { expected: String, actual: String },
+    /// Content size differs from the size recorded in metadata
+    SizeMismatch { expected: u64, actual: u64 },
+    /// Metadata exists but the prompt content could not be loaded
+    Missing,
+}
+
+/// A difference between `FileStorage` metadata and the files on disk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Discrepancy {
+    /// Name of the affected prompt (the file stem for untracked files)
+    pub name: String,
+    /// What kind of mismatch was found
+    pub kind: DiscrepancyKind,
+}
+
+/// Kinds of metadata discrepancies
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiscrepancyKind {
+    /// File size differs from the size recorded in metadata
+    SizeMismatch { recorded: u64, actual: u64 },
+    /// File modification time differs from the time recorded in metadata
+    ModifiedMismatch { recorded: SystemTime, actual: SystemTime },
+    /// Metadata has an entry but the file is gone
+    MissingFile,
+    /// A prompt file exists without a metadata entry
+    UntrackedFile,
+}
@@ -40,31 +40,206 @@
 //! ```
 
 pub mod storage;
+#[cfg(feature = "tokio")]
+pub mod async_storage;
+#[cfg(feature = "tokio")]
+pub mod async_manager;
 pub mod template;
 pub mod error;
+pub mod batch;
+pub mod bundle;
+pub mod constraint;
+#[cfg(feature = "encryption")]
+pub mod encryption;
+pub mod events;
+pub mod export;
+pub mod format;
+pub mod import;
+pub mod include;
+pub mod inherit;
+pub mod integrity;
+pub mod lint;
+pub mod messages;
+pub mod search;
+pub mod shared;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod summary;
+pub mod sync;
+pub mod tokens;
+pub mod transform;
+#[cfg(feature = "notify")]
+mod watch;
 
 use crate::error::{PromptError, Result};
 use crate::storage::PromptStorage;
 use crate::template::TemplateEngine;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use tracing::{debug, info, warn};
+
+/// Namespace prefix for prompts registered as template partials
+const PARTIALS_NAMESPACE: &str = "partials/";
+
+/// Default prompt written to new storage unless `Config::factory_default` overrides it
+pub const FACTORY_DEFAULT_PROMPT: &str = r#"You are Claude, an AI assistant created by Anthropic. You are helpful, harmless, and honest.
+
+When helping with code:
+- Provide clear, concise explanations
+- Follow best practices and conventions
+- Consider security and performance implications
+- Test your suggestions when possible
+
+When helping with general tasks:
+- Be direct and actionable
+- Ask clarifying questions when needed
+- Provide step-by-step guidance for complex tasks
+- Acknowledge limitations or uncertainties"#;
+
+/// Environment variable prefix consulted by `render_template_with_env`
+pub const ENV_VAR_PREFIX: &str = "GAMECODE_VAR_";
+
+/// Group name for prompts without tags in [`PromptManager::group_by_tag`]
+pub const UNTAGGED_GROUP: &str = "(untagged)";
 
 /// Configuration for prompt management
-#[derive(Debug, Clone)]
+///
+/// Serializes to JSON for diagnostics; see [`PromptManager::config_json`].
+/// Fields holding secrets must be marked `#[serde(skip_serializing)]` so
+/// they never end up in a dump.
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct Config {
-    /// Custom storage directory (uses default if None)
+    /// Custom storage directory (takes precedence over `storage_base`)
     pub storage_dir: Option<std::path::PathBuf>,
+    /// Base location used when `storage_dir` is None
+    pub storage_base: StorageBase,
     /// Enable template validation
     pub validate_templates: bool,
     /// Maximum prompt length in characters
     pub max_prompt_length: usize,
+    /// Maximum prompt length in estimated tokens; see [`PromptManager::estimate_tokens`]
+    pub max_prompt_tokens: Option<usize>,
+    /// Minimum prompt length in characters (0 disables)
+    pub min_prompt_length: usize,
+    /// Substrings prompts must not contain, matched case-insensitively
+    pub forbidden_substrings: Vec<String>,
+    /// Format the default prompt is authored in
+    pub default_format: PromptFormat,
+    /// Create missing parent directories of the storage directory
+    pub create_parents: bool,
+    /// Factory default prompt (uses the built-in one if None)
+    pub factory_default: Option<String>,
+    /// Keep compressed copies of overwritten prompts in `history/`
+    pub keep_history: bool,
+    /// Keep at most this many previous versions of each prompt in `versions/` (0 disables)
+    ///
+    /// Independent of `keep_history`; only `versions/` is pruned.
+    pub keep_versions: usize,
+    /// Strategy for mapping prompt names to file names
+    pub name_sanitizer: NameSanitizer,
+    /// Write the default prompt on first access instead of at construction
+    pub lazy_init: bool,
+    /// File extension for prompt files, e.g. `"md"` (existing `.txt` files still load)
+    pub prompt_extension: String,
+    /// Separator splitting prompt names into subdirectories, e.g. `Some('/')`
+    /// stores `alice/coding` as `alice/coding.txt`
+    pub namespace_separator: Option<char>,
+    /// Keep prompt content in memory so repeated loads skip disk
+    pub shared_cache: bool,
+    /// Fail renders that reference an undefined variable instead of rendering it empty
+    pub strict_variables: bool,
+    /// Stat prompt files in `get_prompt_info` instead of trusting cached metadata
+    pub refresh_info_from_disk: bool,
+    /// Line prefix marking a role header in `render_as_messages`, e.g. `### user`
+    pub message_header_prefix: String,
+}
+
+impl Config {
+    /// Get the directory file storage lives in
+    pub(crate) fn resolved_storage_dir(&self) -> Result<std::path::PathBuf> {
+        match &self.storage_dir {
+            Some(dir) => Ok(dir.clone()),
+            None => self.storage_base.resolve(),
+        }
+    }
+    
+    /// Get the file storage settings from this config
+    pub(crate) fn file_storage_options(&self) -> FileStorageOptions {
+        FileStorageOptions {
+            create_parents: self.create_parents,
+            factory_default: self.factory_default.clone(),
+            keep_history: self.keep_history,
+            keep_versions: self.keep_versions,
+            name_sanitizer: self.name_sanitizer,
+            lazy_init: self.lazy_init,
+            extension: self.prompt_extension.clone(),
+            namespace_separator: self.namespace_separator,
+        }
+    }
+    
+    /// Check a prompt against the configured validation rules
+    pub(crate) fn validate_prompt(&self, prompt: &str) -> Result<()> {
+        if prompt.trim().is_empty() {
+            return Err(PromptError::InvalidPrompt("Prompt cannot be empty".to_string()));
+        }
+        
+        if prompt.len() > self.max_prompt_length {
+            return Err(PromptError::InvalidPrompt(
+                format!("Prompt exceeds maximum length of {} characters", self.max_prompt_length)
+            ));
+        }
+        
+        if let Some(max_tokens) = self.max_prompt_tokens {
+            let tokens = crate::tokens::estimate_tokens(prompt);
+            if tokens > max_tokens {
+                return Err(PromptError::InvalidPrompt(
+                    format!("Prompt exceeds maximum of {} tokens (estimated {})", max_tokens, tokens)
+                ));
+            }
+        }
+        
+        if prompt.chars().count() < self.min_prompt_length {
+            return Err(PromptError::InvalidPrompt(
+                format!("Prompt is shorter than minimum length of {} characters", self.min_prompt_length)
+            ));
+        }
+        
+        let lowered = prompt.to_lowercase();
+        if let Some(forbidden) = self.forbidden_substrings.iter()
+            .find(|forbidden| !forbidden.is_empty() && lowered.contains(&forbidden.to_lowercase()))
+        {
+            return Err(PromptError::InvalidPrompt(
+                format!("Prompt contains forbidden substring '{}'", forbidden)
+            ));
+        }
+        
+        Ok(())
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             storage_dir: None,
+            storage_base: StorageBase::default(),
             validate_templates: true,
             max_prompt_length: 10000,
+            max_prompt_tokens: None,
+            min_prompt_length: 0,
+            forbidden_substrings: Vec::new(),
+            default_format: PromptFormat::default(),
+            create_parents: true,
+            factory_default: None,
+            keep_history: false,
+            keep_versions: 0,
+            name_sanitizer: NameSanitizer::default(),
+            lazy_init: false,
+            prompt_extension: "txt".to_string(),
+            namespace_separator: None,
+            shared_cache: false,
+            strict_variables: false,
+            refresh_info_from_disk: false,
+            message_header_prefix: "### ".to_string(),
         }
     }
 }
@@ -73,6 +248,16 @@ impl Default for Config {
 pub struct PromptManager {
     storage: Box<dyn PromptStorage>,
     template_engine: TemplateEngine,
+    transform_pipeline: TransformPipeline,
+    save_transform: Option<SaveTransform>,
+    variable_constraints: HashMap<String, HashMap<String, Constraint>>,
+    notifier: ChangeNotifier,
+    token_cache: Mutex<HashMap<String, usize>>,
+    working: Option<String>,
+    prompt_cache: Option<std::sync::Arc<crate::shared::PromptCache>>,
+    /// Whether `prompt_cache` belongs to a `SharedState`
+    cache_from_state: bool,
+    active_profile: Option<String>,
     config: Config,
 }
 
@@ -85,38 +270,328 @@ impl PromptManager {
     
     /// Create a new prompt manager with custom configuration
     pub fn with_config(config: Config) -> Result<Self> {
-        let storage = match &config.storage_dir {
-            Some(dir) => crate::storage::FileStorage::with_directory(dir)?,
-            None => crate::storage::FileStorage::new()?,
-        };
+        let storage = crate::storage::FileStorage::with_options(config.resolved_storage_dir()?, config.file_storage_options())?;
         
-        Ok(Self {
-            storage: Box::new(storage),
-            template_engine: TemplateEngine::new(),
+        Ok(Self::with_storage(Box::new(storage), config))
+    }
+    
+    /// Create a prompt manager over a custom storage backend
+    ///
+    /// The storage settings in `config` are ignored; the backend is used
+    /// as-is.
+    pub fn with_storage(storage: Box<dyn PromptStorage>, config: Config) -> Self {
+        let mut template_engine = TemplateEngine::new();
+        template_engine.set_strict_variables(config.strict_variables);
+        Self {
+            storage,
+            template_engine,
+            transform_pipeline: TransformPipeline::new(),
+            save_transform: None,
+            variable_constraints: HashMap::new(),
+            notifier: ChangeNotifier::new(),
+            token_cache: Mutex::new(HashMap::new()),
+            working: None,
+            prompt_cache: config.shared_cache.then(Default::default),
+            cache_from_state: false,
+            active_profile: None,
             config,
-        })
+        }
+    }
+    
+    /// Create a manager that can be cloned and shared across threads
+    ///
+    /// Set `Config::shared_cache` to serve repeated loads from memory.
+    pub fn shared(config: Config) -> Result<SharedPromptManager> {
+        Ok(SharedPromptManager::new(Self::with_config(config)?))
+    }
+    
+    /// Create a manager whose prompt cache is shared with others built from `state`
+    ///
+    /// The cache is enabled regardless of `Config::shared_cache`, so
+    /// managers created separately, even on other threads, see each
+    /// other's writes without re-reading storage.
+    pub fn with_shared_state(config: Config, state: &SharedState) -> Result<Self> {
+        let config = Config { shared_cache: true, ..config };
+        let mut manager = Self::with_config(config)?;
+        manager.prompt_cache = Some(state.cache.clone());
+        manager.cache_from_state = true;
+        Ok(manager)
+    }
+    
+    /// Apply a new configuration without rebuilding the manager
+    ///
+    /// Registered helpers, partials, transforms, constraints, observers,
+    /// and the working buffer are preserved. Validation settings,
+    /// `strict_variables`, `refresh_info_from_disk`, and `default_format` take
+    /// effect immediately, and toggling `shared_cache` starts or drops the
+    /// in-memory prompt cache. A manager built with
+    /// [`with_shared_state`](Self::with_shared_state) stays attached to that
+    /// state's cache, so turning `shared_cache` off is a configuration error.
+    ///
+    /// Storage settings (`storage_dir`, `storage_base`, `create_parents`,
+    /// `factory_default`, `keep_history`, `keep_versions`, `name_sanitizer`,
+    /// `lazy_init`, `prompt_extension`, and `namespace_separator`) are fixed when the storage is
+    /// opened; changing any of them requires a new manager and returns a
+    /// configuration error, leaving the current config in place.
+    pub fn apply_config(&mut self, config: Config) -> Result<()> {
+        if let Some(field) = self.changed_storage_setting(&config) {
+            return Err(PromptError::Config(format!(
+                "Changing '{}' requires recreating the PromptManager",
+                field
+            )));
+        }
+        
+        if self.cache_from_state && !config.shared_cache {
+            return Err(PromptError::Config(
+                "Cannot disable 'shared_cache' on a manager built with a SharedState".to_string()
+            ));
+        }
+        if config.shared_cache != self.config.shared_cache {
+            self.prompt_cache = config.shared_cache.then(Default::default);
+        }
+        self.template_engine.set_strict_variables(config.strict_variables);
+        self.config = config;
+        info!("Applied updated configuration");
+        Ok(())
+    }
+    
+    /// Serialize the active config as pretty JSON for diagnostics
+    ///
+    /// `storage_dir` is filled in with the directory resolved from
+    /// `storage_base` when it isn't set explicitly.
+    pub fn config_json(&self) -> Result<String> {
+        let mut config = self.config.clone();
+        if config.storage_dir.is_none() {
+            config.storage_dir = Some(config.storage_base.resolve()?);
+        }
+        Ok(serde_json::to_string_pretty(&config)?)
+    }
+    
+    /// Name the first storage setting that differs from the active config
+    fn changed_storage_setting(&self, config: &Config) -> Option<&'static str> {
+        let current = &self.config;
+        if config.storage_dir != current.storage_dir {
+            Some("storage_dir")
+        } else if config.storage_base != current.storage_base {
+            Some("storage_base")
+        } else if config.create_parents != current.create_parents {
+            Some("create_parents")
+        } else if config.factory_default != current.factory_default {
+            Some("factory_default")
+        } else if config.keep_history != current.keep_history {
+            Some("keep_history")
+        } else if config.keep_versions != current.keep_versions {
+            Some("keep_versions")
+        } else if config.name_sanitizer != current.name_sanitizer {
+            Some("name_sanitizer")
+        } else if config.lazy_init != current.lazy_init {
+            Some("lazy_init")
+        } else if config.prompt_extension != current.prompt_extension {
+            Some("prompt_extension")
+        } else if config.namespace_separator != current.namespace_separator {
+            Some("namespace_separator")
+        } else {
+            None
+        }
     }
     
-    /// Load the default system prompt
+    /// Load the default system prompt, converted from its configured format
+    ///
+    /// With an active profile that has its own default, that default is
+    /// returned instead of the base one.
     pub fn load_default(&self) -> Result<String> {
-        self.storage.load_default()
+        let profile_default = match &self.active_profile {
+            Some(profile) => self.storage.load_profile_default(profile)?,
+            None => None,
+        };
+        let prompt = match profile_default {
+            Some(prompt) => prompt,
+            None => self.storage.load_default()?,
+        };
+        Ok(self.config.default_format.to_plain_text(&prompt))
+    }
+    
+    /// Select the profile whose default [`load_default`](Self::load_default) returns
+    ///
+    /// Profiles without a stored default fall back to the base default.
+    /// Profile names may contain ASCII letters, digits, `-`, and `_`.
+    pub fn set_active_profile(&mut self, profile: &str) -> Result<()> {
+        validate_profile_name(profile)?;
+        self.active_profile = Some(profile.to_string());
+        info!("Switched to profile '{}'", profile);
+        Ok(())
+    }
+    
+    /// Go back to using the base default prompt
+    pub fn clear_active_profile(&mut self) {
+        self.active_profile = None;
+    }
+    
+    /// Get the active profile, if one is selected
+    pub fn active_profile(&self) -> Option<&str> {
+        self.active_profile.as_deref()
+    }
+    
+    /// Save the default prompt for a profile, stored as `default.<profile>.txt`
+    pub fn save_profile_default(&mut self, profile: &str, prompt: &str) -> Result<()> {
+        validate_profile_name(profile)?;
+        let prompt = self.prepare_for_save(prompt)?;
+        self.storage.save_profile_default(profile, &prompt)
+    }
+    
+    /// List profiles that have their own default prompt, sorted
+    pub fn list_profiles(&self) -> Result<Vec<String>> {
+        self.storage.list_profiles()
     }
     
     /// Save the default system prompt
     pub fn save_default(&mut self, prompt: &str) -> Result<()> {
-        self.validate_prompt(prompt)?;
-        self.storage.save_default(prompt)
+        let prompt = self.prepare_for_save(prompt)?;
+        self.storage.save_default(&prompt)
     }
     
-    /// Load a named prompt
+    /// Load a named prompt, applying the transform pipeline
     pub fn load_prompt(&self, name: &str) -> Result<String> {
-        self.storage.load_prompt(name)
+        let prompt = match self.prompt_cache.as_ref().and_then(|cache| cache.get(name)) {
+            Some(prompt) => prompt,
+            None => {
+                let prompt = self.storage.load_prompt(name)?;
+                if let Some(cache) = &self.prompt_cache {
+                    cache.insert(name, &prompt);
+                }
+                prompt
+            }
+        };
+        Ok(self.transform_pipeline.apply(&prompt))
+    }
+    
+    /// Load a prompt's override for an environment, falling back to the base prompt
+    ///
+    /// Overrides are stored as `<name>@<env>`, so `greeting@prod` replaces
+    /// `greeting` when loading for `prod`.
+    pub fn load_prompt_for_env(&self, name: &str, env: &str) -> Result<String> {
+        let override_name = Self::env_prompt_name(name, env);
+        if self.storage.prompt_exists(&override_name) {
+            return self.load_prompt(&override_name);
+        }
+        self.load_prompt(name)
+    }
+    
+    /// Save a prompt override for an environment as `<name>@<env>`
+    ///
+    /// File storage maps `@` to `_`, so this fails with `InvalidName` when
+    /// another prompt such as `greeting_prod` already holds the file.
+    pub fn save_prompt_for_env(&mut self, name: &str, env: &str, prompt: &str) -> Result<()> {
+        self.save_prompt(&Self::env_prompt_name(name, env), prompt)
+    }
+    
+    /// Name under which a prompt's environment override is stored
+    fn env_prompt_name(name: &str, env: &str) -> String {
+        format!("{}@{}", name, env)
     }
     
     /// Save a named prompt
     pub fn save_prompt(&mut self, name: &str, prompt: &str) -> Result<()> {
-        self.validate_prompt(prompt)?;
-        self.storage.save_prompt(name, prompt)
+        let prompt = self.prepare_for_save(prompt)?;
+        let kind = if self.storage.prompt_exists(name) { ChangeKind::Modified } else { ChangeKind::Created };
+        self.storage.save_prompt(name, &prompt)?;
+        if let Some(cache) = &self.prompt_cache {
+            cache.insert(name, prompt.trim());
+        }
+        self.notify(name, kind);
+        Ok(())
+    }
+    
+    /// Save several prompts, attempting every entry
+    ///
+    /// Entries that fail validation or can't be written are listed in the
+    /// report's `failed`; they don't roll back prompts already saved.
+    /// Metadata is written once for the whole batch. An error means that
+    /// final write failed, leaving metadata behind the saved files until
+    /// [`reconcile`](Self::reconcile) is run.
+    pub fn save_prompts(&mut self, entries: &[(String, String)]) -> Result<BatchReport> {
+        let mut report = BatchReport::default();
+        let mut prepared = Vec::new();
+        for (name, prompt) in entries {
+            match self.prepare_for_save(prompt) {
+                Ok(prompt) => prepared.push((name.clone(), prompt)),
+                Err(e) => report.failed.push((name.clone(), e.to_string())),
+            }
+        }
+        
+        let kinds: Vec<ChangeKind> = prepared.iter()
+            .map(|(name, _)| if self.storage.prompt_exists(name) { ChangeKind::Modified } else { ChangeKind::Created })
+            .collect();
+        let results = self.storage.save_prompts(&prepared).inspect_err(|_| self.clear_prompt_cache())?;
+        for (((name, prompt), kind), result) in prepared.iter().zip(kinds).zip(results) {
+            match result {
+                Ok(()) => {
+                    if let Some(cache) = &self.prompt_cache {
+                        cache.insert(name, prompt.trim());
+                    }
+                    self.notify(name, kind);
+                    report.succeeded.push(name.clone());
+                }
+                Err(e) => report.failed.push((name.clone(), e.to_string())),
+            }
+        }
+        
+        info!("Batch saved {} prompts, {} failed", report.succeeded.len(), report.failed.len());
+        Ok(report)
+    }
+    
+    /// Delete several prompts, attempting every name
+    ///
+    /// Missing prompts are reported in `failed` without affecting the
+    /// rest. If the final metadata write fails, every name is reported as
+    /// failed with that error.
+    pub fn delete_prompts(&mut self, names: &[String]) -> BatchReport {
+        let mut report = BatchReport::default();
+        let results = match self.storage.delete_prompts(names) {
+            Ok(results) => results,
+            Err(e) => {
+                self.clear_prompt_cache();
+                report.failed = names.iter().map(|name| (name.clone(), e.to_string())).collect();
+                return report;
+            }
+        };
+        
+        for (name, result) in names.iter().zip(results) {
+            match result {
+                Ok(()) => {
+                    if let Some(cache) = &self.prompt_cache {
+                        cache.remove(name);
+                    }
+                    self.notify(name, ChangeKind::Deleted);
+                    report.succeeded.push(name.clone());
+                }
+                Err(e) => report.failed.push((name.clone(), e.to_string())),
+            }
+        }
+        report
+    }
+    
+    /// Save a named prompt and report latent render problems as warnings
+    ///
+    /// After saving, the prompt is rendered under strict settings with a
+    /// placeholder for every referenced variable, so missing partials or
+    /// bad helper usage surface now rather than at render time. Problems
+    /// don't prevent the save.
+    pub fn save_prompt_validate_render(&mut self, name: &str, prompt: &str) -> Result<Vec<String>> {
+        self.save_prompt(name, prompt)?;
+        
+        let render_config = RenderConfig {
+            strict_mode: Some(true),
+            missing_placeholder: Some("placeholder".to_string()),
+            ..RenderConfig::default()
+        };
+        let mut warnings = Vec::new();
+        if let Err(e) = self.template_engine.render_with_config(prompt, &HashMap::new(), &render_config) {
+            warn!("Prompt '{}' may fail to render: {}", name, e);
+            warnings.push(e.to_string());
+        }
+        
+        Ok(warnings)
     }
     
     /// List all available named prompts
@@ -124,9 +599,283 @@ impl PromptManager {
         self.storage.list_prompts()
     }
     
+    /// List prompts under a namespace, e.g. `alice` for `alice/coding`
+    ///
+    /// Names are split on `namespace_separator`, or `/` when none is set.
+    pub fn list_prompts_in_namespace(&self, prefix: &str) -> Result<Vec<String>> {
+        let separator = self.config.namespace_separator.unwrap_or('/');
+        let prefix = format!("{}{}", prefix.trim_end_matches(separator), separator);
+        Ok(self.storage.list_prompts()?
+            .into_iter()
+            .filter(|name| name.starts_with(&prefix))
+            .collect())
+    }
+    
     /// Delete a named prompt
     pub fn delete_prompt(&mut self, name: &str) -> Result<()> {
-        self.storage.delete_prompt(name)
+        self.storage.delete_prompt(name)?;
+        if let Some(cache) = &self.prompt_cache {
+            cache.remove(name);
+        }
+        self.notify(name, ChangeKind::Deleted);
+        Ok(())
+    }
+    
+    /// Load a named prompt as raw bytes with a MIME type for serving
+    ///
+    /// The MIME type is derived from the prompt's recorded content type,
+    /// defaulting to `text/plain; charset=utf-8`.
+    pub fn load_prompt_bytes(&self, name: &str) -> Result<(Vec<u8>, &'static str)> {
+        let content = self.storage.load_prompt(name)?;
+        let info = self.storage.get_prompt_info(name)?;
+        Ok((content.into_bytes(), mime_type_for(info.content_type.as_deref())))
+    }
+    
+    /// Set the content type recorded for a named prompt
+    pub fn set_content_type(&mut self, name: &str, content_type: &str) -> Result<()> {
+        self.storage.set_content_type(name, content_type)
+    }
+    
+    /// Exchange the content of two existing prompts
+    pub fn swap_prompts(&mut self, a: &str, b: &str) -> Result<()> {
+        self.storage.swap_prompts(a, b)?;
+        self.clear_prompt_cache();
+        self.notify(a, ChangeKind::Modified);
+        self.notify(b, ChangeKind::Modified);
+        Ok(())
+    }
+    
+    /// Rename a prompt, keeping its content, tags, and creation time
+    ///
+    /// Variable constraints registered for the old name move with it.
+    pub fn rename_prompt(&mut self, old: &str, new: &str) -> Result<()> {
+        self.storage.rename_prompt(old, new)?;
+        if let Some(cache) = &self.prompt_cache
+            && let Some(content) = cache.remove(old)
+        {
+            cache.insert(new, &content);
+        }
+        if let Some(constraints) = self.variable_constraints.remove(old) {
+            self.variable_constraints.insert(new.to_string(), constraints);
+        }
+        self.notify(new, ChangeKind::Renamed { from: old.to_string() });
+        Ok(())
+    }
+    
+    /// Copy a prompt to a new name, e.g. to branch `coding` into `coding-v2`
+    ///
+    /// Refuses to replace an existing `dest` unless `overwrite` is true.
+    pub fn copy_prompt(&mut self, source: &str, dest: &str, overwrite: bool) -> Result<()> {
+        let kind = if self.storage.prompt_exists(dest) { ChangeKind::Modified } else { ChangeKind::Created };
+        self.storage.copy_prompt(source, dest, overwrite)?;
+        if let Some(cache) = &self.prompt_cache {
+            cache.remove(dest);
+        }
+        self.notify(dest, kind);
+        Ok(())
+    }
+    
+    /// Remove metadata entries for prompts whose files were deleted outside the API
+    ///
+    /// Returns the number of entries removed.
+    pub fn vacuum(&mut self) -> Result<usize> {
+        self.clear_prompt_cache();
+        self.storage.vacuum()
+    }
+    
+    /// Add prompts stored outside the API to metadata, skipping binary files
+    pub fn reconcile(&mut self) -> Result<ReconcileReport> {
+        self.clear_prompt_cache();
+        self.storage.reconcile()
+    }
+    
+    /// Delete leftover `.tmp`, `.corrupt`, and backup files from storage
+    ///
+    /// Prompt files and metadata are never removed. Returns the deleted paths.
+    pub fn clean_temp_files(&mut self) -> Result<Vec<std::path::PathBuf>> {
+        self.storage.clean_temp_files()
+    }
+    
+    /// Delete all but the most recent `keep` historical versions of a prompt
+    pub fn prune_history(&mut self, name: &str, keep: usize) -> Result<usize> {
+        self.storage.prune_history(name, keep)
+    }
+    
+    /// Get the on-disk size of a prompt's history in bytes
+    pub fn history_size(&self, name: &str) -> Result<u64> {
+        self.storage.history_size(name)
+    }
+    
+    /// List the timestamps of a prompt's previous versions, oldest first
+    ///
+    /// Versions are kept when `keep_history` or `keep_versions` is set.
+    pub fn list_versions(&self, name: &str) -> Result<Vec<u128>> {
+        self.storage.list_versions(name)
+    }
+    
+    /// Restore a previous version of a prompt by its timestamp
+    ///
+    /// The restore is saved like any other edit, so the content it replaces
+    /// becomes the newest version.
+    pub fn restore_version(&mut self, name: &str, timestamp: u128) -> Result<()> {
+        let content = self.storage.load_version(name, timestamp)?;
+        self.save_prompt(name, &content)?;
+        info!("Restored prompt '{}' to version {}", name, timestamp);
+        Ok(())
+    }
+    
+    /// Replace the tags on a named prompt
+    pub fn set_tags(&mut self, name: &str, tags: Vec<String>) -> Result<()> {
+        self.storage.set_tags(name, tags)
+    }
+    
+    /// Get the tags on a named prompt
+    pub fn get_tags(&self, name: &str) -> Result<Vec<String>> {
+        Ok(self.storage.get_prompt_info(name)?.tags)
+    }
+    
+    /// Count how many prompts use each tag
+    ///
+    /// Tags are counted case-insensitively; each tag is reported under the
+    /// casing it first appears with, scanning prompts in name order.
+    pub fn tag_counts(&self) -> Result<BTreeMap<String, usize>> {
+        let mut counts: HashMap<String, (String, usize)> = HashMap::new();
+        for name in self.storage.list_prompts()? {
+            for tag in self.storage.get_prompt_info(&name)?.tags {
+                counts.entry(tag.to_lowercase())
+                    .or_insert_with(|| (tag.clone(), 0))
+                    .1 += 1;
+            }
+        }
+        
+        Ok(counts.into_values().collect())
+    }
+    
+    /// List the prompts carrying a tag, sorted by name
+    ///
+    /// Tags match case-insensitively, like [`tag_counts`](Self::tag_counts).
+    pub fn list_prompts_by_tag(&self, tag: &str) -> Result<Vec<String>> {
+        let tag = tag.trim().to_lowercase();
+        let mut tagged = Vec::new();
+        for name in self.storage.list_prompts()? {
+            if self.storage.get_prompt_info(&name)?.tags.iter().any(|t| t.to_lowercase() == tag) {
+                tagged.push(name);
+            }
+        }
+        tagged.sort();
+        Ok(tagged)
+    }
+    
+    /// Store example variable sets with a prompt for [`run_examples`](Self::run_examples)
+    pub fn set_examples(&mut self, name: &str, examples: Vec<HashMap<String, String>>) -> Result<()> {
+        self.storage.set_examples(name, examples)
+    }
+    
+    /// Get the example variable sets stored with a prompt
+    pub fn get_examples(&self, name: &str) -> Result<Vec<HashMap<String, String>>> {
+        Ok(self.storage.get_prompt_info(name)?.examples)
+    }
+    
+    /// Render a prompt once per stored example, in order
+    ///
+    /// Examples render as `render_prompt` would, with constraints checked
+    /// and embeds expanded, but in strict mode, so one that no longer
+    /// supplies every variable the prompt uses fails instead of rendering
+    /// blanks. A failing example doesn't stop the others; only loading the
+    /// prompt itself can fail the whole run.
+    pub fn run_examples(&self, name: &str) -> Result<Vec<Result<String>>> {
+        let template = self.load_prompt(name)?;
+        let examples = self.storage.get_prompt_info(name)?.examples;
+        let render_config = RenderConfig { strict_mode: Some(true), ..RenderConfig::default() };
+        
+        Ok(examples
+            .iter()
+            .map(|variables| self.render_loaded_with_config(name, &template, variables, &render_config))
+            .collect())
+    }
+    
+    /// Group prompt names by tag, with untagged prompts under `"(untagged)"`
+    ///
+    /// A prompt appears under each of its tags. Tags are grouped
+    /// case-insensitively like [`tag_counts`](Self::tag_counts), and names
+    /// within each group are sorted.
+    pub fn group_by_tag(&self) -> Result<BTreeMap<String, Vec<String>>> {
+        let mut groups: HashMap<String, (String, Vec<String>)> = HashMap::new();
+        for name in self.storage.list_prompts()? {
+            let tags = self.storage.get_prompt_info(&name)?.tags;
+            if tags.is_empty() {
+                groups.entry(UNTAGGED_GROUP.to_string())
+                    .or_insert_with(|| (UNTAGGED_GROUP.to_string(), Vec::new()))
+                    .1.push(name.clone());
+            }
+            for tag in tags {
+                let names = &mut groups.entry(tag.to_lowercase())
+                    .or_insert_with(|| (tag.clone(), Vec::new()))
+                    .1;
+                if !names.contains(&name) {
+                    names.push(name.clone());
+                }
+            }
+        }
+        
+        Ok(groups.into_values().collect())
+    }
+    
+    /// Search stored prompts for lines matching a query
+    ///
+    /// Uses the storage backend's native search when it has one, otherwise
+    /// scans every prompt. Hits are ordered by prompt name, then line.
+    pub fn search(&self, query: &SearchQuery) -> Result<Vec<SearchHit>> {
+        if let Some(hits) = self.storage.search_native(query) {
+            return hits;
+        }
+        
+        let mut hits = Vec::new();
+        for name in self.storage.list_prompts()? {
+            let content = self.storage.load_prompt(&name)?;
+            hits.extend(crate::search::search_content(&name, &content, query));
+        }
+        Ok(hits)
+    }
+    
+    /// Rank prompt names against a fuzzy query, best match first
+    ///
+    /// Names that don't match at all are left out; ties are broken by
+    /// name. An empty query returns every prompt with a score of 0.
+    /// At most `limit` results are returned.
+    pub fn fuzzy_match(&self, query: &str, limit: usize) -> Result<Vec<(String, f64)>> {
+        use fuzzy_matcher::FuzzyMatcher;
+        
+        let names = self.storage.list_prompts()?;
+        let mut ranked: Vec<(String, f64)> = if query.trim().is_empty() {
+            names.into_iter().map(|name| (name, 0.0)).collect()
+        } else {
+            let matcher = fuzzy_matcher::skim::SkimMatcherV2::default();
+            names.into_iter()
+                .filter_map(|name| matcher.fuzzy_match(&name, query.trim()).map(|score| (name, score as f64)))
+                .collect()
+        };
+        
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ranked.truncate(limit);
+        Ok(ranked)
+    }
+    
+    /// Search every prompt for a regular expression
+    ///
+    /// Each hit is the line a match begins on; patterns may span lines.
+    /// An invalid pattern is a `PromptError::Config`.
+    #[cfg(feature = "regex")]
+    pub fn search_regex(&self, pattern: &str) -> Result<Vec<SearchHit>> {
+        let pattern = regex::Regex::new(pattern)
+            .map_err(|e| PromptError::Config(format!("Invalid search pattern '{}': {}", pattern, e)))?;
+        
+        let mut hits = Vec::new();
+        for name in self.storage.list_prompts()? {
+            let content = self.storage.load_prompt(&name)?;
+            hits.extend(crate::search::search_content_regex(&name, &content, &pattern));
+        }
+        Ok(hits)
     }
     
     /// Check if a prompt exists
@@ -134,66 +883,2741 @@ impl PromptManager {
         self.storage.prompt_exists(name)
     }
     
+    /// Set the transform pipeline applied to loaded prompts
+    pub fn set_transform_pipeline(&mut self, pipeline: TransformPipeline) {
+        self.transform_pipeline = pipeline;
+        self.clear_token_cache();
+    }
+    
+    /// Remove all transform steps
+    pub fn clear_transform_pipeline(&mut self) {
+        self.transform_pipeline = TransformPipeline::new();
+        self.clear_token_cache();
+    }
+    
+    /// Set a transform applied to every prompt before it is saved
+    ///
+    /// The transform runs on the trimmed prompt after it passes validation,
+    /// and its output is validated again so length limits apply to what is
+    /// actually written. The output is stored verbatim, so a trailing
+    /// newline the transform adds stays on disk.
+    pub fn set_save_transform(&mut self, transform: SaveTransform) {
+        self.save_transform = Some(transform);
+    }
+    
+    /// Remove the save transform
+    pub fn clear_save_transform(&mut self) {
+        self.save_transform = None;
+    }
+    
+    /// Replace the unsaved working prompt
+    pub fn set_working(&mut self, content: String) {
+        self.working = Some(content);
+    }
+    
+    /// Get the unsaved working prompt, if any
+    pub fn working(&self) -> Option<&str> {
+        self.working.as_deref()
+    }
+    
+    /// Discard the working prompt without saving
+    pub fn clear_working(&mut self) {
+        self.working = None;
+    }
+    
+    /// Render the working prompt with variables
+    pub fn render_working(&self, variables: &HashMap<String, String>) -> Result<String> {
+        let working = self.working.as_deref()
+            .ok_or_else(|| PromptError::InvalidPrompt("No working prompt".to_string()))?;
+        self.render_template(working, variables)
+    }
+    
+    /// Validate and save the working prompt under a name, clearing the buffer
+    pub fn commit_working(&mut self, name: &str) -> Result<()> {
+        let working = self.working.clone()
+            .ok_or_else(|| PromptError::InvalidPrompt("No working prompt".to_string()))?;
+        self.save_prompt(name, &working)?;
+        self.working = None;
+        Ok(())
+    }
+    
+    /// Register a callback for changes to any prompt
+    pub fn subscribe(&self, callback: ChangeCallback) -> WatchHandle {
+        self.notifier.subscribe(None, callback)
+    }
+    
+    /// Register a callback for changes affecting one prompt
+    ///
+    /// The callback fires for saves and deletes of `name`, and for renames
+    /// to or from it.
+    pub fn watch_prompt(&self, name: &str, callback: ChangeCallback) -> WatchHandle {
+        self.notifier.subscribe(Some(name), callback)
+    }
+    
+    /// Notify observers of a change
+    fn notify(&self, name: &str, kind: ChangeKind) {
+        self.notifier.notify(&PromptChangeEvent { name: name.to_string(), kind });
+    }
+    
     /// Render a template with variables
     pub fn render_template(&self, template: &str, variables: &HashMap<String, String>) -> Result<String> {
+        self.render_template_with_options(template, variables, &RenderOptions::default())
+    }
+    
+    /// Render a template with variables and post-processing options
+    pub fn render_template_with_options(
+        &self,
+        template: &str,
+        variables: &HashMap<String, String>,
+        options: &RenderOptions,
+    ) -> Result<String> {
         if self.config.validate_templates {
             self.template_engine.validate_template(template)?;
         }
         
-        let rendered = self.template_engine.render(template, variables)?;
+        let rendered = self.template_engine.render_with_options(template, variables, options)?;
         self.validate_prompt(&rendered)?;
         Ok(rendered)
     }
     
-    /// Get prompt metadata (size, modification time, etc.)
-    pub fn get_prompt_info(&self, name: &str) -> Result<crate::storage::PromptInfo> {
-        self.storage.get_prompt_info(name)
-    }
-    
-    /// Reset to factory default prompt
-    pub fn reset_default(&mut self) -> Result<()> {
-        let factory_default = Self::factory_default_prompt();
-        self.save_default(&factory_default)
+    /// Render a template, filling missing variables from the environment
+    ///
+    /// A variable absent from `variables` is read from `GAMECODE_VAR_` plus
+    /// its name upper-cased with every character other than ASCII letters
+    /// and digits replaced by `_`, so `project_name`, `project-name` and
+    /// `project.name` all read `GAMECODE_VAR_PROJECT_NAME`. Only that prefix
+    /// is consulted, so unrelated environment never leaks into prompts.
+    /// Explicit variables win over the environment, which wins over inline
+    /// `{{default}}` values.
+    pub fn render_template_with_env(&self, template: &str, variables: &HashMap<String, String>) -> Result<String> {
+        self.render_template_with_lookup(template, variables, |name| std::env::var(name).ok())
     }
     
-    /// Get the factory default prompt
-    pub fn factory_default_prompt() -> String {
-        r#"You are Claude, an AI assistant created by Anthropic. You are helpful, harmless, and honest.
-
-When helping with code:
-- Provide clear, concise explanations
-- Follow best practices and conventions
-- Consider security and performance implications
-- Test your suggestions when possible
-
-When helping with general tasks:
-- Be direct and actionable
-- Ask clarifying questions when needed
-- Provide step-by-step guidance for complex tasks
-- Acknowledge limitations or uncertainties"#.to_string()
+    /// Render a template, filling missing variables from `lookup` by env var name
+    fn render_template_with_lookup(
+        &self,
+        template: &str,
+        variables: &HashMap<String, String>,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<String> {
+        let mut merged = variables.clone();
+        let mut nested = Vec::new();
+        for name in self.template_engine.extract_variables(template)? {
+            let root = name.split('.').next().unwrap_or_default();
+            if merged.contains_key(&name) || merged.contains_key(root) {
+                continue;
+            }
+            let key: String = name.chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+                .collect();
+            if let Some(value) = lookup(&format!("{}{}", ENV_VAR_PREFIX, key)) {
+                if name.contains('.') {
+                    nested.push((name, value));
+                } else {
+                    merged.insert(name, value);
+                }
+            }
+        }
+        if nested.is_empty() {
+            return self.render_template(template, &merged);
+        }
+        
+        // Dotted paths need nested objects, so render with a JSON context
+        let mut context = serde_json::Map::new();
+        for (name, value) in template::parse_inline_defaults(template).into_iter().chain(merged) {
+            context.insert(name, serde_json::Value::String(value));
+        }
+        for (path, value) in nested {
+            let segments: Vec<&str> = path.split('.').collect();
+            insert_path(&mut context, &segments, value);
+        }
+        self.render_value(template, &serde_json::Value::Object(context))
     }
     
-    /// Validate a prompt according to current config
-    fn validate_prompt(&self, prompt: &str) -> Result<()> {
-        if prompt.trim().is_empty() {
-            return Err(PromptError::InvalidPrompt("Prompt cannot be empty".to_string()));
+    /// Render a template with an arbitrary JSON context
+    ///
+    /// Use this for lists and nested data, e.g. `{{#each items}}`. The
+    /// output is validated per config like any other render.
+    pub fn render_value(&self, template: &str, context: &serde_json::Value) -> Result<String> {
+        if self.config.validate_templates {
+            self.template_engine.validate_template(template)?;
         }
         
-        if prompt.len() > self.config.max_prompt_length {
-            return Err(PromptError::InvalidPrompt(
-                format!("Prompt exceeds maximum length of {} characters", self.config.max_prompt_length)
-            ));
+        let rendered = self.template_engine.render_value(template, context)?;
+        self.validate_prompt(&rendered)?;
+        Ok(rendered)
+    }
+    
+    /// Render a template with per-call engine configuration overrides
+    pub fn render_template_with_config(
+        &self,
+        template: &str,
+        variables: &HashMap<String, String>,
+        render_config: &RenderConfig,
+    ) -> Result<String> {
+        if self.config.validate_templates {
+            self.template_engine.validate_template(&render_config.normalize_template(template))?;
         }
         
-        Ok(())
+        let rendered = self.template_engine.render_with_config(template, variables, render_config)?;
+        self.validate_prompt(&rendered)?;
+        Ok(rendered)
     }
-}
-
-impl Default for PromptManager {
-    fn default() -> Self {
-        Self::new().expect("Failed to create default PromptManager")
+    
+    /// Load a named prompt and render it with variables
+    ///
+    /// Provided variables are checked against any constraints registered
+    /// for the prompt before rendering.
+    ///
+    /// `{{embed "name"}}` inserts the stored content of another prompt
+    /// verbatim, without rendering it as a template. Variable names starting
+    /// with `__embed_` are reserved for this and rejected.
+    pub fn render_prompt(&self, name: &str, variables: &HashMap<String, String>) -> Result<String> {
+        let template = self.load_prompt(name)?;
+        self.render_loaded(name, &template, variables)
     }
-}
-
-// Re-export important types
-pub use crate::storage::PromptInfo;
\ No newline at end of file
+    
+    /// Render a named prompt and split it into chat messages
+    ///
+    /// Sections start at role-header lines such as `### user`, using
+    /// `Config::message_header_prefix`. Content before the first header is
+    /// a system message.
+    pub fn render_as_messages(&self, name: &str, variables: &HashMap<String, String>) -> Result<Vec<Message>> {
+        let rendered = self.render_prompt(name, variables)?;
+        Ok(crate::messages::split_messages(&rendered, &self.config.message_header_prefix))
+    }
+    
+    /// Render a named prompt and log the variables with sensitive values masked
+    ///
+    /// Emits a `debug` event listing every variable, with the values of
+    /// `redact_keys` replaced by `***`. The rendered output isn't logged,
+    /// since it contains the values; the returned string is unredacted.
+    pub fn render_and_log_safe(
+        &self,
+        name: &str,
+        variables: &HashMap<String, String>,
+        redact_keys: &[String],
+    ) -> Result<String> {
+        let rendered = self.render_prompt(name, variables)?;
+        
+        let logged: BTreeMap<&str, &str> = variables.iter()
+            .map(|(key, value)| {
+                let shown = if redact_keys.contains(key) { "***" } else { value.as_str() };
+                (key.as_str(), shown)
+            })
+            .collect();
+        debug!(prompt = name, variables = ?logged, length = rendered.len(), "Rendered prompt");
+        
+        Ok(rendered)
+    }
+    
+    /// Load the default prompt and render it with variables
+    ///
+    /// The output is validated per config, like [`render_prompt`](Self::render_prompt).
+    pub fn render_default(&self, variables: &HashMap<String, String>) -> Result<String> {
+        let template = self.load_default()?;
+        self.render_template(&template, variables)
+    }
+    
+    /// Import every `*.txt` file in a directory as a prompt
+    ///
+    /// The file stem becomes the prompt name and nested directories become
+    /// namespaces. Existing prompts are skipped unless `overwrite` is set.
+    /// Files that fail to read or validate are recorded in the report
+    /// instead of aborting the import.
+    pub fn import_text_dir(&mut self, dir: &std::path::Path, overwrite: bool) -> Result<ImportReport> {
+        let mut report = ImportReport::default();
+        
+        for (name, path) in crate::import::text_files(dir)? {
+            if !overwrite && self.storage.prompt_exists(&name) {
+                report.skipped.push(name);
+                continue;
+            }
+            
+            let saved = std::fs::read_to_string(&path)
+                .map_err(PromptError::from)
+                .and_then(|content| self.save_prompt(&name, &content));
+            match saved {
+                Ok(()) => report.imported.push(name),
+                Err(e) => {
+                    warn!("Failed to import {}: {}", path.display(), e);
+                    report.failed.push((name, e.to_string()));
+                }
+            }
+        }
+        
+        info!("Imported {} prompts from {}", report.imported.len(), dir.display());
+        Ok(report)
+    }
+    
+    /// Export the library as a JSON bundle
+    ///
+    /// Prompts in the `partials/` namespace go in the bundle's partials
+    /// section, and the registered helpers the templates use are listed so
+    /// an importer can tell which ones it's missing.
+    pub fn export_bundle(&self) -> Result<String> {
+        let mut bundle = PromptBundle::new();
+        bundle.default = Some(self.storage.load_default()?);
+        let mut helpers = std::collections::BTreeSet::new();
+        
+        for name in self.storage.list_prompts()? {
+            let content = self.storage.load_prompt(&name)?;
+            let info = self.storage.get_prompt_info(&name)?;
+            helpers.extend(self.template_engine.helpers_used(&content));
+            
+            let partial = name.strip_prefix(PARTIALS_NAMESPACE).map(str::to_string);
+            let entry = BundleEntry {
+                name: partial.clone().unwrap_or(name),
+                content,
+                tags: info.tags,
+                created_at: Some(crate::export::unix_seconds(info.created_at)),
+                modified_at: Some(crate::export::unix_seconds(info.modified_at)),
+            };
+            if partial.is_some() {
+                bundle.partials.push(entry);
+            } else {
+                bundle.prompts.push(entry);
+            }
+        }
+        
+        bundle.helpers = helpers.into_iter().collect();
+        Ok(serde_json::to_string_pretty(&bundle)?)
+    }
+    
+    /// Import prompts and partials from a JSON bundle
+    ///
+    /// Partials are saved under the `partials/` namespace and registered.
+    /// Existing prompts are skipped unless `overwrite` is set. The bundled
+    /// default replaces the current one if `overwrite` is set or the current
+    /// default is still the factory prompt. Helpers the bundle expects but
+    /// this manager lacks are logged and listed in the report.
+    pub fn import_bundle(&mut self, bundle_json: &str, overwrite: bool) -> Result<ImportReport> {
+        let bundle: PromptBundle = serde_json::from_str(bundle_json)?;
+        let mut report = ImportReport::default();
+        
+        for helper in &bundle.helpers {
+            if !self.template_engine.has_helper(helper) {
+                warn!("Bundle expects helper '{}' which is not registered", helper);
+                report.missing_helpers.push(helper.clone());
+            }
+        }
+        
+        if let Some(default) = &bundle.default
+            && (overwrite || self.default_is_factory())
+            && let Err(e) = self.save_default(default)
+        {
+            warn!("Failed to import default prompt: {}", e);
+            report.failed.push(("default".to_string(), e.to_string()));
+        }
+        
+        let partials = bundle.partials.iter()
+            .map(|entry| (format!("{}{}", PARTIALS_NAMESPACE, entry.name), entry));
+        let prompts = bundle.prompts.iter().map(|entry| (entry.name.clone(), entry));
+        for (name, entry) in partials.chain(prompts) {
+            if !overwrite && self.storage.prompt_exists(&name) {
+                report.skipped.push(name);
+                continue;
+            }
+            
+            let imported = self.save_prompt(&name, &entry.content)
+                .and_then(|()| self.storage.set_tags(&name, entry.tags.clone()));
+            match imported {
+                Ok(()) => report.imported.push(name),
+                Err(e) => {
+                    warn!("Failed to import '{}': {}", name, e);
+                    report.failed.push((name, e.to_string()));
+                }
+            }
+        }
+        
+        self.register_storage_partials()?;
+        info!("Imported {} prompts from bundle", report.imported.len());
+        Ok(report)
+    }
+    
+    /// Check every prompt in a JSON bundle against this manager's config without saving
+    ///
+    /// Each entry is checked for length and emptiness and, if template
+    /// validation is enabled, for template syntax. Results are listed in
+    /// import order: the default as `default`, then partials under the
+    /// `partials/` namespace, then named prompts. Only a bundle that can't
+    /// be parsed is an error.
+    pub fn validate_bundle(&self, bundle_json: &str) -> Result<Vec<(String, Result<()>)>> {
+        let bundle: PromptBundle = serde_json::from_str(bundle_json)?;
+        
+        let default = bundle.default.iter().map(|content| ("default".to_string(), content));
+        let partials = bundle.partials.iter()
+            .map(|entry| (format!("{}{}", PARTIALS_NAMESPACE, entry.name), &entry.content));
+        let prompts = bundle.prompts.iter().map(|entry| (entry.name.clone(), &entry.content));
+        
+        Ok(default
+            .chain(partials)
+            .chain(prompts)
+            .map(|(name, content)| {
+                let checked = self.validate_prompt(content).and_then(|()| {
+                    if self.config.validate_templates {
+                        self.template_engine.validate_template(content)
+                    } else {
+                        Ok(())
+                    }
+                });
+                (name, checked)
+            })
+            .collect())
+    }
+    
+    /// Write every named prompt and the default to a JSON bundle
+    ///
+    /// The bundle format is the one produced by [`export_bundle`](Self::export_bundle).
+    pub fn export_all(&self, mut writer: impl std::io::Write) -> Result<()> {
+        let bundle = self.export_bundle()?;
+        writer.write_all(bundle.as_bytes())?;
+        writer.flush()?;
+        Ok(())
+    }
+    
+    /// Read a JSON bundle written by [`export_all`](Self::export_all) and import it
+    ///
+    /// Each prompt is validated as it's saved; prompts that fail are listed
+    /// in the report and the rest are still imported. See
+    /// [`import_bundle`](Self::import_bundle) for how existing prompts and
+    /// the default are handled.
+    pub fn import_all(&mut self, mut reader: impl std::io::Read, overwrite: bool) -> Result<ImportReport> {
+        let mut bundle = String::new();
+        reader.read_to_string(&mut bundle)?;
+        self.import_bundle(&bundle, overwrite)
+    }
+    
+    /// Compile a template once for repeated rendering by name
+    ///
+    /// Bad syntax fails here with `PromptError::TemplateSyntax`, so
+    /// templates can be checked at startup.
+    pub fn register_template(&mut self, name: &str, source: &str) -> Result<()> {
+        self.template_engine.register_template(name, source)
+    }
+    
+    /// Render a template registered with [`register_template`](Self::register_template)
+    ///
+    /// The output is validated per config like any other render.
+    pub fn render_registered(&self, name: &str, variables: &HashMap<String, String>) -> Result<String> {
+        let rendered = self.template_engine.render_registered(name, variables)?;
+        self.validate_prompt(&rendered)?;
+        Ok(rendered)
+    }
+    
+    /// Add glossary terms that templates look up with `{{define "term"}}`
+    ///
+    /// Undefined terms render as the term itself.
+    pub fn register_glossary(&mut self, terms: HashMap<String, String>) {
+        self.template_engine.register_glossary(terms);
+    }
+    
+    /// Register every prompt in the `partials/` namespace as a template partial
+    ///
+    /// The namespace prefix is stripped, so `partials/safety` is included
+    /// as `{{> safety}}`. Returns the number of partials registered.
+    pub fn register_storage_partials(&mut self) -> Result<usize> {
+        let mut count = 0;
+        for name in self.storage.list_prompts()? {
+            let Some(partial) = name.strip_prefix(PARTIALS_NAMESPACE) else {
+                continue;
+            };
+            let template = self.load_prompt(&name)?;
+            self.template_engine.register_partial(partial, &template)?;
+            count += 1;
+        }
+        
+        info!("Registered {} partials from storage", count);
+        Ok(count)
+    }
+    
+    /// Render a named prompt compactly for token-constrained requests
+    ///
+    /// Drops `{{#optional}}` sections and HTML comments and collapses
+    /// whitespace; see [`RenderMode::Compact`].
+    pub fn render_prompt_compact(&self, name: &str, variables: &HashMap<String, String>) -> Result<String> {
+        let template = self.load_prompt(name)?;
+        let options = RenderOptions { mode: RenderMode::Compact, ..RenderOptions::default() };
+        self.render_loaded_with_options(name, &template, variables, &options)
+    }
+    
+    /// Render a named prompt, recording every prompt and partial it pulls in
+    ///
+    /// `{{> name}}` includes are resolved against the `partials/` namespace
+    /// first, then against named prompts, and inlined before rendering.
+    /// The trace lists the rendered prompt at depth 0 followed by each
+    /// include in the order it was resolved.
+    pub fn render_with_trace(
+        &self,
+        name: &str,
+        variables: &HashMap<String, String>,
+    ) -> Result<(String, RenderTrace)> {
+        let template = self.load_prompt(name)?;
+        let mut trace = RenderTrace::default();
+        let expanded = self.expand_includes(name, &template, &mut trace)?;
+        let rendered = self.render_loaded(name, &expanded, variables)?;
+        Ok((rendered, trace))
+    }
+    
+    /// Render a named prompt with `{{> name}}` replaced by other stored prompts
+    ///
+    /// Includes resolve like in [`render_with_trace`](Self::render_with_trace)
+    /// and may nest. A prompt that includes itself, directly or through
+    /// others, fails with `InvalidPrompt` naming the cycle.
+    pub fn render_prompt_with_includes(&self, name: &str, variables: &HashMap<String, String>) -> Result<String> {
+        let template = self.load_prompt(name)?;
+        let expanded = self.expand_includes(name, &template, &mut RenderTrace::default())?;
+        self.render_loaded(name, &expanded, variables)
+    }
+    
+    /// Inline `{{> name}}` includes from `partials/` or named prompts
+    fn expand_includes(&self, name: &str, template: &str, trace: &mut RenderTrace) -> Result<String> {
+        let mut resolve = |include: &str| -> Result<Option<(String, String)>> {
+            let partial = format!("{}{}", PARTIALS_NAMESPACE, include);
+            for candidate in [partial.as_str(), include] {
+                if self.storage.prompt_exists(candidate) {
+                    return Ok(Some((candidate.to_string(), self.load_prompt(candidate)?)));
+                }
+            }
+            Ok(None)
+        };
+        crate::include::expand(name, template, &mut resolve, trace)
+    }
+    
+    /// Render a prompt with two variable sets and diff the outputs
+    ///
+    /// Returns a unified line diff from the render with `a` to the render
+    /// with `b`, or an empty string if they render identically.
+    pub fn diff_renders(
+        &self,
+        name: &str,
+        a: &HashMap<String, String>,
+        b: &HashMap<String, String>,
+    ) -> Result<String> {
+        let template = self.load_prompt(name)?;
+        let rendered_a = self.render_loaded(name, &template, a)?;
+        let rendered_b = self.render_loaded(name, &template, b)?;
+        
+        let diff = similar::TextDiff::from_lines(&rendered_a, &rendered_b);
+        Ok(diff.unified_diff()
+            .header(&format!("{} (a)", name), &format!("{} (b)", name))
+            .to_string())
+    }
+    
+    /// Render a child prompt that extends a base prompt
+    ///
+    /// Blocks in the base declared with `{{#block "name"}}default{{/block}}`
+    /// are replaced by the child's `{{#override "name"}}...{{/override}}`
+    /// sections; blocks the child doesn't override keep their defaults.
+    pub fn render_extending(
+        &self,
+        child: &str,
+        base: &str,
+        variables: &HashMap<String, String>,
+    ) -> Result<String> {
+        let child_prompt = self.load_prompt(child)?;
+        let base_prompt = self.load_prompt(base)?;
+        let extended = crate::inherit::extend(&base_prompt, &child_prompt)?;
+        self.render_loaded(child, &extended, variables)
+    }
+    
+    /// Load a prompt, rendering it only if it contains template syntax
+    ///
+    /// Static prompts are returned as-is. Templated prompts are rendered
+    /// with the given variables, or with an empty context under the
+    /// current lenient rules when none are given.
+    pub fn get(&self, name: &str, variables: Option<&HashMap<String, String>>) -> Result<String> {
+        let prompt = self.load_prompt(name)?;
+        if !prompt.contains("{{") {
+            return Ok(prompt);
+        }
+        
+        let empty = HashMap::new();
+        self.render_loaded(name, &prompt, variables.unwrap_or(&empty))
+    }
+    
+    /// Render an already loaded named prompt, checking its constraints
+    fn render_loaded(&self, name: &str, template: &str, variables: &HashMap<String, String>) -> Result<String> {
+        self.render_loaded_with_options(name, template, variables, &RenderOptions::default())
+    }
+    
+    /// Check a named prompt's constraints, then render it with options
+    fn render_loaded_with_options(
+        &self,
+        name: &str,
+        template: &str,
+        variables: &HashMap<String, String>,
+        options: &RenderOptions,
+    ) -> Result<String> {
+        let (template, variables) = self.prepare_loaded(name, template, variables)?;
+        self.render_template_with_options(&template, &variables, options)
+    }
+    
+    /// Check a named prompt's constraints, then render it with per-call overrides
+    fn render_loaded_with_config(
+        &self,
+        name: &str,
+        template: &str,
+        variables: &HashMap<String, String>,
+        render_config: &RenderConfig,
+    ) -> Result<String> {
+        let (template, variables) = self.prepare_loaded(name, template, variables)?;
+        self.render_template_with_config(&template, &variables, render_config)
+    }
+    
+    /// Check a named prompt's constraints and expand its embeds
+    fn prepare_loaded<'a>(
+        &self,
+        name: &str,
+        template: &str,
+        variables: &'a HashMap<String, String>,
+    ) -> Result<(String, std::borrow::Cow<'a, HashMap<String, String>>)> {
+        if let Some(constraints) = self.variable_constraints.get(name) {
+            crate::constraint::check_all(constraints, variables)?;
+        }
+        crate::include::check_reserved_variables(variables)?;
+        
+        let mut load = |embed: &str| self.load_prompt(embed);
+        let (template, embedded) = crate::include::expand_embeds(name, template, &mut load)?;
+        if embedded.is_empty() {
+            return Ok((template, std::borrow::Cow::Borrowed(variables)));
+        }
+        let mut variables = variables.clone();
+        variables.extend(embedded);
+        Ok((template, std::borrow::Cow::Owned(variables)))
+    }
+    
+    /// Number the lines of a prompt for error reports, marking one line
+    ///
+    /// Lines are numbered from 1 and the highlighted line is prefixed with
+    /// `>`, e.g. `> 2 | {{#if}}`.
+    pub fn format_with_lines(text: &str, highlight_line: Option<usize>) -> String {
+        let width = text.lines().count().max(1).to_string().len();
+        text.lines()
+            .enumerate()
+            .map(|(i, line)| {
+                let marker = if highlight_line == Some(i + 1) { '>' } else { ' ' };
+                format!("{} {:>width$} | {}", marker, i + 1, line, width = width)
+                    .trim_end()
+                    .to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+    
+    /// Lint a named prompt for common content problems
+    pub fn lint_prompt(&self, name: &str) -> Result<Vec<LintWarning>> {
+        let prompt = self.load_prompt(name)?;
+        Ok(crate::lint::lint_prompt(&prompt))
+    }
+    
+    /// Set the variable constraints checked when rendering a named prompt
+    pub fn set_variable_constraints(&mut self, template_name: &str, constraints: HashMap<String, Constraint>) {
+        self.variable_constraints.insert(template_name.to_string(), constraints);
+    }
+    
+    /// Get prompt metadata (size, modification time, etc.)
+    ///
+    /// With `refresh_info_from_disk`, size and modification time come from the
+    /// file itself and stale metadata is updated.
+    pub fn get_prompt_info(&self, name: &str) -> Result<crate::storage::PromptInfo> {
+        if self.config.refresh_info_from_disk {
+            self.storage.refresh_prompt_info(name)
+        } else {
+            self.storage.get_prompt_info(name)
+        }
+    }
+    
+    /// Verify stored prompts against the size and content hash in metadata
+    pub fn verify_integrity(&self) -> Result<Vec<IntegrityIssue>> {
+        let mut issues = Vec::new();
+        
+        for name in self.storage.list_prompts()? {
+            let info = self.storage.get_prompt_info(&name)?;
+            let content = match self.storage.load_prompt_raw(&name) {
+                Ok(content) => content,
+                Err(_) => {
+                    issues.push(IntegrityIssue { name, kind: IntegrityIssueKind::Missing });
+                    continue;
+                }
+            };
+            
+            if let Some(expected) = info.content_hash {
+                let actual = crate::storage::content_hash(&content);
+                if actual != expected {
+                    issues.push(IntegrityIssue {
+                        name: name.clone(),
+                        kind: IntegrityIssueKind::HashMismatch { expected, actual },
+                    });
+                }
+            }
+            
+            let actual_size = content.len() as u64;
+            if actual_size != info.size {
+                issues.push(IntegrityIssue {
+                    name,
+                    kind: IntegrityIssueKind::SizeMismatch { expected: info.size, actual: actual_size },
+                });
+            }
+        }
+        
+        Ok(issues)
+    }
+    
+    /// Split a named prompt into chunks that each fit a token budget
+    ///
+    /// Chunks break on paragraph boundaries; a paragraph larger than the
+    /// budget is split on sentences. Token counts use the approximate
+    /// estimator in [`tokens`].
+    pub fn chunk_prompt(&self, name: &str, max_tokens: usize) -> Result<Vec<String>> {
+        if max_tokens == 0 {
+            return Err(PromptError::Config("Token budget must be greater than zero".to_string()));
+        }
+        
+        let prompt = self.load_prompt(name)?;
+        Ok(crate::tokens::chunk_text(&prompt, max_tokens))
+    }
+    
+    /// Find prompts not modified within `older_than`, oldest first
+    pub fn stale_prompts(&self, older_than: std::time::Duration) -> Result<Vec<PromptInfo>> {
+        let Some(cutoff) = std::time::SystemTime::now().checked_sub(older_than) else {
+            return Ok(Vec::new());
+        };
+        
+        let mut stale = Vec::new();
+        for name in self.storage.list_prompts()? {
+            let info = self.storage.get_prompt_info(&name)?;
+            if info.modified_at < cutoff {
+                stale.push(info);
+            }
+        }
+        
+        stale.sort_by_key(|info| info.modified_at);
+        Ok(stale)
+    }
+    
+    /// Export metadata for every prompt as CSV
+    ///
+    /// Columns are name, size, tags (joined by `;`), created/modified
+    /// timestamps in Unix seconds, and a single-line content preview.
+    #[cfg(feature = "csv")]
+    pub fn export_metadata_csv(&self) -> Result<String> {
+        let mut rows = Vec::new();
+        for name in self.storage.list_prompts()? {
+            let info = self.storage.get_prompt_info(&name)?;
+            let preview = crate::export::preview(&self.storage.load_prompt(&name)?);
+            rows.push((info, preview));
+        }
+        crate::export::metadata_csv(&rows)
+    }
+    
+    /// Export every named prompt as JSON in another tool's schema
+    pub fn export_as(&self, mapping: ExportMapping) -> Result<String> {
+        let mut prompts = Vec::new();
+        for name in self.storage.list_prompts()? {
+            let info = self.storage.get_prompt_info(&name)?;
+            prompts.push((info, self.storage.load_prompt(&name)?));
+        }
+        Ok(serde_json::to_string_pretty(&mapping.apply(&prompts))?)
+    }
+    
+    /// Estimate the number of model tokens in text
+    ///
+    /// Each whitespace-separated word costs one token per four alphanumeric
+    /// characters (at least one), plus one per punctuation or symbol
+    /// character. Expect estimates within roughly 25% of common BPE
+    /// tokenizers for English prose and code.
+    pub fn estimate_tokens(&self, text: &str) -> usize {
+        crate::tokens::estimate_tokens(text)
+    }
+    
+    /// Estimate the token count of a named prompt
+    pub fn estimate_prompt_tokens(&self, name: &str) -> Result<usize> {
+        Ok(crate::tokens::estimate_tokens(&self.load_prompt(name)?))
+    }
+    
+    /// Estimate token counts for every prompt in the library
+    ///
+    /// Estimates are cached by the content hash recorded in metadata, so
+    /// unchanged prompts are not re-read on later calls.
+    pub fn estimate_all_tokens(&self) -> Result<BTreeMap<String, usize>> {
+        let mut estimates = BTreeMap::new();
+        
+        for name in self.storage.list_prompts()? {
+            let hash = self.storage.get_prompt_info(&name)?.content_hash;
+            let cached = hash.as_ref().and_then(|hash| self.lock_token_cache().get(hash).copied());
+            
+            let tokens = match cached {
+                Some(tokens) => tokens,
+                None => {
+                    let tokens = self.estimate_prompt_tokens(&name)?;
+                    if let Some(hash) = hash {
+                        self.lock_token_cache().insert(hash, tokens);
+                    }
+                    tokens
+                }
+            };
+            estimates.insert(name, tokens);
+        }
+        
+        Ok(estimates)
+    }
+    
+    /// Find prompts whose estimated token count exceeds a budget
+    ///
+    /// Returns each oversized prompt with its estimate, largest first
+    /// (ties ordered by name).
+    pub fn prompts_exceeding(&self, max_tokens: usize) -> Result<Vec<(String, usize)>> {
+        let mut exceeding: Vec<(String, usize)> = self.estimate_all_tokens()?
+            .into_iter()
+            .filter(|(_, tokens)| *tokens > max_tokens)
+            .collect();
+        exceeding.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(exceeding)
+    }
+    
+    /// Lock the token estimate cache
+    fn lock_token_cache(&self) -> std::sync::MutexGuard<'_, HashMap<String, usize>> {
+        self.token_cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    
+    /// Drop cached prompt content
+    fn clear_prompt_cache(&self) {
+        if let Some(cache) = &self.prompt_cache {
+            cache.clear();
+        }
+    }
+    
+    /// Drop cached token estimates
+    fn clear_token_cache(&self) {
+        self.lock_token_cache().clear();
+    }
+    
+    /// Compare this library with another, producing a read-only sync plan
+    ///
+    /// Prompts present in both libraries are compared by content hash.
+    pub fn compare_with(&self, other: &PromptManager) -> Result<SyncPlan> {
+        let ours = self.storage.list_prompts()?;
+        let theirs = other.storage.list_prompts()?;
+        let mut plan = SyncPlan::default();
+        
+        for name in &ours {
+            if !theirs.contains(name) {
+                plan.only_in_self.push(name.clone());
+                continue;
+            }
+            
+            let our_hash = crate::storage::content_hash(&self.storage.load_prompt(name)?);
+            let their_hash = crate::storage::content_hash(&other.storage.load_prompt(name)?);
+            if our_hash != their_hash {
+                plan.differing.push(name.clone());
+            }
+        }
+        
+        plan.only_in_other = theirs.into_iter()
+            .filter(|name| !ours.contains(name))
+            .collect();
+        
+        Ok(plan)
+    }
+    
+    /// Make the library match a JSON bundle exactly
+    ///
+    /// Bundle prompts and partials are added or updated (content and tags),
+    /// prompts missing from the bundle are deleted, and the bundled default
+    /// replaces the current one. Every entry is validated before anything
+    /// is written; if any write then fails, the changes already made are
+    /// rolled back and the error is returned.
+    pub fn sync_to_bundle(&mut self, bundle_json: &str) -> Result<SyncReport> {
+        let bundle: PromptBundle = serde_json::from_str(bundle_json)?;
+        let partials = bundle.partials.iter()
+            .map(|entry| (format!("{}{}", PARTIALS_NAMESPACE, entry.name), entry));
+        let prompts = bundle.prompts.iter().map(|entry| (entry.name.clone(), entry));
+        
+        let mut desired = Vec::new();
+        for (name, entry) in partials.chain(prompts) {
+            let content = self.prepare_for_save(&entry.content)
+                .map_err(|e| PromptError::InvalidPrompt(format!("Bundle prompt '{}': {}", name, e)))?;
+            desired.push((name, content, entry.tags.clone()));
+        }
+        let default = match &bundle.default {
+            Some(default) => Some(self.prepare_for_save(default)?),
+            None => None,
+        };
+        
+        let mut report = SyncReport::default();
+        let mut snapshots = Vec::new();
+        let mut changes = Vec::new();
+        for (name, content, tags) in &desired {
+            if !self.storage.prompt_exists(name) {
+                report.added.push(name.clone());
+                changes.push((name, content, tags));
+                continue;
+            }
+            let current = self.storage.load_prompt(name)?;
+            let current_tags = self.storage.get_prompt_info(name)?.tags;
+            if current == content.trim() && current_tags == *tags {
+                report.unchanged.push(name.clone());
+            } else {
+                report.updated.push(name.clone());
+                snapshots.push((name.clone(), current, current_tags));
+                changes.push((name, content, tags));
+            }
+        }
+        for name in self.storage.list_prompts()? {
+            if !desired.iter().any(|(wanted, _, _)| *wanted == name) {
+                let content = self.storage.load_prompt(&name)?;
+                let tags = self.storage.get_prompt_info(&name)?.tags;
+                snapshots.push((name.clone(), content, tags));
+                report.deleted.push(name);
+            }
+        }
+        let previous_default = self.storage.load_default()?;
+        let default = default.filter(|default| default.trim() != previous_default);
+        report.default_updated = default.is_some();
+        
+        let applied = (|| -> Result<()> {
+            if let Some(default) = &default {
+                self.storage.save_default(default)?;
+            }
+            for (name, content, tags) in &changes {
+                self.save_prompt(name, content)?;
+                self.storage.set_tags(name, tags.to_vec())?;
+            }
+            for name in &report.deleted {
+                self.delete_prompt(name)?;
+            }
+            Ok(())
+        })();
+        
+        if let Err(e) = applied {
+            warn!("Sync to bundle failed, rolling back: {}", e);
+            self.roll_back_sync(&report.added, &snapshots, &previous_default);
+            return Err(e);
+        }
+        
+        self.register_storage_partials()?;
+        info!(
+            "Synced to bundle: {} added, {} updated, {} deleted",
+            report.added.len(), report.updated.len(), report.deleted.len()
+        );
+        Ok(report)
+    }
+    
+    /// Undo a partially applied sync, logging anything that can't be restored
+    fn roll_back_sync(&mut self, added: &[String], snapshots: &[(String, String, Vec<String>)], default: &str) {
+        for name in added {
+            if self.storage.prompt_exists(name) && let Err(e) = self.delete_prompt(name) {
+                warn!("Failed to remove '{}' during rollback: {}", name, e);
+            }
+        }
+        for (name, content, tags) in snapshots {
+            let restored = self.storage.save_prompt(name, content)
+                .and_then(|()| self.storage.set_tags(name, tags.clone()));
+            if let Err(e) = restored {
+                warn!("Failed to restore '{}' during rollback: {}", name, e);
+            }
+        }
+        if let Err(e) = self.storage.save_default(default) {
+            warn!("Failed to restore default prompt during rollback: {}", e);
+        }
+        self.clear_prompt_cache();
+    }
+    
+    /// Reset to factory default prompt
+    pub fn reset_default(&mut self) -> Result<()> {
+        let factory_default = self.configured_factory_default();
+        self.save_default(&factory_default)
+    }
+    
+    /// Summarize the library in one pass over prompt metadata
+    pub fn summary(&self) -> Result<LibrarySummary> {
+        let mut summary = LibrarySummary {
+            total_prompts: 0,
+            total_size: 0,
+            largest: None,
+            most_recently_modified: None,
+            tag_count: 0,
+            default_customized: !self.default_is_factory(),
+        };
+        let mut tags = std::collections::HashSet::new();
+        
+        for name in self.storage.list_prompts()? {
+            let info = self.storage.get_prompt_info(&name)?;
+            summary.total_prompts += 1;
+            summary.total_size += info.size;
+            if summary.largest.as_ref().is_none_or(|(_, size)| info.size > *size) {
+                summary.largest = Some((name.clone(), info.size));
+            }
+            if summary.most_recently_modified.as_ref().is_none_or(|(_, time)| info.modified_at > *time) {
+                summary.most_recently_modified = Some((name.clone(), info.modified_at));
+            }
+            tags.extend(info.tags.iter().map(|tag| tag.to_lowercase()));
+        }
+        
+        summary.tag_count = tags.len();
+        Ok(summary)
+    }
+    
+    /// Check if the stored default prompt still matches the factory default
+    pub fn default_is_factory(&self) -> bool {
+        match self.storage.load_default() {
+            Ok(stored) => stored.trim() == self.configured_factory_default().trim(),
+            Err(_) => false,
+        }
+    }
+    
+    /// Get the factory default from config, falling back to the built-in one
+    fn configured_factory_default(&self) -> String {
+        self.config.factory_default.clone()
+            .unwrap_or_else(Self::factory_default_prompt)
+    }
+    
+    /// Get the factory default prompt
+    pub fn factory_default_prompt() -> String {
+        FACTORY_DEFAULT_PROMPT.to_string()
+    }
+    
+    /// Get the built-in starter prompts as name/content pairs
+    pub fn factory_prompts() -> Vec<(&'static str, String)> {
+        vec![
+            (
+                "coding",
+                "You are an expert programmer who writes clean, efficient, and well-documented code. \
+Follow the conventions of the language and codebase you are working in, and explain \
+non-obvious decisions."
+                    .to_string(),
+            ),
+            (
+                "debugging",
+                "You are a debugging specialist. Help identify and fix issues in code systematically: \
+reproduce the problem, narrow down the cause, and propose a minimal fix with an explanation."
+                    .to_string(),
+            ),
+            (
+                "review",
+                "You are a senior developer conducting code reviews. Focus on correctness, security, \
+maintainability, and adherence to best practices. Be specific and constructive."
+                    .to_string(),
+            ),
+        ]
+    }
+    
+    /// Validate a prompt and apply the save transform
+    fn prepare_for_save(&self, prompt: &str) -> Result<String> {
+        let prompt = prompt.trim();
+        self.validate_prompt(prompt)?;
+        
+        match &self.save_transform {
+            Some(transform) => {
+                let transformed = transform(prompt);
+                self.validate_prompt(&transformed)?;
+                Ok(transformed)
+            }
+            None => Ok(prompt.to_string()),
+        }
+    }
+    
+    /// Validate a prompt according to current config
+    fn validate_prompt(&self, prompt: &str) -> Result<()> {
+        self.config.validate_prompt(prompt)
+    }
+}
+
+/// Check that a profile name is safe to use in a file name
+fn validate_profile_name(profile: &str) -> Result<()> {
+    let valid = !profile.is_empty()
+        && profile.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(PromptError::Config(format!("Invalid profile name '{}'", profile)))
+    }
+}
+
+/// Set a dotted path in a JSON object, leaving values already present alone
+fn insert_path(object: &mut serde_json::Map<String, serde_json::Value>, segments: &[&str], value: String) {
+    match segments {
+        [] => {}
+        [last] => {
+            object.entry(*last).or_insert(serde_json::Value::String(value));
+        }
+        [first, rest @ ..] => {
+            let entry = object.entry(*first).or_insert_with(|| serde_json::json!({}));
+            if let Some(inner) = entry.as_object_mut() {
+                insert_path(inner, rest, value);
+            }
+        }
+    }
+}
+
+/// Map a stored content type to a MIME type for serving
+fn mime_type_for(content_type: Option<&str>) -> &'static str {
+    let essence = content_type
+        .and_then(|ct| ct.split(';').next())
+        .map(|ct| ct.trim().to_ascii_lowercase());
+    
+    match essence.as_deref() {
+        Some("application/json" | "json") => "application/json",
+        Some("text/markdown" | "markdown") => "text/markdown; charset=utf-8",
+        Some("application/yaml" | "text/yaml" | "yaml") => "application/yaml",
+        Some("text/html" | "html") => "text/html; charset=utf-8",
+        _ => "text/plain; charset=utf-8",
+    }
+}
+
+impl Default for PromptManager {
+    fn default() -> Self {
+        Self::new().expect("Failed to create default PromptManager")
+    }
+}
+
+// Re-export important types
+#[cfg(feature = "tokio")]
+pub use crate::async_manager::AsyncPromptManager;
+#[cfg(feature = "tokio")]
+pub use crate::async_storage::{AsyncFileStorage, AsyncPromptStorage};
+pub use crate::batch::BatchReport;
+pub use crate::bundle::{BundleEntry, PromptBundle};
+pub use crate::constraint::Constraint;
+#[cfg(feature = "encryption")]
+pub use crate::encryption::EncryptedStorage;
+pub use crate::events::{ChangeCallback, ChangeKind, ChangeNotifier, PromptChangeEvent, WatchHandle};
+pub use crate::export::{ExportMapping, ExportShape};
+pub use crate::format::PromptFormat;
+pub use crate::import::ImportReport;
+pub use crate::include::{RenderTrace, TraceEntry};
+pub use crate::integrity::{Discrepancy, DiscrepancyKind, IntegrityIssue, IntegrityIssueKind};
+pub use crate::lint::{LintKind, LintWarning};
+pub use crate::messages::Message;
+pub use crate::search::{SearchHit, SearchQuery};
+pub use crate::shared::{SharedPromptManager, SharedState};
+#[cfg(feature = "sqlite")]
+pub use crate::sqlite::SqliteStorage;
+pub use crate::summary::LibrarySummary;
+pub use crate::storage::{FileStorageOptions, MemoryStorage, NameSanitizer, PromptInfo, ReconcileReport, StorageBase};
+pub use crate::sync::{SyncPlan, SyncReport};
+pub use crate::template::{MissingVar, RenderConfig, RenderMode, RenderOptions};
+pub use crate::transform::{SaveTransform, TransformPipeline};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    
+    fn test_manager(temp_dir: &TempDir) -> PromptManager {
+        let config = Config {
+            storage_dir: Some(temp_dir.path().to_path_buf()),
+            ..Config::default()
+        };
+        PromptManager::with_config(config).unwrap()
+    }
+    
+    fn strip_comment(line: &str) -> String {
+        line.split('#').next().unwrap_or("").to_string()
+    }
+    
+    fn normalize_whitespace(line: &str) -> String {
+        line.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+    
+    #[test]
+    fn test_transform_pipeline_on_load() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("styled", "You are   helpful. # tone\n  Be    concise.").unwrap();
+        
+        manager.set_transform_pipeline(
+            TransformPipeline::new()
+                .then(strip_comment)
+                .then(normalize_whitespace),
+        );
+        assert_eq!(manager.load_prompt("styled").unwrap(), "You are helpful.\nBe concise.");
+        
+        // Reversed order leaves the whitespace that preceded the comment
+        manager.set_transform_pipeline(
+            TransformPipeline::new()
+                .then(normalize_whitespace)
+                .then(strip_comment),
+        );
+        assert_eq!(manager.load_prompt("styled").unwrap(), "You are helpful. \nBe concise.");
+        
+        manager.clear_transform_pipeline();
+        assert_eq!(manager.load_prompt("styled").unwrap(), "You are   helpful. # tone\n  Be    concise.");
+    }
+    
+    #[test]
+    fn test_verify_integrity_detects_out_of_band_edit() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("stable", "Untouched prompt").unwrap();
+        manager.save_prompt("edited", "Original content").unwrap();
+        assert!(manager.verify_integrity().unwrap().is_empty());
+        
+        std::fs::write(temp_dir.path().join("edited.txt"), "Tampered content here").unwrap();
+        
+        let issues = manager.verify_integrity().unwrap();
+        assert_eq!(issues.len(), 2);
+        assert!(issues.iter().all(|issue| issue.name == "edited"));
+        assert!(matches!(issues[0].kind, IntegrityIssueKind::HashMismatch { .. }));
+        assert_eq!(
+            issues[1].kind,
+            IntegrityIssueKind::SizeMismatch { expected: 16, actual: 21 }
+        );
+    }
+    
+    #[test]
+    fn test_save_transform_applied_before_write() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.set_save_transform(Box::new(|prompt: &str| {
+            let lines: Vec<&str> = prompt.lines().map(str::trim_end).collect();
+            format!("{}\n", lines.join("\n"))
+        }));
+        
+        manager.save_prompt("styled", "First line   \nSecond line\t").unwrap();
+        let stored = std::fs::read_to_string(temp_dir.path().join("styled.txt")).unwrap();
+        assert_eq!(stored, "First line\nSecond line\n");
+        assert_eq!(manager.load_prompt("styled").unwrap(), "First line\nSecond line");
+        
+        manager.save_default("Default   \nprompt  ").unwrap();
+        assert_eq!(manager.load_default().unwrap(), "Default\nprompt");
+    }
+    
+    #[test]
+    fn test_verify_integrity_after_newline_transform() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.set_save_transform(Box::new(|prompt: &str| format!("{}\n", prompt)));
+        
+        manager.save_prompt("hello", "Hello").unwrap();
+        manager.save_prompts(&[("batch".to_string(), "Batched".to_string())]).unwrap();
+        assert_eq!(manager.get_prompt_info("hello").unwrap().size, 6);
+        assert!(manager.verify_integrity().unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_save_transform_output_is_validated() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config {
+            storage_dir: Some(temp_dir.path().to_path_buf()),
+            max_prompt_length: 20,
+            ..Config::default()
+        };
+        let mut manager = PromptManager::with_config(config).unwrap();
+        manager.set_save_transform(Box::new(|prompt: &str| format!("# Heading\n\n{}", prompt)));
+        
+        let result = manager.save_prompt("long", "Fits before heading");
+        assert!(matches!(result, Err(PromptError::InvalidPrompt(_))));
+        assert!(!manager.prompt_exists("long"));
+    }
+    
+    #[cfg(feature = "markdown")]
+    #[test]
+    fn test_markdown_default_format() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config {
+            storage_dir: Some(temp_dir.path().to_path_buf()),
+            default_format: PromptFormat::Markdown,
+            ..Config::default()
+        };
+        let mut manager = PromptManager::with_config(config).unwrap();
+        manager.save_default("# Assistant\n\nYou are **precise**.").unwrap();
+        
+        assert_eq!(manager.load_default().unwrap(), "Assistant\n\nYou are precise.");
+    }
+    
+    #[test]
+    fn test_factory_prompts_are_valid() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = test_manager(&temp_dir);
+        let prompts = PromptManager::factory_prompts();
+        
+        assert!(!prompts.is_empty());
+        for (name, content) in prompts {
+            assert!(!name.is_empty());
+            assert!(manager.validate_prompt(&content).is_ok(), "factory prompt '{}' is invalid", name);
+        }
+    }
+    
+    #[test]
+    fn test_variable_constraints() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("expert", "You are an expert in {{language}}. {{focus}}").unwrap();
+        
+        let mut constraints = HashMap::new();
+        constraints.insert(
+            "language".to_string(),
+            Constraint::OneOf(vec!["Rust".to_string(), "Go".to_string()]),
+        );
+        constraints.insert("focus".to_string(), Constraint::MaxLen(10));
+        manager.set_variable_constraints("expert", constraints);
+        
+        let mut vars = HashMap::new();
+        vars.insert("language".to_string(), "Rust".to_string());
+        vars.insert("focus".to_string(), "Safety.".to_string());
+        assert_eq!(manager.render_prompt("expert", &vars).unwrap(), "You are an expert in Rust. Safety.");
+        
+        vars.insert("language".to_string(), "COBOL".to_string());
+        let err = manager.render_prompt("expert", &vars).unwrap_err();
+        assert!(matches!(err, PromptError::ConstraintViolation(_)));
+        assert!(err.to_string().contains("'language' must be one of [Rust, Go]"));
+        
+        vars.insert("language".to_string(), "Go".to_string());
+        vars.insert("focus".to_string(), "Performance tuning".to_string());
+        let err = manager.render_prompt("expert", &vars).unwrap_err();
+        assert!(matches!(err, PromptError::ConstraintViolation(_)));
+        assert!(err.to_string().contains("'focus' exceeds maximum length of 10"));
+    }
+    
+    #[test]
+    fn test_load_prompt_bytes_mime_types() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        let json = r#"{"role": "system", "content": "Be helpful"}"#;
+        manager.save_prompt("structured", json).unwrap();
+        manager.set_content_type("structured", "application/json").unwrap();
+        manager.save_prompt("plain", "Be helpful").unwrap();
+        
+        let (bytes, mime) = manager.load_prompt_bytes("structured").unwrap();
+        assert_eq!(bytes, json.as_bytes());
+        assert_eq!(mime, "application/json");
+        
+        let (bytes, mime) = manager.load_prompt_bytes("plain").unwrap();
+        assert_eq!(bytes, b"Be helpful");
+        assert_eq!(mime, "text/plain; charset=utf-8");
+        
+        // Content type survives a re-save
+        manager.save_prompt("structured", json).unwrap();
+        assert_eq!(manager.load_prompt_bytes("structured").unwrap().1, "application/json");
+    }
+    
+    #[test]
+    fn test_compare_with_produces_sync_plan() {
+        let local_dir = TempDir::new().unwrap();
+        let golden_dir = TempDir::new().unwrap();
+        let mut local = test_manager(&local_dir);
+        let mut golden = test_manager(&golden_dir);
+        
+        local.save_prompt("shared", "Same everywhere").unwrap();
+        golden.save_prompt("shared", "Same everywhere").unwrap();
+        local.save_prompt("coding", "Local coding prompt").unwrap();
+        golden.save_prompt("coding", "Golden coding prompt").unwrap();
+        local.save_prompt("draft", "Only local").unwrap();
+        golden.save_prompt("review", "Only golden").unwrap();
+        
+        let plan = local.compare_with(&golden).unwrap();
+        assert_eq!(plan.only_in_self, vec!["draft"]);
+        assert_eq!(plan.only_in_other, vec!["review"]);
+        assert_eq!(plan.differing, vec!["coding"]);
+        
+        assert!(local.compare_with(&local).unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_chunk_prompt_on_paragraphs() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        let paragraph = "This paragraph has exactly ten words in it for testing.";
+        let oversized = "Short sentence one. Short sentence two. Short sentence three. Short sentence four.";
+        let prompt = format!("{}\n\n{}\n\n{}\n\n{}", paragraph, paragraph, paragraph, oversized);
+        manager.save_prompt("long", &prompt).unwrap();
+        
+        let budget = crate::tokens::estimate_tokens(paragraph) * 2 + 1;
+        let chunks = manager.chunk_prompt("long", budget).unwrap();
+        
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0], format!("{}\n\n{}", paragraph, paragraph));
+        assert!(chunks[2].starts_with("Short sentence"));
+        for chunk in &chunks {
+            assert!(crate::tokens::estimate_tokens(chunk) <= budget);
+        }
+        
+        // A paragraph over budget is split on sentences
+        let small = manager.chunk_prompt("long", 8).unwrap();
+        assert!(small.contains(&"Short sentence one.".to_string()));
+        assert!(small.iter().all(|chunk| crate::tokens::estimate_tokens(chunk) <= 8));
+        
+        assert!(manager.chunk_prompt("long", 0).is_err());
+    }
+    
+    #[test]
+    fn test_default_is_factory() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        assert!(manager.default_is_factory());
+        
+        manager.save_default("My customized default").unwrap();
+        assert!(!manager.default_is_factory());
+        
+        manager.reset_default().unwrap();
+        assert!(manager.default_is_factory());
+    }
+    
+    #[test]
+    fn test_default_is_factory_with_configured_default() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config {
+            storage_dir: Some(temp_dir.path().to_path_buf()),
+            factory_default: Some("Company default prompt".to_string()),
+            ..Config::default()
+        };
+        let mut manager = PromptManager::with_config(config).unwrap();
+        assert_eq!(manager.load_default().unwrap(), "Company default prompt");
+        assert!(manager.default_is_factory());
+        
+        manager.save_default(&PromptManager::factory_default_prompt()).unwrap();
+        assert!(!manager.default_is_factory());
+    }
+    
+    #[test]
+    fn test_get_static_and_templated() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("static", "You are a helpful assistant.").unwrap();
+        manager.save_prompt("templated", "You are a {{role}} assistant.").unwrap();
+        
+        let mut vars = HashMap::new();
+        vars.insert("role".to_string(), "coding".to_string());
+        
+        assert_eq!(manager.get("static", None).unwrap(), "You are a helpful assistant.");
+        assert_eq!(manager.get("static", Some(&vars)).unwrap(), "You are a helpful assistant.");
+        assert_eq!(manager.get("templated", Some(&vars)).unwrap(), "You are a coding assistant.");
+        assert_eq!(manager.get("templated", None).unwrap(), "You are a  assistant.");
+    }
+    
+    #[test]
+    fn test_stale_prompts() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("fresh", "Recently edited").unwrap();
+        manager.save_prompt("old", "Abandoned long ago").unwrap();
+        manager.save_prompt("ancient", "Abandoned even longer ago").unwrap();
+        
+        // Backdate two entries directly in metadata
+        let metadata_path = temp_dir.path().join("metadata.json");
+        let mut metadata: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&metadata_path).unwrap()).unwrap();
+        metadata["prompts"]["old"]["modified_at"]["secs_since_epoch"] = 2_000_000.into();
+        metadata["prompts"]["ancient"]["modified_at"]["secs_since_epoch"] = 1_000_000.into();
+        std::fs::write(&metadata_path, metadata.to_string()).unwrap();
+        
+        let day = std::time::Duration::from_secs(24 * 60 * 60);
+        let stale = manager.stale_prompts(day * 30).unwrap();
+        let names: Vec<&str> = stale.iter().map(|info| info.name.as_str()).collect();
+        assert_eq!(names, vec!["ancient", "old"]);
+    }
+    
+    #[test]
+    fn test_render_extending() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt(
+            "base",
+            "You are a {{role}}.\n{{#block \"rules\"}}Follow general rules.{{/block}}\n{{#block \"tone\"}}Be formal.{{/block}}",
+        ).unwrap();
+        manager.save_prompt("child", "{{#override \"rules\"}}Only write {{language}}.{{/override}}").unwrap();
+        
+        let mut vars = HashMap::new();
+        vars.insert("role".to_string(), "programmer".to_string());
+        vars.insert("language".to_string(), "Rust".to_string());
+        
+        let rendered = manager.render_extending("child", "base", &vars).unwrap();
+        assert_eq!(rendered, "You are a programmer.\nOnly write Rust.\nBe formal.");
+        
+        // The base renders its defaults on its own
+        let rendered = manager.render_prompt("base", &vars).unwrap();
+        assert_eq!(rendered, "You are a programmer.\nFollow general rules.\nBe formal.");
+    }
+    
+    #[test]
+    fn test_tags() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("coding", "Write code").unwrap();
+        
+        manager.set_tags("coding", vec!["rust".to_string(), " review ".to_string(), "rust".to_string()]).unwrap();
+        assert_eq!(manager.get_tags("coding").unwrap(), vec!["rust", "review"]);
+        
+        // Tags survive a re-save
+        manager.save_prompt("coding", "Write better code").unwrap();
+        assert_eq!(manager.get_tags("coding").unwrap(), vec!["rust", "review"]);
+        
+        assert!(matches!(manager.set_tags("missing", vec![]), Err(PromptError::PromptNotFound(_))));
+    }
+    
+    #[cfg(feature = "csv")]
+    #[test]
+    fn test_export_metadata_csv() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("alpha", "Plain prompt").unwrap();
+        manager.save_prompt("beta", "Says \"hello\", then waits").unwrap();
+        manager.save_prompt("gamma", "Tagged prompt").unwrap();
+        manager.set_tags("gamma", vec!["a,b".to_string(), "c".to_string()]).unwrap();
+        
+        let csv = manager.export_metadata_csv().unwrap();
+        let mut reader = csv::Reader::from_reader(csv.as_bytes());
+        assert_eq!(
+            reader.headers().unwrap(),
+            vec!["name", "size", "tags", "created_at", "modified_at", "preview"]
+        );
+        
+        let rows: Vec<csv::StringRecord> = reader.records().map(|r| r.unwrap()).collect();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(&rows[1][0], "beta");
+        assert_eq!(&rows[1][5], "Says \"hello\", then waits");
+        assert_eq!(&rows[2][2], "a,b;c");
+    }
+    
+    #[test]
+    fn test_save_prompt_validate_render() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        
+        let warnings = manager.save_prompt_validate_render("benign", "Hello {{name}}, you are a {{role}}.").unwrap();
+        assert!(warnings.is_empty());
+        
+        let warnings = manager.save_prompt_validate_render("broken", "{{> safety_preamble}}\nBe helpful.").unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("safety_preamble"));
+        assert!(manager.prompt_exists("broken"));
+    }
+    
+    #[test]
+    fn test_tag_counts() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        for name in ["alpha", "beta", "gamma", "delta"] {
+            manager.save_prompt(name, "Tagged prompt").unwrap();
+        }
+        let tags = |list: &[&str]| list.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        manager.set_tags("alpha", tags(&["Rust", "review"])).unwrap();
+        manager.set_tags("beta", tags(&["rust", "testing"])).unwrap();
+        manager.set_tags("gamma", tags(&["RUST", "Review"])).unwrap();
+        
+        let counts = manager.tag_counts().unwrap();
+        assert_eq!(counts.len(), 3);
+        assert_eq!(counts["Rust"], 3);
+        assert_eq!(counts["review"], 2);
+        assert_eq!(counts["testing"], 1);
+    }
+    
+    #[test]
+    fn test_watch_prompt_filters_events() {
+        use std::sync::{Arc, Mutex};
+        
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        let watched = Arc::new(Mutex::new(Vec::new()));
+        let all = Arc::new(Mutex::new(Vec::new()));
+        
+        let sink = Arc::clone(&watched);
+        let handle = manager.watch_prompt("coding", Box::new(move |event| {
+            sink.lock().unwrap().push(event.clone());
+        }));
+        let sink = Arc::clone(&all);
+        let _global = manager.subscribe(Box::new(move |event| {
+            sink.lock().unwrap().push(event.clone());
+        }));
+        
+        manager.save_prompt("coding", "First").unwrap();
+        manager.save_prompt("writing", "Unrelated").unwrap();
+        manager.save_prompt("coding", "Second").unwrap();
+        manager.delete_prompt("writing").unwrap();
+        manager.delete_prompt("coding").unwrap();
+        
+        let kinds: Vec<ChangeKind> = watched.lock().unwrap().iter().map(|e| e.kind.clone()).collect();
+        assert_eq!(kinds, vec![ChangeKind::Created, ChangeKind::Modified, ChangeKind::Deleted]);
+        assert_eq!(all.lock().unwrap().len(), 5);
+        
+        // Dropping the handle unsubscribes
+        drop(handle);
+        manager.save_prompt("coding", "Third").unwrap();
+        assert_eq!(watched.lock().unwrap().len(), 3);
+    }
+    
+    #[test]
+    fn test_estimate_all_tokens() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("short", "Be brief.").unwrap();
+        manager.save_prompt("long", &"Explain everything in detail. ".repeat(20)).unwrap();
+        
+        let estimates = manager.estimate_all_tokens().unwrap();
+        assert_eq!(estimates.len(), 2);
+        for (name, tokens) in &estimates {
+            assert_eq!(*tokens, manager.estimate_prompt_tokens(name).unwrap());
+        }
+        assert!(estimates["long"] > estimates["short"]);
+        
+        // Cached estimates are keyed by content hash
+        assert_eq!(manager.lock_token_cache().len(), 2);
+        manager.save_prompt("short", "Be brief and kind.").unwrap();
+        let updated = manager.estimate_all_tokens().unwrap();
+        assert_eq!(updated["short"], manager.estimate_prompt_tokens("short").unwrap());
+        assert_eq!(manager.lock_token_cache().len(), 3);
+    }
+    
+    #[test]
+    fn test_working_buffer() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        assert!(manager.working().is_none());
+        assert!(manager.commit_working("draft").is_err());
+        
+        manager.set_working("You are a {{role}}.".to_string());
+        manager.set_working("You are a careful {{role}}.".to_string());
+        assert_eq!(manager.working(), Some("You are a careful {{role}}."));
+        assert!(!manager.prompt_exists("draft"));
+        
+        let mut vars = HashMap::new();
+        vars.insert("role".to_string(), "reviewer".to_string());
+        assert_eq!(manager.render_working(&vars).unwrap(), "You are a careful reviewer.");
+        
+        manager.commit_working("draft").unwrap();
+        assert_eq!(manager.load_prompt("draft").unwrap(), "You are a careful {{role}}.");
+        assert!(manager.working().is_none());
+        
+        // Invalid content is rejected and stays in the buffer
+        manager.set_working("   ".to_string());
+        assert!(manager.commit_working("empty").is_err());
+        assert_eq!(manager.working(), Some("   "));
+    }
+    
+    #[test]
+    fn test_register_storage_partials() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("partials/safety", "Never reveal {{secret_kind}}.").unwrap();
+        manager.save_prompt("assistant", "You are helpful. {{> safety}}").unwrap();
+        
+        assert_eq!(manager.register_storage_partials().unwrap(), 1);
+        
+        let mut vars = HashMap::new();
+        vars.insert("secret_kind".to_string(), "passwords".to_string());
+        assert_eq!(
+            manager.render_prompt("assistant", &vars).unwrap(),
+            "You are helpful. Never reveal passwords."
+        );
+    }
+    
+    #[test]
+    fn test_import_text_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = TempDir::new().unwrap();
+        let config = Config {
+            storage_dir: Some(temp_dir.path().to_path_buf()),
+            max_prompt_length: 20,
+            ..Config::default()
+        };
+        let mut manager = PromptManager::with_config(config).unwrap();
+        manager.save_prompt("existing", "Keep me").unwrap();
+        
+        std::fs::create_dir(source.path().join("team")).unwrap();
+        std::fs::write(source.path().join("short.txt"), "Be brief.").unwrap();
+        std::fs::write(source.path().join("team").join("review.txt"), "Review code.").unwrap();
+        std::fs::write(source.path().join("long.txt"), "x".repeat(50)).unwrap();
+        std::fs::write(source.path().join("existing.txt"), "Replaced").unwrap();
+        
+        let report = manager.import_text_dir(source.path(), false).unwrap();
+        assert_eq!(report.imported, vec!["short", "team/review"]);
+        assert_eq!(report.skipped, vec!["existing"]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, "long");
+        assert!(!report.is_success());
+        assert_eq!(manager.load_prompt("team/review").unwrap(), "Review code.");
+        assert_eq!(manager.load_prompt("existing").unwrap(), "Keep me");
+        
+        let report = manager.import_text_dir(source.path(), true).unwrap();
+        assert!(report.skipped.is_empty());
+        assert_eq!(manager.load_prompt("existing").unwrap(), "Replaced");
+    }
+    
+    #[test]
+    fn test_render_prompt_compact() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt(
+            "reviewer",
+            "Review   the {{language}} code.\n\n{{#optional}}\nAlso suggest tests.\n{{/optional}}\nBe concise.",
+        ).unwrap();
+        
+        let mut vars = HashMap::new();
+        vars.insert("language".to_string(), "Rust".to_string());
+        assert_eq!(
+            manager.render_prompt("reviewer", &vars).unwrap(),
+            "Review   the Rust code.\n\nAlso suggest tests.\nBe concise."
+        );
+        assert_eq!(
+            manager.render_prompt_compact("reviewer", &vars).unwrap(),
+            "Review the Rust code.\nBe concise."
+        );
+    }
+    
+    #[test]
+    fn test_group_by_tag() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("coder", "Write code").unwrap();
+        manager.save_prompt("tester", "Write tests").unwrap();
+        manager.save_prompt("writer", "Write prose").unwrap();
+        manager.save_prompt("plain", "Nothing special").unwrap();
+        manager.set_tags("coder", vec!["dev".to_string(), "Rust".to_string()]).unwrap();
+        manager.set_tags("tester", vec!["rust".to_string(), "qa".to_string()]).unwrap();
+        manager.set_tags("writer", vec!["docs".to_string()]).unwrap();
+        
+        let groups = manager.group_by_tag().unwrap();
+        assert_eq!(groups.keys().collect::<Vec<_>>(), vec![UNTAGGED_GROUP, "Rust", "dev", "docs", "qa"]);
+        assert_eq!(groups["Rust"], vec!["coder", "tester"]);
+        assert_eq!(groups["dev"], vec!["coder"]);
+        assert_eq!(groups["qa"], vec!["tester"]);
+        assert_eq!(groups[UNTAGGED_GROUP], vec!["plain"]);
+    }
+    
+    #[test]
+    fn test_apply_config_preserves_state() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("partials/sig", "-- {{upper team}}").unwrap();
+        manager.register_storage_partials().unwrap();
+        manager.set_transform_pipeline(TransformPipeline::new().then(normalize_whitespace));
+        let events = std::sync::Arc::new(Mutex::new(0));
+        let seen = events.clone();
+        let _handle = manager.subscribe(Box::new(move |_| *seen.lock().unwrap() += 1));
+        
+        let config = Config { max_prompt_length: 30, ..manager.config.clone() };
+        manager.apply_config(config).unwrap();
+        
+        assert!(matches!(manager.save_prompt("long", &"x".repeat(40)), Err(PromptError::InvalidPrompt(_))));
+        manager.save_prompt("note", "Thanks,   {{> sig}}").unwrap();
+        
+        let mut vars = HashMap::new();
+        vars.insert("team".to_string(), "core".to_string());
+        assert_eq!(manager.render_prompt("note", &vars).unwrap(), "Thanks, -- CORE");
+        assert_eq!(*events.lock().unwrap(), 1);
+    }
+    
+    #[test]
+    fn test_apply_config_rejects_storage_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        let config = Config { prompt_extension: "md".to_string(), ..manager.config.clone() };
+        
+        let err = manager.apply_config(config).unwrap_err();
+        assert!(matches!(err, PromptError::Config(ref msg) if msg.contains("prompt_extension")));
+        assert_eq!(manager.config.prompt_extension, "txt");
+    }
+    
+    #[test]
+    fn test_diff_renders() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("persona", "You are an assistant.\nSpeak {{tone}}.\nAnswer in English.").unwrap();
+        
+        let mut a = HashMap::new();
+        a.insert("tone".to_string(), "formally".to_string());
+        let mut b = HashMap::new();
+        b.insert("tone".to_string(), "casually".to_string());
+        
+        let diff = manager.diff_renders("persona", &a, &b).unwrap();
+        let changed: Vec<&str> = diff.lines()
+            .filter(|line| (line.starts_with('-') || line.starts_with('+')) && !line.starts_with("---") && !line.starts_with("+++"))
+            .collect();
+        assert!(diff.starts_with("--- persona (a)\n+++ persona (b)\n"));
+        assert_eq!(changed, vec!["-Speak formally.", "+Speak casually."]);
+        
+        assert_eq!(manager.diff_renders("persona", &a, &a).unwrap(), "");
+    }
+    
+    #[test]
+    fn test_search() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("coder", "Write Rust code.\nAdd tests.").unwrap();
+        manager.save_prompt("reviewer", "Review the code.\nSuggest RUST idioms.").unwrap();
+        manager.save_prompt("writer", "Write documentation.").unwrap();
+        
+        let hits = manager.search(&SearchQuery::new("rust")).unwrap();
+        let found: Vec<(&str, usize)> = hits.iter().map(|h| (h.name.as_str(), h.line_number)).collect();
+        assert_eq!(found, vec![("coder", 1), ("reviewer", 2)]);
+        
+        let hits = manager.search(&SearchQuery::new("Rust").case_sensitive(true)).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].line, "Write Rust code.");
+    }
+    
+    #[test]
+    fn test_prompts_exceeding() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("tiny", "Be brief.").unwrap();
+        manager.save_prompt("medium", &"word ".repeat(30)).unwrap();
+        manager.save_prompt("large", &"word ".repeat(80)).unwrap();
+        
+        let medium = crate::tokens::estimate_tokens(&manager.load_prompt("medium").unwrap());
+        let large = crate::tokens::estimate_tokens(&manager.load_prompt("large").unwrap());
+        assert_eq!(
+            manager.prompts_exceeding(20).unwrap(),
+            vec![("large".to_string(), large), ("medium".to_string(), medium)]
+        );
+        assert_eq!(manager.prompts_exceeding(large).unwrap(), vec![]);
+    }
+    
+    #[test]
+    fn test_bundle_round_trip_with_partials() {
+        let source_dir = TempDir::new().unwrap();
+        let mut source = test_manager(&source_dir);
+        source.save_prompt("partials/footer", "Signed, {{upper team}}").unwrap();
+        source.save_prompt("letter", "Dear {{name}},\n{{> footer}}").unwrap();
+        source.set_tags("letter", vec!["mail".to_string()]).unwrap();
+        
+        let json = source.export_bundle().unwrap();
+        let bundle: PromptBundle = serde_json::from_str(&json).unwrap();
+        assert_eq!(bundle.partials.len(), 1);
+        assert_eq!(bundle.partials[0].name, "footer");
+        assert_eq!(bundle.prompts.len(), 1);
+        assert_eq!(bundle.helpers, vec!["upper"]);
+        
+        let target_dir = TempDir::new().unwrap();
+        let mut target = test_manager(&target_dir);
+        let report = target.import_bundle(&json, false).unwrap();
+        assert_eq!(report.imported, vec!["partials/footer", "letter"]);
+        assert!(report.missing_helpers.is_empty());
+        assert_eq!(target.get_tags("letter").unwrap(), vec!["mail"]);
+        
+        let mut vars = HashMap::new();
+        vars.insert("name".to_string(), "Ada".to_string());
+        vars.insert("team".to_string(), "core".to_string());
+        assert_eq!(target.render_prompt("letter", &vars).unwrap(), "Dear Ada,\nSigned, CORE");
+    }
+    
+    #[test]
+    fn test_import_bundle_reports_missing_helpers() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        let json = r#"{"version": "1", "prompts": [{"name": "a", "content": "{{shout x}}"}], "helpers": ["shout", "upper"]}"#;
+        
+        let report = manager.import_bundle(json, false).unwrap();
+        assert_eq!(report.imported, vec!["a"]);
+        assert_eq!(report.missing_helpers, vec!["shout"]);
+    }
+    
+    #[test]
+    fn test_render_with_trace() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("partials/safety", "Stay safe.").unwrap();
+        manager.save_prompt("style", "Write for {{audience}}. {{> safety}}").unwrap();
+        manager.save_prompt("assistant", "You help.\n{{> style}}\n{{> safety}}").unwrap();
+        
+        let mut vars = HashMap::new();
+        vars.insert("audience".to_string(), "kids".to_string());
+        let (rendered, trace) = manager.render_with_trace("assistant", &vars).unwrap();
+        
+        assert_eq!(rendered, "You help.\nWrite for kids. Stay safe.\nStay safe.");
+        let entries: Vec<(&str, usize)> = trace.entries.iter().map(|e| (e.name.as_str(), e.depth)).collect();
+        assert_eq!(
+            entries,
+            vec![("assistant", 0), ("style", 1), ("partials/safety", 2), ("partials/safety", 1)]
+        );
+    }
+    
+    #[test]
+    fn test_env_overrides() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("greeting", "Hello from the base prompt").unwrap();
+        manager.save_prompt_for_env("greeting", "prod", "Hello from production").unwrap();
+        
+        assert_eq!(manager.load_prompt_for_env("greeting", "prod").unwrap(), "Hello from production");
+        assert_eq!(manager.load_prompt_for_env("greeting", "dev").unwrap(), "Hello from the base prompt");
+        assert!(manager.list_prompts().unwrap().contains(&"greeting@prod".to_string()));
+        assert!(matches!(
+            manager.load_prompt_for_env("missing", "dev"),
+            Err(PromptError::PromptNotFound(_))
+        ));
+    }
+    
+    #[test]
+    fn test_env_override_does_not_collide_with_sanitized_name() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("greeting", "Hello from the base prompt").unwrap();
+        manager.save_prompt("greeting_prod", "A separate prompt").unwrap();
+        
+        // Both names sanitize to greeting_prod.txt, which is already taken
+        let err = manager.save_prompt_for_env("greeting", "prod", "Hello from production").unwrap_err();
+        assert!(matches!(err, PromptError::InvalidName(ref msg) if msg.contains("greeting_prod")));
+        assert_eq!(manager.load_prompt("greeting_prod").unwrap(), "A separate prompt");
+        assert_eq!(manager.load_prompt_for_env("greeting", "prod").unwrap(), "Hello from the base prompt");
+        assert!(manager.delete_prompt("greeting@prod").is_err());
+        
+        // The other order is rejected the same way
+        manager.delete_prompt("greeting_prod").unwrap();
+        manager.save_prompt_for_env("greeting", "prod", "Hello from production").unwrap();
+        assert!(matches!(manager.save_prompt("greeting_prod", "Clobber"), Err(PromptError::InvalidName(_))));
+        assert_eq!(manager.load_prompt_for_env("greeting", "prod").unwrap(), "Hello from production");
+        assert!(!manager.prompt_exists("greeting_prod"));
+    }
+    
+    #[test]
+    fn test_format_with_lines() {
+        let text = "First line\nSecond {{line\nThird line";
+        assert_eq!(
+            PromptManager::format_with_lines(text, Some(2)),
+            "  1 | First line\n> 2 | Second {{line\n  3 | Third line"
+        );
+        assert_eq!(PromptManager::format_with_lines("a\n\nb", None), "  1 | a\n  2 |\n  3 | b");
+        
+        let long: String = (1..=10).map(|i| format!("line {}\n", i)).collect();
+        let formatted = PromptManager::format_with_lines(&long, Some(10));
+        assert!(formatted.starts_with("   1 | line 1\n"));
+        assert!(formatted.ends_with("> 10 | line 10"));
+    }
+    
+    #[test]
+    fn test_vacuum_removes_dangling_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("kept", "Still here").unwrap();
+        manager.save_prompt("ghost", "Deleted by hand").unwrap();
+        std::fs::remove_file(temp_dir.path().join("ghost.txt")).unwrap();
+        assert_eq!(manager.list_prompts().unwrap(), vec!["ghost", "kept"]);
+        
+        assert_eq!(manager.vacuum().unwrap(), 1);
+        assert_eq!(manager.list_prompts().unwrap(), vec!["kept"]);
+        assert_eq!(manager.vacuum().unwrap(), 0);
+    }
+    
+    #[test]
+    fn test_rename_prompt_notifies() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("old", "Content").unwrap();
+        let events = std::sync::Arc::new(Mutex::new(Vec::new()));
+        let seen = events.clone();
+        let _handle = manager.watch_prompt("old", Box::new(move |event| seen.lock().unwrap().push(event.clone())));
+        
+        manager.rename_prompt("old", "new").unwrap();
+        assert_eq!(manager.load_prompt("new").unwrap(), "Content");
+        assert!(!manager.prompt_exists("old"));
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![PromptChangeEvent { name: "new".to_string(), kind: ChangeKind::Renamed { from: "old".to_string() } }]
+        );
+    }
+    
+    #[test]
+    fn test_copy_prompt() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("coding", "Write {{language}} code").unwrap();
+        
+        manager.copy_prompt("coding", "coding-v2", false).unwrap();
+        manager.save_prompt("coding-v2", "Write idiomatic {{language}} code").unwrap();
+        assert_eq!(manager.load_prompt("coding").unwrap(), "Write {{language}} code");
+        assert_eq!(manager.list_prompts().unwrap(), vec!["coding", "coding-v2"]);
+        assert!(matches!(
+            manager.copy_prompt("coding", "coding-v2", false),
+            Err(PromptError::PromptAlreadyExists(_))
+        ));
+    }
+    
+    #[test]
+    fn test_with_memory_storage() {
+        let mut manager = PromptManager::with_storage(Box::new(MemoryStorage::new()), Config::default());
+        manager.save_prompt("greeting", "Hello {{name}}").unwrap();
+        manager.save_default("You are terse.").unwrap();
+        
+        let mut vars = HashMap::new();
+        vars.insert("name".to_string(), "Ada".to_string());
+        assert_eq!(manager.render_prompt("greeting", &vars).unwrap(), "Hello Ada");
+        assert_eq!(manager.load_default().unwrap(), "You are terse.");
+        assert_eq!(manager.list_prompts().unwrap(), vec!["greeting"]);
+    }
+    
+    #[test]
+    fn test_summary() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        let empty = manager.summary().unwrap();
+        assert_eq!(empty.total_prompts, 0);
+        assert_eq!(empty.largest, None);
+        assert!(!empty.default_customized);
+        
+        manager.save_prompt("short", "Hi").unwrap();
+        manager.save_prompt("long", "A considerably longer prompt").unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        manager.save_prompt("latest", "Newest").unwrap();
+        manager.set_tags("short", vec!["a".to_string(), "B".to_string()]).unwrap();
+        manager.set_tags("long", vec!["b".to_string(), "c".to_string()]).unwrap();
+        manager.save_default("Customized default").unwrap();
+        
+        let summary = manager.summary().unwrap();
+        assert_eq!(summary.total_prompts, 3);
+        assert_eq!(summary.total_size, (2 + 28 + 6) as u64);
+        assert_eq!(summary.largest, Some(("long".to_string(), 28)));
+        assert_eq!(summary.most_recently_modified.map(|(name, _)| name), Some("latest".to_string()));
+        assert_eq!(summary.tag_count, 3);
+        assert!(summary.default_customized);
+    }
+    
+    #[test]
+    fn test_register_glossary() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.register_glossary(HashMap::from([
+            ("idempotent".to_string(), "idempotent (safe to retry)".to_string()),
+            ("SLA".to_string(), "service level agreement".to_string()),
+        ]));
+        manager.save_prompt("ops", r#"Keep handlers {{define "idempotent"}} and meet the {{define "SLA"}} via {{define "sharding"}}."#).unwrap();
+        
+        assert_eq!(
+            manager.render_prompt("ops", &HashMap::new()).unwrap(),
+            "Keep handlers idempotent (safe to retry) and meet the service level agreement via sharding."
+        );
+    }
+    
+    #[test]
+    fn test_export_as_custom_fields() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("coder", "Write code").unwrap();
+        manager.save_prompt("writer", "Write prose").unwrap();
+        manager.set_tags("coder", vec!["dev".to_string()]).unwrap();
+        
+        let mapping = ExportMapping {
+            id_field: "id".to_string(),
+            body_field: "body".to_string(),
+            title_field: Some("title".to_string()),
+            tags_field: Some("labels".to_string()),
+            ..ExportMapping::default()
+        };
+        let exported: serde_json::Value = serde_json::from_str(&manager.export_as(mapping).unwrap()).unwrap();
+        assert_eq!(
+            exported,
+            serde_json::json!([
+                {"id": "coder", "title": "coder", "body": "Write code", "labels": ["dev"]},
+                {"id": "writer", "title": "writer", "body": "Write prose", "labels": []}
+            ])
+        );
+        
+        let mapping = ExportMapping { shape: ExportShape::Map, ..ExportMapping::default() };
+        let exported: serde_json::Value = serde_json::from_str(&manager.export_as(mapping).unwrap()).unwrap();
+        assert_eq!(exported["writer"], serde_json::json!({"content": "Write prose"}));
+    }
+    
+    #[test]
+    fn test_list_prompts_by_tag() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        for name in ["zeta", "alpha", "mid", "untagged"] {
+            manager.save_prompt(name, "Content").unwrap();
+        }
+        manager.set_tags("zeta", vec!["review".to_string()]).unwrap();
+        manager.set_tags("alpha", vec!["Review".to_string(), "testing".to_string()]).unwrap();
+        manager.set_tags("mid", vec!["writing".to_string()]).unwrap();
+        
+        assert_eq!(manager.list_prompts_by_tag("review").unwrap(), vec!["alpha", "zeta"]);
+        assert_eq!(manager.list_prompts_by_tag("testing").unwrap(), vec!["alpha"]);
+        assert!(manager.list_prompts_by_tag("missing").unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_metadata_without_tags_loads() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("legacy", "Old install").unwrap();
+        
+        let metadata_path = temp_dir.path().join("metadata.json");
+        let mut metadata: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&metadata_path).unwrap()).unwrap();
+        metadata["prompts"]["legacy"].as_object_mut().unwrap().remove("tags");
+        std::fs::write(&metadata_path, metadata.to_string()).unwrap();
+        
+        assert!(manager.get_tags("legacy").unwrap().is_empty());
+        assert!(manager.list_prompts_by_tag("any").unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_run_examples() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("greet", "Hello {{name}}, welcome to {{place}}").unwrap();
+        
+        let example = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+        };
+        manager.set_examples("greet", vec![
+            example(&[("name", "Ada"), ("place", "the lab")]),
+            example(&[("name", "Bob")]),
+        ]).unwrap();
+        assert_eq!(manager.get_examples("greet").unwrap().len(), 2);
+        
+        let results = manager.run_examples("greet").unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].as_ref().unwrap(), "Hello Ada, welcome to the lab");
+        assert!(results[1].is_err(), "missing variable should fail in strict mode");
+        
+        // Examples survive edits to the prompt
+        manager.save_prompt("greet", "Hi {{name}} at {{place}}").unwrap();
+        let results = manager.run_examples("greet").unwrap();
+        assert_eq!(results[0].as_ref().unwrap(), "Hi Ada at the lab");
+        
+        // Examples render like render_prompt, embeds included
+        manager.save_prompt("signature", "-- {{team}}").unwrap();
+        manager.save_prompt("greet", "Hi {{name}}\n{{embed \"signature\"}}").unwrap();
+        let results = manager.run_examples("greet").unwrap();
+        assert_eq!(results[0].as_ref().unwrap(), "Hi Ada\n-- {{team}}");
+        assert_eq!(results[0].as_ref().unwrap(), &manager.render_prompt("greet", &example(&[("name", "Ada")])).unwrap());
+        
+        assert!(manager.run_examples("missing").is_err());
+        assert!(manager.set_examples("missing", Vec::new()).is_err());
+    }
+    
+    #[test]
+    fn test_render_prompt_embeds_verbatim() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("examples", "Example: {{x}} stays literal").unwrap();
+        manager.save_prompt("main", "x is {{x}}.\n{{embed \"examples\"}}").unwrap();
+        
+        let mut vars = HashMap::new();
+        vars.insert("x".to_string(), "42".to_string());
+        let rendered = manager.render_prompt("main", &vars).unwrap();
+        assert_eq!(rendered, "x is 42.\nExample: {{x}} stays literal");
+        
+        let mut spoofed = vars.clone();
+        spoofed.insert("__embed_0".to_string(), "Spoofed".to_string());
+        assert!(matches!(manager.render_prompt("main", &spoofed), Err(PromptError::InvalidVariableNames(_))));
+        
+        manager.save_prompt("loop_a", "{{embed \"loop_b\"}}").unwrap();
+        manager.save_prompt("loop_b", "{{embed \"loop_a\"}}").unwrap();
+        let err = manager.render_prompt("loop_a", &vars).unwrap_err();
+        assert!(err.to_string().contains("Cyclic embed"));
+        
+        manager.save_prompt("dangling", "{{embed \"nowhere\"}}").unwrap();
+        assert!(matches!(
+            manager.render_prompt("dangling", &vars),
+            Err(PromptError::PromptNotFound(_))
+        ));
+    }
+    
+    #[test]
+    fn test_export_all_import_all_round_trip() {
+        let source_dir = TempDir::new().unwrap();
+        let mut source = test_manager(&source_dir);
+        source.save_default("Custom default").unwrap();
+        source.save_prompt("coding", "You write {{language}} code.").unwrap();
+        source.save_prompt("review", "Review carefully.\n\nBe kind.").unwrap();
+        source.set_tags("coding", vec!["dev".to_string()]).unwrap();
+        
+        let mut archive = Vec::new();
+        source.export_all(&mut archive).unwrap();
+        
+        let target_dir = TempDir::new().unwrap();
+        let mut target = test_manager(&target_dir);
+        let report = target.import_all(archive.as_slice(), false).unwrap();
+        assert!(report.is_success());
+        assert_eq!(report.imported, vec!["coding", "review"]);
+        
+        assert_eq!(target.load_default().unwrap(), source.load_default().unwrap());
+        for name in source.list_prompts().unwrap() {
+            assert_eq!(target.load_prompt(&name).unwrap(), source.load_prompt(&name).unwrap());
+        }
+        assert_eq!(target.get_tags("coding").unwrap(), vec!["dev"]);
+    }
+    
+    #[test]
+    fn test_import_all_collects_invalid_prompts() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config {
+            storage_dir: Some(temp_dir.path().to_path_buf()),
+            max_prompt_length: 20,
+            ..Config::default()
+        };
+        let mut manager = PromptManager::with_config(config).unwrap();
+        let archive = r#"{"version": "1", "prompts": [
+            {"name": "long", "content": "This prompt is far too long to import"},
+            {"name": "empty", "content": "   "},
+            {"name": "short", "content": "Short"}
+        ]}"#;
+        
+        let report = manager.import_all(archive.as_bytes(), false).unwrap();
+        assert_eq!(report.imported, vec!["short"]);
+        let failed: Vec<&str> = report.failed.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(failed, vec!["long", "empty"]);
+        assert!(!manager.list_prompts().unwrap().contains(&"long".to_string()));
+    }
+    
+    #[test]
+    fn test_validate_bundle_reports_without_saving() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config {
+            storage_dir: Some(temp_dir.path().to_path_buf()),
+            max_prompt_length: 30,
+            ..Config::default()
+        };
+        let manager = PromptManager::with_config(config).unwrap();
+        let bundle = r#"{"version": "1", "prompts": [
+            {"name": "long", "content": "This prompt is much longer than thirty characters"},
+            {"name": "valid", "content": "Hello {{name}}"},
+            {"name": "broken", "content": "{{#if x}}open"}
+        ]}"#;
+        
+        let report = manager.validate_bundle(bundle).unwrap();
+        let names: Vec<&str> = report.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["long", "valid", "broken"]);
+        assert!(matches!(report[0].1, Err(PromptError::InvalidPrompt(_))));
+        assert!(report[1].1.is_ok());
+        assert!(report[2].1.is_err());
+        assert!(manager.list_prompts().unwrap().is_empty());
+        
+        assert!(manager.validate_bundle("not json").is_err());
+    }
+    
+    #[test]
+    fn test_render_prompt_and_default_by_name() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config {
+            storage_dir: Some(temp_dir.path().to_path_buf()),
+            max_prompt_length: 40,
+            ..Config::default()
+        };
+        let mut manager = PromptManager::with_config(config).unwrap();
+        manager.save_default("You assist {{user}}.").unwrap();
+        manager.save_prompt("echo", "Say {{text}}").unwrap();
+        
+        let mut vars = HashMap::new();
+        vars.insert("user".to_string(), "Ada".to_string());
+        assert_eq!(manager.render_default(&vars).unwrap(), "You assist Ada.");
+        
+        vars.insert("text".to_string(), "hi".to_string());
+        assert_eq!(manager.render_prompt("echo", &vars).unwrap(), "Say hi");
+        
+        // Rendered output is validated like saved prompts
+        vars.insert("text".to_string(), "x".repeat(50));
+        assert!(matches!(manager.render_prompt("echo", &vars), Err(PromptError::InvalidPrompt(_))));
+        assert!(matches!(manager.render_prompt("missing", &vars), Err(PromptError::PromptNotFound(_))));
+    }
+    
+    #[test]
+    fn test_profile_defaults() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_default("Base default").unwrap();
+        manager.save_profile_default("coding", "You write code.").unwrap();
+        manager.save_profile_default("writing", "You edit prose.").unwrap();
+        
+        assert_eq!(manager.list_profiles().unwrap(), vec!["coding", "writing"]);
+        assert!(temp_dir.path().join("default.coding.txt").exists());
+        assert_eq!(manager.load_default().unwrap(), "Base default");
+        
+        manager.set_active_profile("coding").unwrap();
+        assert_eq!(manager.load_default().unwrap(), "You write code.");
+        manager.set_active_profile("writing").unwrap();
+        assert_eq!(manager.load_default().unwrap(), "You edit prose.");
+        assert_eq!(manager.active_profile(), Some("writing"));
+        
+        // Profiles without their own default fall back to the base one
+        manager.set_active_profile("gaming").unwrap();
+        assert_eq!(manager.load_default().unwrap(), "Base default");
+        manager.clear_active_profile();
+        assert_eq!(manager.load_default().unwrap(), "Base default");
+        
+        // Profile defaults aren't listed as named prompts, even after a rebuild
+        assert!(manager.list_prompts().unwrap().is_empty());
+        manager.reconcile().unwrap();
+        assert!(manager.list_prompts().unwrap().is_empty());
+        
+        assert!(matches!(manager.set_active_profile("../etc"), Err(PromptError::Config(_))));
+        assert!(manager.save_profile_default("", "x").is_err());
+    }
+    
+    #[test]
+    fn test_restore_version() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config {
+            storage_dir: Some(temp_dir.path().to_path_buf()),
+            keep_versions: 3,
+            ..Config::default()
+        };
+        let mut manager = PromptManager::with_config(config).unwrap();
+        manager.save_prompt("essay", "Draft one").unwrap();
+        manager.save_prompt("essay", "Draft two").unwrap();
+        manager.save_prompt("essay", "Regretted edit").unwrap();
+        
+        let versions = manager.list_versions("essay").unwrap();
+        assert_eq!(versions.len(), 2);
+        manager.restore_version("essay", versions[1]).unwrap();
+        assert_eq!(manager.load_prompt("essay").unwrap(), "Draft two");
+        
+        // The regretted edit is itself kept, and the limit still applies
+        let versions = manager.list_versions("essay").unwrap();
+        assert_eq!(versions.len(), 3);
+        manager.restore_version("essay", *versions.last().unwrap()).unwrap();
+        assert_eq!(manager.load_prompt("essay").unwrap(), "Regretted edit");
+        assert_eq!(manager.list_versions("essay").unwrap().len(), 3);
+        
+        assert!(manager.restore_version("essay", 0).is_err());
+    }
+    
+    #[test]
+    fn test_versions_disabled_by_default() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("essay", "One").unwrap();
+        manager.save_prompt("essay", "Two").unwrap();
+        
+        assert!(manager.list_versions("essay").unwrap().is_empty());
+        assert!(!temp_dir.path().join("history").exists());
+    }
+    
+    #[test]
+    fn test_render_value_bulleted_list() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = test_manager(&temp_dir);
+        let context = serde_json::json!({"items": ["apples", "pears"]});
+        
+        let rendered = manager.render_value("Buy:\n{{#each items}}* {{this}}\n{{/each}}", &context).unwrap();
+        assert_eq!(rendered, "Buy:\n* apples\n* pears\n");
+        
+        let empty = serde_json::json!({"items": []});
+        assert!(matches!(
+            manager.render_value("{{#each items}}{{this}}{{/each}}", &empty),
+            Err(PromptError::InvalidPrompt(_))
+        ));
+    }
+    
+    #[test]
+    fn test_factory_default_constant() {
+        assert!(!FACTORY_DEFAULT_PROMPT.trim().is_empty());
+        assert_eq!(PromptManager::factory_default_prompt(), FACTORY_DEFAULT_PROMPT);
+        
+        let temp_dir = TempDir::new().unwrap();
+        let manager = test_manager(&temp_dir);
+        manager.validate_prompt(FACTORY_DEFAULT_PROMPT).unwrap();
+        manager.template_engine.validate_template(FACTORY_DEFAULT_PROMPT).unwrap();
+        
+        let written = std::fs::read_to_string(temp_dir.path().join("default.txt")).unwrap();
+        assert_eq!(written, FACTORY_DEFAULT_PROMPT.trim());
+        assert!(manager.default_is_factory());
+    }
+    
+    #[test]
+    fn test_render_and_log_safe_masks_redacted_keys() {
+        use std::sync::{Arc, Mutex};
+        
+        #[derive(Clone)]
+        struct Captured(Arc<Mutex<Vec<u8>>>);
+        
+        impl std::io::Write for Captured {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("account", "User {{user}} has SSN {{ssn}}").unwrap();
+        
+        let mut vars = HashMap::new();
+        vars.insert("user".to_string(), "ada".to_string());
+        vars.insert("ssn".to_string(), "123-45-6789".to_string());
+        
+        let captured = Captured(Arc::new(Mutex::new(Vec::new())));
+        let writer = captured.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        let rendered = tracing::subscriber::with_default(subscriber, || {
+            manager.render_and_log_safe("account", &vars, &["ssn".to_string()]).unwrap()
+        });
+        
+        assert_eq!(rendered, "User ada has SSN 123-45-6789");
+        let log = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        let event = log.lines().find(|line| line.contains("Rendered prompt")).unwrap();
+        assert!(event.contains("\"ssn\": \"***\""));
+        assert!(event.contains("\"user\": \"ada\""));
+        assert!(!log.contains("123-45-6789"));
+    }
+    
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_search_regex() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("api", "Respond as JSON:\n{\"status\": \"ok\"}").unwrap();
+        manager.save_prompt("tool", "Call with {\"name\": \"search\"} only").unwrap();
+        manager.save_prompt("plain", "No structure here").unwrap();
+        
+        let hits = manager.search_regex(r#"\{"\w+":"#).unwrap();
+        let found: Vec<(&str, usize)> = hits.iter().map(|h| (h.name.as_str(), h.line_number)).collect();
+        assert_eq!(found, vec![("api", 2), ("tool", 1)]);
+        assert_eq!(hits[0].line, "{\"status\": \"ok\"}");
+        
+        let err = manager.search_regex("(unclosed").unwrap_err();
+        assert!(matches!(&err, PromptError::Config(message) if message.contains("(unclosed")));
+    }
+    
+    #[test]
+    fn test_strict_variables() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        let template = "You know {{langauge}}.";
+        let mut vars = HashMap::new();
+        vars.insert("language".to_string(), "Rust".to_string());
+        
+        assert_eq!(manager.render_template(template, &vars).unwrap(), "You know .");
+        
+        let strict = Config { strict_variables: true, ..manager.config.clone() };
+        manager.apply_config(strict).unwrap();
+        let err = manager.render_template(template, &vars).unwrap_err();
+        assert!(matches!(err, PromptError::Render(_)));
+        assert!(err.to_string().contains("langauge"), "unexpected message: {}", err);
+        assert_eq!(manager.render_template("You know {{language}}.", &vars).unwrap(), "You know Rust.");
+        
+        let config = Config {
+            storage_dir: Some(temp_dir.path().to_path_buf()),
+            strict_variables: true,
+            ..Config::default()
+        };
+        let fresh = PromptManager::with_config(config).unwrap();
+        assert!(fresh.render_template(template, &vars).is_err());
+    }
+    
+    #[test]
+    fn test_sync_to_bundle() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("keep", "Same content").unwrap();
+        manager.save_prompt("change", "Old content").unwrap();
+        manager.save_prompt("extra", "Not in the bundle").unwrap();
+        
+        let bundle = r#"{"version": "1", "default": "Provisioned default", "prompts": [
+            {"name": "keep", "content": "Same content"},
+            {"name": "change", "content": "New content", "tags": ["v2"]},
+            {"name": "fresh", "content": "Brand new"}
+        ], "partials": [{"name": "footer", "content": "Bye"}]}"#;
+        
+        let report = manager.sync_to_bundle(bundle).unwrap();
+        assert_eq!(report.added, vec!["partials/footer", "fresh"]);
+        assert_eq!(report.updated, vec!["change"]);
+        assert_eq!(report.deleted, vec!["extra"]);
+        assert_eq!(report.unchanged, vec!["keep"]);
+        assert!(report.default_updated);
+        
+        assert_eq!(manager.list_prompts().unwrap(), vec!["change", "fresh", "keep", "partials/footer"]);
+        assert_eq!(manager.load_prompt("change").unwrap(), "New content");
+        assert_eq!(manager.get_tags("change").unwrap(), vec!["v2"]);
+        assert_eq!(manager.load_default().unwrap(), "Provisioned default");
+        
+        // A second sync finds nothing to do
+        assert!(manager.sync_to_bundle(bundle).unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_sync_to_bundle_is_all_or_nothing() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("existing", "Keep me").unwrap();
+        
+        let bundle = r#"{"version": "1", "prompts": [
+            {"name": "good", "content": "Fine"},
+            {"name": "bad", "content": "   "}
+        ]}"#;
+        let err = manager.sync_to_bundle(bundle).unwrap_err();
+        assert!(err.to_string().contains("'bad'"));
+        assert_eq!(manager.list_prompts().unwrap(), vec!["existing"]);
+        assert_eq!(manager.load_prompt("existing").unwrap(), "Keep me");
+    }
+    
+    #[test]
+    fn test_render_registered_template() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.register_template("system", "You are a {{role}}.").unwrap();
+        
+        let mut vars = HashMap::new();
+        vars.insert("role".to_string(), "reviewer".to_string());
+        for _ in 0..3 {
+            assert_eq!(manager.render_registered("system", &vars).unwrap(), "You are a reviewer.");
+        }
+        
+        assert!(matches!(
+            manager.register_template("bad", "{{/if}}"),
+            Err(PromptError::TemplateSyntax { .. })
+        ));
+        assert!(manager.render_registered("unknown", &vars).is_err());
+    }
+    
+    #[test]
+    fn test_fuzzy_match_ranks_closer_names_first() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        for name in ["code_review", "cold_read_everything", "debugging"] {
+            manager.save_prompt(name, "Content").unwrap();
+        }
+        
+        let ranked = manager.fuzzy_match("codrev", 10).unwrap();
+        let names: Vec<&str> = ranked.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["code_review", "cold_read_everything"]);
+        assert!(ranked[0].1 > ranked[1].1);
+        
+        let all = manager.fuzzy_match("", 2).unwrap();
+        assert_eq!(all, vec![("code_review".to_string(), 0.0), ("cold_read_everything".to_string(), 0.0)]);
+        assert!(manager.fuzzy_match("zzz", 10).unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_config_json() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config {
+            storage_base: StorageBase::Custom(temp_dir.path().to_path_buf()),
+            max_prompt_length: 4321,
+            keep_versions: 7,
+            ..Config::default()
+        };
+        let manager = PromptManager::with_config(config).unwrap();
+        
+        let json: serde_json::Value = serde_json::from_str(&manager.config_json().unwrap()).unwrap();
+        assert_eq!(json["storage_dir"], temp_dir.path().to_str().unwrap());
+        assert_eq!(json["max_prompt_length"], 4321);
+        assert_eq!(json["keep_versions"], 7);
+        assert_eq!(json["name_sanitizer"], "ascii_only");
+        assert_eq!(json["validate_templates"], true);
+    }
+    
+    #[test]
+    fn test_render_template_with_env() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = test_manager(&temp_dir);
+        
+        let env: HashMap<String, String> = [
+            ("GAMECODE_VAR_PROJECT", "from-env"),
+            ("GAMECODE_VAR_OWNER", "env-owner"),
+            ("GAMECODE_VAR_BUILD_ID", "42"),
+            ("GAMECODE_VAR_USER_EMAIL", "ada@example.com"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let lookup = |name: &str| env.get(name).cloned();
+        
+        let mut vars = HashMap::new();
+        vars.insert("owner".to_string(), "explicit".to_string());
+        let template = "{{project}} by {{owner}} {{default unset \"n/a\"}}";
+        let rendered = manager.render_template_with_lookup(template, &vars, lookup).unwrap();
+        assert_eq!(rendered, "from-env by explicit n/a");
+        
+        let template = "{{build-id}} for {{user.email}}";
+        let rendered = manager.render_template_with_lookup(template, &HashMap::new(), lookup).unwrap();
+        assert_eq!(rendered, "42 for ada@example.com");
+        
+        // Lookups are confined to the prefix
+        let rendered = manager.render_template_with_env("[{{path}}]", &HashMap::new()).unwrap();
+        assert_eq!(rendered, "[]");
+    }
+    
+    #[test]
+    fn test_refresh_info_from_disk() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("edited", "tiny").unwrap();
+        let path = manager.get_prompt_info("edited").unwrap().file_path;
+        std::fs::write(&path, "edited by hand outside the manager").unwrap();
+        
+        // Cached by default
+        assert_eq!(manager.get_prompt_info("edited").unwrap().size, 4);
+        
+        let config = Config { refresh_info_from_disk: true, ..manager.config.clone() };
+        manager.apply_config(config).unwrap();
+        assert_eq!(manager.get_prompt_info("edited").unwrap().size, 34);
+    }
+    
+    #[test]
+    fn test_min_prompt_length_boundary() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config {
+            storage_dir: Some(temp_dir.path().to_path_buf()),
+            min_prompt_length: 50,
+            ..Config::default()
+        };
+        let mut manager = PromptManager::with_config(config).unwrap();
+        
+        assert!(manager.save_prompt("exact", &"x".repeat(50)).is_ok());
+        match manager.save_prompt("short", &"x".repeat(49)) {
+            Err(PromptError::InvalidPrompt(msg)) => assert!(msg.contains("minimum length of 50")),
+            other => panic!("expected InvalidPrompt, got {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn test_forbidden_substrings_case_insensitive() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config {
+            storage_dir: Some(temp_dir.path().to_path_buf()),
+            forbidden_substrings: vec!["FIXME".to_string(), "TODO".to_string()],
+            ..Config::default()
+        };
+        let mut manager = PromptManager::with_config(config).unwrap();
+        
+        match manager.save_prompt("draft", "Finish this later, ToDo: tone") {
+            Err(PromptError::InvalidPrompt(msg)) => assert!(msg.contains("forbidden substring 'TODO'")),
+            other => panic!("expected InvalidPrompt, got {:?}", other),
+        }
+        assert!(manager.save_prompt("clean", "Ready to ship").is_ok());
+    }
+    
+    #[test]
+    fn test_render_as_messages() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("chat", "### system\nYou review {{language}} code.\n\n### user\nReview this diff.").unwrap();
+        
+        let mut vars = HashMap::new();
+        vars.insert("language".to_string(), "Rust".to_string());
+        let messages = manager.render_as_messages("chat", &vars).unwrap();
+        assert_eq!(messages, vec![
+            Message { role: "system".to_string(), content: "You review Rust code.".to_string() },
+            Message { role: "user".to_string(), content: "Review this diff.".to_string() },
+        ]);
+    }
+    
+    #[test]
+    fn test_max_prompt_tokens() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config {
+            storage_dir: Some(temp_dir.path().to_path_buf()),
+            max_prompt_tokens: Some(4),
+            ..Config::default()
+        };
+        let mut manager = PromptManager::with_config(config).unwrap();
+        
+        assert_eq!(manager.estimate_tokens("Hello world"), 4);
+        assert!(manager.save_prompt("fits", "Hello world").is_ok());
+        match manager.save_prompt("over", "Hello world, again") {
+            Err(PromptError::InvalidPrompt(msg)) => assert!(msg.contains("maximum of 4 tokens")),
+            other => panic!("expected InvalidPrompt, got {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn test_clean_temp_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("real", "Keep me").unwrap();
+        std::fs::write(temp_dir.path().join("real.txt.tmp"), "Kee").unwrap();
+        std::fs::write(temp_dir.path().join("metadata.json.corrupt"), "{").unwrap();
+        
+        let removed = manager.clean_temp_files().unwrap();
+        assert_eq!(removed, vec![
+            temp_dir.path().join("metadata.json.corrupt"),
+            temp_dir.path().join("real.txt.tmp"),
+        ]);
+        assert_eq!(manager.load_prompt("real").unwrap(), "Keep me");
+        assert!(temp_dir.path().join("metadata.json").exists());
+        assert!(manager.clean_temp_files().unwrap().is_empty());
+    }
+    
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_manager_over_sqlite_storage() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = SqliteStorage::open(temp_dir.path().join("prompts.db")).unwrap();
+        let mut manager = PromptManager::with_storage(Box::new(storage), Config::default());
+        
+        manager.save_prompt("greeting", "Hello {{name}}").unwrap();
+        let mut vars = HashMap::new();
+        vars.insert("name".to_string(), "Ada".to_string());
+        assert_eq!(manager.render_prompt("greeting", &vars).unwrap(), "Hello Ada");
+        assert_eq!(manager.list_prompts().unwrap(), vec!["greeting"]);
+    }
+    
+    #[test]
+    fn test_save_prompts_reports_partial_failure() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        let entries = vec![
+            ("first".to_string(), "First prompt".to_string()),
+            ("blank".to_string(), "   ".to_string()),
+            ("second".to_string(), "Second prompt".to_string()),
+        ];
+        
+        let report = manager.save_prompts(&entries).unwrap();
+        assert_eq!(report.succeeded, vec!["first", "second"]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, "blank");
+        assert!(!report.is_success());
+        assert_eq!(manager.list_prompts().unwrap(), vec!["first", "second"]);
+        assert_eq!(manager.get_prompt_info("second").unwrap().size, 13);
+    }
+    
+    #[test]
+    fn test_delete_prompts_reports_missing() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("keep", "Kept").unwrap();
+        manager.save_prompt("drop", "Dropped").unwrap();
+        
+        let report = manager.delete_prompts(&["drop".to_string(), "ghost".to_string()]);
+        assert_eq!(report.succeeded, vec!["drop"]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, "ghost");
+        assert_eq!(manager.list_prompts().unwrap(), vec!["keep"]);
+    }
+    
+    #[test]
+    fn test_render_prompt_with_includes() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("tone", "Be kind.").unwrap();
+        manager.save_prompt("safety", "Stay safe. {{> tone}}").unwrap();
+        manager.save_prompt("reviewer", "{{> safety}}\nYou review {{language}} code.").unwrap();
+        
+        let mut vars = HashMap::new();
+        vars.insert("language".to_string(), "Rust".to_string());
+        let rendered = manager.render_prompt_with_includes("reviewer", &vars).unwrap();
+        assert_eq!(rendered, "Stay safe. Be kind.\nYou review Rust code.");
+    }
+    
+    #[test]
+    fn test_render_prompt_with_includes_cycle() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = test_manager(&temp_dir);
+        manager.save_prompt("a", "A then {{> b}}").unwrap();
+        manager.save_prompt("b", "B then {{> a}}").unwrap();
+        
+        match manager.render_prompt_with_includes("a", &HashMap::new()) {
+            Err(PromptError::InvalidPrompt(msg)) => assert_eq!(msg, "Cyclic include: a -> b -> a"),
+            other => panic!("expected cyclic include error, got {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn test_list_prompts_in_namespace() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config {
+            storage_dir: Some(temp_dir.path().to_path_buf()),
+            namespace_separator: Some('/'),
+            ..Config::default()
+        };
+        let mut manager = PromptManager::with_config(config).unwrap();
+        manager.save_prompt("alice/coding", "Code carefully").unwrap();
+        manager.save_prompt("alice/writing", "Write clearly").unwrap();
+        manager.save_prompt("alicia/coding", "Someone else").unwrap();
+        manager.save_prompt("general", "Be helpful").unwrap();
+        
+        assert_eq!(manager.list_prompts_in_namespace("alice").unwrap(), vec!["alice/coding", "alice/writing"]);
+        assert_eq!(manager.list_prompts_in_namespace("alice/").unwrap(), vec!["alice/coding", "alice/writing"]);
+        assert!(manager.list_prompts_in_namespace("bob").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,141 @@
+//! Lint checks for prompt content
+
+use std::collections::HashMap;
+
+/// Number of times a line must appear before it is reported
+pub const REPEATED_LINE_THRESHOLD: usize = 3;
+
+/// Lines shorter than this are ignored by the repeated-line rule
+const MIN_REPEATED_LINE_LENGTH: usize = 20;
+
+/// A problem found while linting a prompt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintWarning {
+    /// 1-based line number the warning refers to
+    pub line: usize,
+    /// Which rule produced the warning
+    pub kind: LintKind,
+    /// Human-readable description
+    pub message: String,
+}
+
+/// Kinds of lint warnings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LintKind {
+    /// A paragraph repeats one that starts at `first_line`
+    DuplicateParagraph { first_line: usize },
+    /// A line appears `count` times in the prompt
+    RepeatedLine { count: usize },
+}
+
+/// Lint prompt content, returning warnings ordered by line
+pub fn lint_prompt(text: &str) -> Vec<LintWarning> {
+    let mut warnings = duplicate_paragraphs(text);
+    warnings.extend(repeated_lines(text));
+    warnings.sort_by_key(|w| w.line);
+    warnings
+}
+
+/// Report paragraphs that duplicate an earlier paragraph
+fn duplicate_paragraphs(text: &str) -> Vec<LintWarning> {
+    let mut warnings = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut current: Vec<&str> = Vec::new();
+    let mut start_line = 0;
+
+    let mut flush = |lines: &mut Vec<&str>, start: usize| {
+        if lines.is_empty() {
+            return;
+        }
+        let normalized = lines
+            .iter()
+            .map(|l| l.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect::<Vec<_>>()
+            .join("\n");
+        match seen.get(&normalized) {
+            Some(&first_line) => warnings.push(LintWarning {
+                line: start,
+                kind: LintKind::DuplicateParagraph { first_line },
+                message: format!(
+                    "Paragraph at line {} duplicates paragraph at line {}",
+                    start, first_line
+                ),
+            }),
+            None => {
+                seen.insert(normalized, start);
+            }
+        }
+        lines.clear();
+    };
+
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            flush(&mut current, start_line);
+        } else {
+            if current.is_empty() {
+                start_line = i + 1;
+            }
+            current.push(line);
+        }
+    }
+    flush(&mut current, start_line);
+
+    warnings
+}
+
+/// Report lines that repeat at or above the threshold
+fn repeated_lines(text: &str) -> Vec<LintWarning> {
+    let mut occurrences: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, line) in text.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.len() >= MIN_REPEATED_LINE_LENGTH {
+            occurrences.entry(trimmed).or_default().push(i + 1);
+        }
+    }
+
+    occurrences
+        .into_values()
+        .filter(|lines| lines.len() >= REPEATED_LINE_THRESHOLD)
+        .map(|lines| LintWarning {
+            line: lines[0],
+            kind: LintKind::RepeatedLine { count: lines.len() },
+            message: format!(
+                "Line {} is repeated {} times (lines {})",
+                lines[0],
+                lines.len(),
+                lines.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+            ),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duplicate_paragraph_warned() {
+        let prompt = "You are a helpful assistant.\nAnswer concisely.\n\nUse examples.\n\nYou are a helpful assistant.\nAnswer  concisely.";
+        let warnings = lint_prompt(prompt);
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].line, 6);
+        assert_eq!(warnings[0].kind, LintKind::DuplicateParagraph { first_line: 1 });
+    }
+
+    #[test]
+    fn test_repeated_line_warned() {
+        let prompt = "Always cite your sources.\nBe brief.\nAlways cite your sources.\nAlways cite your sources.";
+        let warnings = lint_prompt(prompt);
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].line, 1);
+        assert_eq!(warnings[0].kind, LintKind::RepeatedLine { count: 3 });
+    }
+
+    #[test]
+    fn test_normal_prompt_has_no_warnings() {
+        let prompt = crate::PromptManager::factory_default_prompt();
+        assert!(lint_prompt(&prompt).is_empty());
+    }
+}
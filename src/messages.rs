@@ -0,0 +1,83 @@
+//! Splitting rendered prompts into chat messages
+
+use serde::{Deserialize, Serialize};
+
+/// Roles recognized in message header lines
+const ROLES: &[&str] = &["system", "user", "assistant"];
+
+/// Role given to content that appears before any header
+const DEFAULT_ROLE: &str = "system";
+
+/// A single chat turn
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Message {
+    pub role: String,
+    pub content: String,
+}
+
+/// Split text into messages on role-header lines
+///
+/// A header is a line consisting of `header_prefix` followed by one of
+/// `system`, `user`, or `assistant` (case-insensitive), e.g. `### user`.
+/// Other lines starting with the prefix are treated as content. Text
+/// before the first header becomes a system message unless it is blank.
+/// Message content is trimmed.
+pub fn split_messages(text: &str, header_prefix: &str) -> Vec<Message> {
+    let mut messages = Vec::new();
+    let mut role: Option<String> = None;
+    let mut content = String::new();
+
+    for line in text.lines() {
+        if let Some(next_role) = header_role(line, header_prefix) {
+            push_message(&mut messages, role.take(), &content);
+            role = Some(next_role);
+            content.clear();
+        } else {
+            content.push_str(line);
+            content.push('\n');
+        }
+    }
+    push_message(&mut messages, role, &content);
+
+    messages
+}
+
+/// Role named by a header line, if the line is one
+fn header_role(line: &str, header_prefix: &str) -> Option<String> {
+    let role = line.trim().strip_prefix(header_prefix)?.trim().to_lowercase();
+    ROLES.contains(&role.as_str()).then_some(role)
+}
+
+/// Append a finished section, dropping blank text that precedes any header
+fn push_message(messages: &mut Vec<Message>, role: Option<String>, content: &str) {
+    let content = content.trim();
+    if role.is_none() && content.is_empty() {
+        return;
+    }
+    messages.push(Message {
+        role: role.unwrap_or_else(|| DEFAULT_ROLE.to_string()),
+        content: content.to_string(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preamble_defaults_to_system() {
+        let messages = split_messages("Be brief.\n### User\nHi\n### notes\nstill user", "### ");
+        assert_eq!(messages, vec![
+            Message { role: "system".to_string(), content: "Be brief.".to_string() },
+            Message { role: "user".to_string(), content: "Hi\n### notes\nstill user".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn test_custom_header_prefix() {
+        let messages = split_messages("@@assistant\nDone.", "@@");
+        assert_eq!(messages, vec![
+            Message { role: "assistant".to_string(), content: "Done.".to_string() },
+        ]);
+    }
+}
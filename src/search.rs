@@ -0,0 +1,111 @@
+//! Searching prompt content
+
+/// A substring search over prompt content
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    /// Text to look for
+    pub text: String,
+    /// Match case exactly instead of case-insensitively
+    pub case_sensitive: bool,
+}
+
+impl SearchQuery {
+    /// Create a case-insensitive query for the given text
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            case_sensitive: false,
+        }
+    }
+
+    /// Set whether the query matches case exactly
+    pub fn case_sensitive(mut self, case_sensitive: bool) -> Self {
+        self.case_sensitive = case_sensitive;
+        self
+    }
+
+    /// Check if a line contains the query text
+    pub fn matches_line(&self, line: &str) -> bool {
+        if self.case_sensitive {
+            line.contains(&self.text)
+        } else {
+            line.to_lowercase().contains(&self.text.to_lowercase())
+        }
+    }
+}
+
+/// A line of a prompt matching a search
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchHit {
+    /// Name of the matching prompt
+    pub name: String,
+    /// 1-based line number of the match
+    pub line_number: usize,
+    /// The matching line
+    pub line: String,
+}
+
+/// Find every line of a prompt matching a query
+pub fn search_content(name: &str, content: &str, query: &SearchQuery) -> Vec<SearchHit> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| query.matches_line(line))
+        .map(|(i, line)| SearchHit {
+            name: name.to_string(),
+            line_number: i + 1,
+            line: line.to_string(),
+        })
+        .collect()
+}
+
+/// Find every line of a prompt where a regex match starts
+///
+/// The pattern runs over the whole content, so it can match across lines;
+/// each hit reports the line the match begins on, once per line.
+#[cfg(feature = "regex")]
+pub fn search_content_regex(name: &str, content: &str, pattern: &regex::Regex) -> Vec<SearchHit> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut hits: Vec<SearchHit> = Vec::new();
+    for found in pattern.find_iter(content) {
+        let line_number = content[..found.start()].matches('\n').count() + 1;
+        if hits.last().is_some_and(|hit| hit.line_number == line_number) {
+            continue;
+        }
+        hits.push(SearchHit {
+            name: name.to_string(),
+            line_number,
+            line: lines.get(line_number - 1).copied().unwrap_or_default().to_string(),
+        });
+    }
+    hits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_content() {
+        let content = "Review the code.\nCheck for bugs.\nREVIEW tests too.";
+
+        let hits = search_content("review", content, &SearchQuery::new("review"));
+        assert_eq!(hits.iter().map(|h| h.line_number).collect::<Vec<_>>(), vec![1, 3]);
+        assert_eq!(hits[1].line, "REVIEW tests too.");
+
+        let hits = search_content("review", content, &SearchQuery::new("review").case_sensitive(true));
+        assert!(hits.is_empty());
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_search_content_regex() {
+        let content = "Reply with:\n{\n  \"answer\": 1\n}\nThen {stop} {here}.";
+        let pattern = regex::Regex::new(r"\{[^}]*\}").unwrap();
+
+        let hits = search_content_regex("json", content, &pattern);
+        assert_eq!(hits.iter().map(|h| h.line_number).collect::<Vec<_>>(), vec![2, 5]);
+        assert_eq!(hits[0].line, "{");
+        assert_eq!(hits[1].line, "Then {stop} {here}.");
+    }
+}
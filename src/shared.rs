@@ -0,0 +1,197 @@
+//! Sharing one prompt manager across threads
+
+use crate::error::Result;
+use crate::PromptManager;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// In-memory copy of stored prompt content, keyed by prompt name
+#[derive(Debug, Default)]
+pub(crate) struct PromptCache {
+    prompts: Mutex<HashMap<String, String>>,
+}
+
+impl PromptCache {
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, String>> {
+        self.prompts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<String> {
+        self.lock().get(name).cloned()
+    }
+
+    pub(crate) fn insert(&self, name: &str, content: &str) {
+        self.lock().insert(name.to_string(), content.to_string());
+    }
+
+    pub(crate) fn remove(&self, name: &str) -> Option<String> {
+        self.lock().remove(name)
+    }
+
+    pub(crate) fn clear(&self) {
+        self.lock().clear();
+    }
+}
+
+/// Prompt cache that independently built managers can share
+///
+/// Clones refer to the same cache. Managers created with
+/// [`PromptManager::with_shared_state`] from one state see each other's
+/// writes without re-reading storage, so they should all point at the
+/// same storage.
+#[derive(Debug, Clone, Default)]
+pub struct SharedState {
+    pub(crate) cache: Arc<PromptCache>,
+}
+
+impl SharedState {
+    /// Create an empty shared state
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// A prompt manager shared across threads
+///
+/// Clones refer to the same manager, so a prompt saved through one clone
+/// is visible to every other clone. With `Config::shared_cache` enabled,
+/// loaded and saved prompts are kept in memory and later loads skip disk.
+#[derive(Clone)]
+pub struct SharedPromptManager {
+    inner: Arc<RwLock<PromptManager>>,
+}
+
+impl SharedPromptManager {
+    /// Share an existing manager
+    pub fn new(manager: PromptManager) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(manager)),
+        }
+    }
+
+    /// Lock the manager for reading
+    pub fn read(&self) -> RwLockReadGuard<'_, PromptManager> {
+        self.inner.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Lock the manager for writing
+    pub fn write(&self) -> RwLockWriteGuard<'_, PromptManager> {
+        self.inner.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Load a named prompt
+    pub fn load_prompt(&self, name: &str) -> Result<String> {
+        self.read().load_prompt(name)
+    }
+
+    /// Save a named prompt
+    pub fn save_prompt(&self, name: &str, prompt: &str) -> Result<()> {
+        self.write().save_prompt(name, prompt)
+    }
+
+    /// Delete a named prompt
+    pub fn delete_prompt(&self, name: &str) -> Result<()> {
+        self.write().delete_prompt(name)
+    }
+
+    /// List all named prompts
+    pub fn list_prompts(&self) -> Result<Vec<String>> {
+        self.read().list_prompts()
+    }
+
+    /// Render a named prompt with variables
+    pub fn render_prompt(&self, name: &str, variables: &HashMap<String, String>) -> Result<String> {
+        self.read().render_prompt(name, variables)
+    }
+
+    /// Render the default prompt with variables
+    pub fn render_default(&self, variables: &HashMap<String, String>) -> Result<String> {
+        self.read().render_default(variables)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+    use std::thread;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_clones_share_cached_writes() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config {
+            storage_dir: Some(temp_dir.path().to_path_buf()),
+            shared_cache: true,
+            ..Config::default()
+        };
+        let writer = PromptManager::shared(config).unwrap();
+        let reader = writer.clone();
+
+        thread::spawn(move || writer.save_prompt("greeting", "Hello from another thread").unwrap())
+            .join()
+            .unwrap();
+
+        // Remove the file behind the manager's back; the cache still serves it
+        std::fs::remove_file(temp_dir.path().join("greeting.txt")).unwrap();
+        assert_eq!(reader.load_prompt("greeting").unwrap(), "Hello from another thread");
+    }
+
+    #[test]
+    fn test_without_cache_reads_disk() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config {
+            storage_dir: Some(temp_dir.path().to_path_buf()),
+            ..Config::default()
+        };
+        let shared = PromptManager::shared(config).unwrap();
+        shared.save_prompt("greeting", "Hello").unwrap();
+
+        std::fs::write(temp_dir.path().join("greeting.txt"), "Edited on disk").unwrap();
+        assert_eq!(shared.clone().load_prompt("greeting").unwrap(), "Edited on disk");
+    }
+
+    #[test]
+    fn test_managers_built_from_one_state_share_writes() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config {
+            storage_dir: Some(temp_dir.path().to_path_buf()),
+            ..Config::default()
+        };
+        let state = SharedState::new();
+        let mut writer = PromptManager::with_shared_state(config.clone(), &state).unwrap();
+        let reader = PromptManager::with_shared_state(config.clone(), &state).unwrap();
+        let unshared = PromptManager::with_config(config).unwrap();
+
+        writer.save_prompt("greeting", "Hello from a separate manager").unwrap();
+        std::fs::remove_file(temp_dir.path().join("greeting.txt")).unwrap();
+        assert_eq!(reader.load_prompt("greeting").unwrap(), "Hello from a separate manager");
+        assert!(unshared.load_prompt("greeting").is_err());
+
+        writer.save_prompt("greeting", "Updated").unwrap();
+        assert_eq!(reader.load_prompt("greeting").unwrap(), "Updated");
+        writer.delete_prompt("greeting").unwrap();
+        assert!(reader.load_prompt("greeting").is_err());
+    }
+
+    #[test]
+    fn test_apply_config_keeps_shared_state_cache() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Config {
+            storage_dir: Some(temp_dir.path().to_path_buf()),
+            ..Config::default()
+        };
+        let state = SharedState::new();
+        let mut writer = PromptManager::with_shared_state(config.clone(), &state).unwrap();
+        let mut reader = PromptManager::with_shared_state(config.clone(), &state).unwrap();
+
+        let detached = Config { shared_cache: false, ..config.clone() };
+        assert!(matches!(reader.apply_config(detached), Err(crate::PromptError::Config(_))));
+        let stricter = Config { shared_cache: true, max_prompt_length: 1000, ..config };
+        reader.apply_config(stricter).unwrap();
+
+        writer.save_prompt("greeting", "Cached for both").unwrap();
+        std::fs::remove_file(temp_dir.path().join("greeting.txt")).unwrap();
+        assert_eq!(reader.load_prompt("greeting").unwrap(), "Cached for both");
+    }
+}
@@ -0,0 +1,451 @@
+//! SQLite-backed prompt storage
+
+use crate::error::{PromptError, Result};
+use crate::search::{SearchHit, SearchQuery, search_content};
+use crate::storage::{PromptInfo, PromptStorage, check_prompt_name, content_hash};
+use rusqlite::{Connection, OptionalExtension, params};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, info};
+
+/// Row name the default prompt is stored under
+///
+/// The NUL byte keeps it from clashing with any real prompt name.
+const DEFAULT_KEY: &str = "\0default";
+
+/// Prompt storage in a single `prompts` table of a SQLite database
+///
+/// Useful for applications that already ship a database. Tags and
+/// examples are stored as JSON text.
+pub struct SqliteStorage {
+    conn: Mutex<Connection>,
+    path: PathBuf,
+}
+
+impl SqliteStorage {
+    /// Open or create a database at `path`, seeding the factory default prompt
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let conn = Connection::open(&path)
+            .map_err(|e| PromptError::Storage(format!("Failed to open database {}: {}", path.display(), e)))?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS prompts (
+                name TEXT PRIMARY KEY NOT NULL,
+                content TEXT NOT NULL,
+                content_type TEXT,
+                tags TEXT NOT NULL DEFAULT '[]',
+                examples TEXT NOT NULL DEFAULT '[]',
+                created_at INTEGER NOT NULL,
+                modified_at INTEGER NOT NULL
+            )",
+        )
+        .map_err(|e| PromptError::Storage(format!("Failed to create prompts table: {}", e)))?;
+
+        let storage = Self { conn: Mutex::new(conn), path };
+        let now = to_nanos(SystemTime::now());
+        let seeded = storage.lock()
+            .execute(
+                "INSERT OR IGNORE INTO prompts (name, content, created_at, modified_at) VALUES (?1, ?2, ?3, ?3)",
+                params![DEFAULT_KEY, crate::PromptManager::factory_default_prompt(), now],
+            )
+            .map_err(|e| PromptError::Storage(format!("Failed to write default prompt: {}", e)))?;
+        if seeded > 0 {
+            info!("Created prompt database: {}", storage.path.display());
+        }
+        Ok(storage)
+    }
+
+    /// Lock the connection
+    fn lock(&self) -> MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Insert or replace content, keeping creation time, content type, tags, and examples
+    fn put(&self, name: &str, prompt: &str) -> Result<()> {
+        let now = to_nanos(SystemTime::now());
+        self.lock()
+            .execute(
+                "INSERT INTO prompts (name, content, created_at, modified_at) VALUES (?1, ?2, ?3, ?3)
+                 ON CONFLICT(name) DO UPDATE SET content = excluded.content, modified_at = excluded.modified_at",
+                params![name, prompt.trim(), now],
+            )
+            .map_err(|e| PromptError::Storage(format!("Failed to write prompt '{}': {}", name, e)))?;
+        Ok(())
+    }
+
+    /// Load content stored under a row name
+    fn get(&self, name: &str) -> Result<Option<String>> {
+        self.lock()
+            .query_row("SELECT content FROM prompts WHERE name = ?1", params![name], |row| row.get(0))
+            .optional()
+            .map_err(|e| PromptError::Storage(format!("Failed to read prompt '{}': {}", name, e)))
+    }
+
+    /// Update one column of a named prompt
+    fn update_column(&self, name: &str, column: &str, value: Option<String>) -> Result<()> {
+        if name == DEFAULT_KEY {
+            return Err(PromptError::PromptNotFound(name.to_string()));
+        }
+        let sql = format!("UPDATE prompts SET {} = ?1 WHERE name = ?2", column);
+        let updated = self.lock()
+            .execute(&sql, params![value, name])
+            .map_err(|e| PromptError::Storage(format!("Failed to update prompt '{}': {}", name, e)))?;
+        if updated == 0 {
+            return Err(PromptError::PromptNotFound(name.to_string()));
+        }
+        Ok(())
+    }
+}
+
+impl PromptStorage for SqliteStorage {
+    fn load_default(&self) -> Result<String> {
+        Ok(self.get(DEFAULT_KEY)?.unwrap_or_default())
+    }
+
+    fn save_default(&self, prompt: &str) -> Result<()> {
+        self.put(DEFAULT_KEY, prompt)?;
+        info!("Saved default prompt to {}", self.path.display());
+        Ok(())
+    }
+
+    fn load_prompt(&self, name: &str) -> Result<String> {
+        if name == DEFAULT_KEY {
+            return Err(PromptError::PromptNotFound(name.to_string()));
+        }
+        let prompt = self.get(name)?
+            .ok_or_else(|| PromptError::PromptNotFound(name.to_string()))?;
+        debug!("Loaded prompt '{}' from {}", name, self.path.display());
+        Ok(prompt)
+    }
+
+    fn save_prompt(&self, name: &str, prompt: &str) -> Result<()> {
+        check_prompt_name(name)?;
+        if name == DEFAULT_KEY {
+            return Err(PromptError::InvalidPrompt("Prompt name is reserved".to_string()));
+        }
+        self.put(name, prompt)?;
+        info!("Saved prompt '{}' to {}", name, self.path.display());
+        Ok(())
+    }
+
+    fn list_prompts(&self) -> Result<Vec<String>> {
+        let conn = self.lock();
+        let mut statement = conn.prepare("SELECT name FROM prompts WHERE name != ?1 ORDER BY name")
+            .map_err(|e| PromptError::Storage(format!("Failed to list prompts: {}", e)))?;
+        let prompts = statement.query_map(params![DEFAULT_KEY], |row| row.get(0))
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<String>>>())
+            .map_err(|e| PromptError::Storage(format!("Failed to list prompts: {}", e)))?;
+
+        debug!("Listed {} prompts", prompts.len());
+        Ok(prompts)
+    }
+
+    fn delete_prompt(&self, name: &str) -> Result<()> {
+        if name == DEFAULT_KEY {
+            return Err(PromptError::PromptNotFound(name.to_string()));
+        }
+        let deleted = self.lock()
+            .execute("DELETE FROM prompts WHERE name = ?1", params![name])
+            .map_err(|e| PromptError::Storage(format!("Failed to delete prompt '{}': {}", name, e)))?;
+        if deleted == 0 {
+            return Err(PromptError::PromptNotFound(name.to_string()));
+        }
+
+        info!("Deleted prompt '{}'", name);
+        Ok(())
+    }
+
+    fn prompt_exists(&self, name: &str) -> bool {
+        name != DEFAULT_KEY && matches!(self.get(name), Ok(Some(_)))
+    }
+
+    fn get_prompt_info(&self, name: &str) -> Result<PromptInfo> {
+        if name == DEFAULT_KEY {
+            return Err(PromptError::PromptNotFound(name.to_string()));
+        }
+        let row = self.lock()
+            .query_row(
+                "SELECT content, content_type, tags, examples, created_at, modified_at FROM prompts WHERE name = ?1",
+                params![name],
+                |row| Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, i64>(4)?,
+                    row.get::<_, i64>(5)?,
+                )),
+            )
+            .optional()
+            .map_err(|e| PromptError::Storage(format!("Failed to read prompt '{}': {}", name, e)))?;
+        let (content, content_type, tags, examples, created_at, modified_at) = row
+            .ok_or_else(|| PromptError::PromptNotFound(name.to_string()))?;
+
+        Ok(PromptInfo {
+            name: name.to_string(),
+            size: content.len() as u64,
+            created_at: from_nanos(created_at),
+            modified_at: from_nanos(modified_at),
+            file_path: self.path.clone(),
+            content_hash: Some(content_hash(&content)),
+            content_type,
+            tags: serde_json::from_str(&tags)?,
+            examples: serde_json::from_str(&examples)?,
+        })
+    }
+
+    fn set_content_type(&self, name: &str, content_type: &str) -> Result<()> {
+        self.update_column(name, "content_type", Some(content_type.to_string()))
+    }
+
+    fn swap_prompts(&self, a: &str, b: &str) -> Result<()> {
+        let info_a = self.get_prompt_info(a)?;
+        let info_b = self.get_prompt_info(b)?;
+        let content_a = self.load_prompt(a)?;
+        let content_b = self.load_prompt(b)?;
+        let now = to_nanos(SystemTime::now());
+
+        let mut conn = self.lock();
+        let tx = conn.transaction()
+            .map_err(|e| PromptError::Storage(format!("Failed to swap prompts: {}", e)))?;
+        for (name, content, content_type) in [(a, content_b, info_b.content_type), (b, content_a, info_a.content_type)] {
+            tx.execute(
+                "UPDATE prompts SET content = ?1, content_type = ?2, modified_at = ?3 WHERE name = ?4",
+                params![content, content_type, now, name],
+            )
+            .map_err(|e| PromptError::Storage(format!("Failed to swap prompts: {}", e)))?;
+        }
+        tx.commit()
+            .map_err(|e| PromptError::Storage(format!("Failed to swap prompts: {}", e)))?;
+
+        info!("Swapped prompts '{}' and '{}'", a, b);
+        Ok(())
+    }
+
+    fn rename_prompt(&self, old: &str, new: &str) -> Result<()> {
+        check_prompt_name(old)?;
+        check_prompt_name(new)?;
+        if !self.prompt_exists(old) {
+            return Err(PromptError::PromptNotFound(old.to_string()));
+        }
+        if new == DEFAULT_KEY || self.prompt_exists(new) {
+            return Err(PromptError::PromptAlreadyExists(new.to_string()));
+        }
+
+        self.lock()
+            .execute("UPDATE prompts SET name = ?1 WHERE name = ?2", params![new, old])
+            .map_err(|e| PromptError::Storage(format!("Failed to rename prompt '{}': {}", old, e)))?;
+        info!("Renamed prompt '{}' to '{}'", old, new);
+        Ok(())
+    }
+
+    fn copy_prompt(&self, source: &str, dest: &str, overwrite: bool) -> Result<()> {
+        check_prompt_name(dest)?;
+        let content = self.load_prompt(source)?;
+        let info = self.get_prompt_info(source)?;
+        if source == dest || dest == DEFAULT_KEY || (!overwrite && self.prompt_exists(dest)) {
+            return Err(PromptError::PromptAlreadyExists(dest.to_string()));
+        }
+
+        let now = to_nanos(SystemTime::now());
+        self.lock()
+            .execute(
+                "INSERT OR REPLACE INTO prompts (name, content, content_type, tags, examples, created_at, modified_at)
+                 VALUES (?1, ?2, ?3, '[]', ?4, ?5, ?5)",
+                params![dest, content, info.content_type, serde_json::to_string(&info.examples)?, now],
+            )
+            .map_err(|e| PromptError::Storage(format!("Failed to copy prompt '{}': {}", source, e)))?;
+
+        info!("Copied prompt '{}' to '{}'", source, dest);
+        Ok(())
+    }
+
+    fn set_tags(&self, name: &str, tags: Vec<String>) -> Result<()> {
+        self.update_column(name, "tags", Some(serde_json::to_string(&tags)?))
+    }
+
+    fn set_examples(&self, name: &str, examples: Vec<HashMap<String, String>>) -> Result<()> {
+        self.update_column(name, "examples", Some(serde_json::to_string(&examples)?))
+    }
+
+    /// Select matching prompts in SQL, then report lines as the generic scan does
+    ///
+    /// `LIKE` only folds ASCII case, so case-insensitive queries with
+    /// non-ASCII text fall back to the generic scan.
+    fn search_native(&self, query: &SearchQuery) -> Option<Result<Vec<SearchHit>>> {
+        let sql = if query.case_sensitive {
+            "SELECT name, content FROM prompts WHERE name != ?1 AND instr(content, ?2) > 0 ORDER BY name"
+        } else if query.text.is_ascii() {
+            "SELECT name, content FROM prompts WHERE name != ?1 AND content LIKE ?2 ESCAPE '\\' ORDER BY name"
+        } else {
+            return None;
+        };
+        let needle = if query.case_sensitive {
+            query.text.clone()
+        } else {
+            format!("%{}%", escape_like(&query.text))
+        };
+
+        let conn = self.lock();
+        let rows = conn.prepare(sql)
+            .and_then(|mut statement| {
+                statement.query_map(params![DEFAULT_KEY, needle], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+                    .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            })
+            .map_err(|e| PromptError::Storage(format!("Failed to search prompts: {}", e)));
+        Some(rows.map(|rows| {
+            rows.iter()
+                .flat_map(|(name, content)| search_content(name, content, query))
+                .collect()
+        }))
+    }
+}
+
+/// Escape `LIKE` wildcards so the text matches literally with `ESCAPE '\'`
+fn escape_like(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Convert a timestamp to nanoseconds since the Unix epoch
+fn to_nanos(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as i64)
+        .unwrap_or(0)
+}
+
+/// Convert nanoseconds since the Unix epoch to a timestamp
+fn from_nanos(nanos: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_nanos(nanos.max(0) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_sqlite_storage_basic_operations() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = SqliteStorage::open(temp_dir.path().join("prompts.db")).unwrap();
+
+        // Test default prompt
+        let default_prompt = storage.load_default().unwrap();
+        assert!(!default_prompt.is_empty());
+
+        // Save a custom default
+        let custom_default = "Custom default prompt";
+        storage.save_default(custom_default).unwrap();
+        let loaded_default = storage.load_default().unwrap();
+        assert_eq!(loaded_default, custom_default);
+
+        // Test named prompts
+        let prompt_name = "test_prompt";
+        let prompt_content = "This is a test prompt";
+
+        // Save prompt
+        storage.save_prompt(prompt_name, prompt_content).unwrap();
+
+        // Load prompt
+        let loaded_prompt = storage.load_prompt(prompt_name).unwrap();
+        assert_eq!(loaded_prompt, prompt_content);
+
+        // Check existence
+        assert!(storage.prompt_exists(prompt_name));
+        assert!(!storage.prompt_exists("nonexistent"));
+
+        // List prompts
+        let prompts = storage.list_prompts().unwrap();
+        assert_eq!(prompts.len(), 1);
+        assert_eq!(prompts[0], prompt_name);
+
+        // Get prompt info
+        let info = storage.get_prompt_info(prompt_name).unwrap();
+        assert_eq!(info.name, prompt_name);
+        assert_eq!(info.size, prompt_content.len() as u64);
+        assert_eq!(info.content_hash, Some(content_hash(prompt_content)));
+
+        // Delete prompt
+        storage.delete_prompt(prompt_name).unwrap();
+        assert!(!storage.prompt_exists(prompt_name));
+        let prompts = storage.list_prompts().unwrap();
+        assert_eq!(prompts.len(), 0);
+    }
+
+    #[test]
+    fn test_sqlite_storage_persists_and_reports_not_found() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("prompts.db");
+        {
+            let storage = SqliteStorage::open(&path).unwrap();
+            storage.save_default("Reopened default").unwrap();
+            storage.save_prompt("notes", "Kept").unwrap();
+            storage.set_tags("notes", vec!["work".to_string()]).unwrap();
+        }
+
+        let storage = SqliteStorage::open(&path).unwrap();
+        assert_eq!(storage.load_default().unwrap(), "Reopened default");
+        assert_eq!(storage.get_prompt_info("notes").unwrap().tags, vec!["work"]);
+        assert!(matches!(storage.load_prompt("missing"), Err(PromptError::PromptNotFound(_))));
+        assert!(matches!(storage.delete_prompt("missing"), Err(PromptError::PromptNotFound(_))));
+        assert!(matches!(storage.set_tags("missing", Vec::new()), Err(PromptError::PromptNotFound(_))));
+        assert!(matches!(storage.load_prompt(DEFAULT_KEY), Err(PromptError::PromptNotFound(_))));
+    }
+
+    #[test]
+    fn test_sqlite_rejects_invalid_names_like_file_storage() {
+        let temp_dir = TempDir::new().unwrap();
+        let sqlite = SqliteStorage::open(temp_dir.path().join("prompts.db")).unwrap();
+        let files = crate::storage::FileStorage::with_directory(temp_dir.path().join("files")).unwrap();
+        let backends: [&dyn PromptStorage; 2] = [&sqlite, &files];
+
+        for storage in backends {
+            storage.save_prompt("valid", "Content").unwrap();
+            for name in ["", ".", "..", "../escape"] {
+                assert!(matches!(storage.save_prompt(name, "Content"), Err(PromptError::InvalidName(_))), "save {:?}", name);
+                assert!(matches!(storage.rename_prompt("valid", name), Err(PromptError::InvalidName(_))), "rename {:?}", name);
+                assert!(matches!(storage.copy_prompt("valid", name, true), Err(PromptError::InvalidName(_))), "copy {:?}", name);
+            }
+            assert_eq!(storage.list_prompts().unwrap(), vec!["valid"]);
+        }
+    }
+
+    #[test]
+    fn test_sqlite_search_matches_generic_scan() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = SqliteStorage::open(temp_dir.path().join("prompts.db")).unwrap();
+        storage.save_prompt("review", "Review the code.\nCheck for bugs.\nREVIEW tests too.").unwrap();
+        storage.save_prompt("percent", "Aim for 100% coverage\nuse snake_case names").unwrap();
+        storage.save_prompt("plain", "Nothing to see here").unwrap();
+
+        let queries = [
+            SearchQuery::new("review"),
+            SearchQuery::new("Review").case_sensitive(true),
+            SearchQuery::new("100%"),
+            SearchQuery::new("e_c"),
+            SearchQuery::new("%"),
+            SearchQuery::new("missing"),
+        ];
+        for query in &queries {
+            let mut scanned = Vec::new();
+            for name in storage.list_prompts().unwrap() {
+                scanned.extend(search_content(&name, &storage.load_prompt(&name).unwrap(), query));
+            }
+            let native = storage.search_native(query).unwrap().unwrap();
+            assert_eq!(native, scanned, "{:?}", query);
+        }
+
+        // Wildcards match literally rather than as patterns
+        let hits = storage.search_native(&SearchQuery::new("e_c")).unwrap().unwrap();
+        assert_eq!(hits.iter().map(|h| h.name.as_str()).collect::<Vec<_>>(), vec!["percent"]);
+        assert!(storage.search_native(&SearchQuery::new("ÉTÉ")).is_none());
+    }
+}
@@ -1,10 +1,19 @@
 use crate::error::{PromptError, Result};
+use crate::integrity::{Discrepancy, DiscrepancyKind};
+use crate::search::{SearchHit, SearchQuery};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
 use std::time::SystemTime;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
+use unicode_normalization::UnicodeNormalization;
 
 /// Trait for prompt storage backends
 pub trait PromptStorage: Send + Sync {
@@ -14,9 +23,32 @@ pub trait PromptStorage: Send + Sync {
     /// Save the default system prompt
     fn save_default(&self, prompt: &str) -> Result<()>;
     
+    /// Load the default prompt stored for a profile, if any
+    fn load_profile_default(&self, _profile: &str) -> Result<Option<String>> {
+        Ok(None)
+    }
+    
+    /// Save the default prompt for a profile
+    fn save_profile_default(&self, profile: &str, _prompt: &str) -> Result<()> {
+        Err(PromptError::Storage(format!("Cannot save profile '{}': profiles are not supported by this storage", profile)))
+    }
+    
+    /// List profiles that have a stored default, sorted
+    fn list_profiles(&self) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+    
     /// Load a named prompt
     fn load_prompt(&self, name: &str) -> Result<String>;
     
+    /// Load a named prompt exactly as stored, without trimming
+    ///
+    /// The `size` and `content_hash` in [`PromptInfo`] describe this
+    /// content. The default returns [`load_prompt`](Self::load_prompt).
+    fn load_prompt_raw(&self, name: &str) -> Result<String> {
+        self.load_prompt(name)
+    }
+    
     /// Save a named prompt
     fn save_prompt(&self, name: &str, prompt: &str) -> Result<()>;
     
@@ -26,11 +58,173 @@ pub trait PromptStorage: Send + Sync {
     /// Delete a named prompt
     fn delete_prompt(&self, name: &str) -> Result<()>;
     
+    /// Save several prompts, returning a result per entry in order
+    ///
+    /// A failed entry doesn't stop the rest. The outer error means the
+    /// batch couldn't be recorded; backends may then have saved some
+    /// entries without updating their metadata.
+    fn save_prompts(&self, entries: &[(String, String)]) -> Result<Vec<Result<()>>> {
+        Ok(entries.iter().map(|(name, prompt)| self.save_prompt(name, prompt)).collect())
+    }
+    
+    /// Delete several prompts, returning a result per name in order
+    ///
+    /// Error semantics match [`save_prompts`](Self::save_prompts).
+    fn delete_prompts(&self, names: &[String]) -> Result<Vec<Result<()>>> {
+        Ok(names.iter().map(|name| self.delete_prompt(name)).collect())
+    }
+    
     /// Check if a prompt exists
     fn prompt_exists(&self, name: &str) -> bool;
     
     /// Get prompt metadata
     fn get_prompt_info(&self, name: &str) -> Result<PromptInfo>;
+    
+    /// Get prompt metadata from the stored content rather than cached metadata
+    ///
+    /// Backends that can't be modified out-of-band return the cached info.
+    fn refresh_prompt_info(&self, name: &str) -> Result<PromptInfo> {
+        self.get_prompt_info(name)
+    }
+    
+    /// Set the content type recorded for a prompt
+    fn set_content_type(&self, name: &str, _content_type: &str) -> Result<()> {
+        Err(PromptError::Storage(format!("Cannot set content type of '{}': content types are not supported by this storage", name)))
+    }
+    
+    /// Exchange the content of two existing prompts
+    ///
+    /// The default loads both and saves each under the other's name; a
+    /// failure between the two saves leaves both holding `b`'s content.
+    fn swap_prompts(&self, a: &str, b: &str) -> Result<()> {
+        let content_a = self.load_prompt(a)?;
+        let content_b = self.load_prompt(b)?;
+        self.save_prompt(a, &content_b)?;
+        self.save_prompt(b, &content_a)
+    }
+    
+    /// Rename a prompt, keeping its content, tags, and creation time
+    ///
+    /// Fails with `PromptNotFound` if `old` doesn't exist and with
+    /// `PromptAlreadyExists` if `new` is taken. The default saves the
+    /// content under `new` and deletes `old`, so only the content carries
+    /// over.
+    fn rename_prompt(&self, old: &str, new: &str) -> Result<()> {
+        let content = self.load_prompt(old)?;
+        if self.prompt_exists(new) {
+            return Err(PromptError::PromptAlreadyExists(new.to_string()));
+        }
+        self.save_prompt(new, &content)?;
+        self.delete_prompt(old)
+    }
+    
+    /// Copy a prompt's content to a new name with fresh metadata
+    ///
+    /// Fails with `PromptNotFound` if `source` doesn't exist and with
+    /// `PromptAlreadyExists` if `dest` is taken and `overwrite` is false.
+    fn copy_prompt(&self, source: &str, dest: &str, overwrite: bool) -> Result<()> {
+        let content = self.load_prompt(source)?;
+        if !overwrite && self.prompt_exists(dest) {
+            return Err(PromptError::PromptAlreadyExists(dest.to_string()));
+        }
+        self.save_prompt(dest, &content)
+    }
+    
+    /// Replace the tags on a prompt
+    fn set_tags(&self, name: &str, _tags: Vec<String>) -> Result<()> {
+        Err(PromptError::Storage(format!("Cannot tag '{}': tags are not supported by this storage", name)))
+    }
+    
+    /// Replace the example variable sets stored with a prompt
+    fn set_examples(&self, name: &str, _examples: Vec<HashMap<String, String>>) -> Result<()> {
+        Err(PromptError::Storage(format!("Cannot store examples for '{}': examples are not supported by this storage", name)))
+    }
+    
+    /// Delete all but the most recent `keep` historical versions of a prompt
+    ///
+    /// Returns the number of versions removed. Backends without history
+    /// remove nothing.
+    fn prune_history(&self, _name: &str, _keep: usize) -> Result<usize> {
+        Ok(0)
+    }
+    
+    /// Get the on-disk size of a prompt's history in bytes
+    fn history_size(&self, _name: &str) -> Result<u64> {
+        Ok(0)
+    }
+    
+    /// List the timestamps of a prompt's historical versions, oldest first
+    ///
+    /// Timestamps are nanoseconds since the Unix epoch and identify a
+    /// version for [`load_version`](Self::load_version).
+    fn list_versions(&self, _name: &str) -> Result<Vec<u128>> {
+        Ok(Vec::new())
+    }
+    
+    /// Load the content of a historical version of a prompt
+    fn load_version(&self, name: &str, timestamp: u128) -> Result<String> {
+        Err(PromptError::PromptNotFound(format!("{} (version {})", name, timestamp)))
+    }
+    
+    /// Remove metadata entries for prompts whose content no longer exists
+    ///
+    /// Returns the number of entries removed.
+    fn vacuum(&self) -> Result<usize> {
+        Ok(0)
+    }
+    
+    /// Search prompt content using the backend's own query support
+    ///
+    /// Returns `None` to fall back to loading and scanning every prompt.
+    fn search_native(&self, _query: &SearchQuery) -> Option<Result<Vec<SearchHit>>> {
+        None
+    }
+    
+    /// Bring metadata in line with prompts stored outside the API
+    ///
+    /// Backends that can't be modified out-of-band report nothing.
+    fn reconcile(&self) -> Result<ReconcileReport> {
+        Ok(ReconcileReport::default())
+    }
+    
+    /// Delete leftover temporary and backup files, returning their paths
+    ///
+    /// Backends without such artifacts delete nothing.
+    fn clean_temp_files(&self) -> Result<Vec<PathBuf>> {
+        Ok(Vec::new())
+    }
+}
+
+/// Extensions of temporary and backup artifacts left in the prompts directory
+const TEMP_EXTENSIONS: &[&str] = &["tmp", "corrupt", "bak"];
+
+/// Outcome of reconciling metadata with stored prompts
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReconcileReport {
+    /// Prompts found on disk and added to metadata
+    pub added: Vec<String>,
+    /// Files skipped because they are binary or not valid UTF-8
+    pub skipped: Vec<PathBuf>,
+}
+
+/// Check if file content looks binary rather than prompt text
+///
+/// Samples the first 8 KiB: any NUL byte, more than 10% control
+/// characters, or invalid UTF-8 marks the content as binary.
+pub fn looks_binary(bytes: &[u8]) -> bool {
+    let sample = &bytes[..bytes.len().min(8192)];
+    if sample.contains(&0) {
+        return true;
+    }
+    
+    let control = sample.iter()
+        .filter(|&&b| b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c))
+        .count();
+    if control * 10 > sample.len() {
+        return true;
+    }
+    
+    std::str::from_utf8(bytes).is_err()
 }
 
 /// Information about a stored prompt
@@ -41,39 +235,264 @@ pub struct PromptInfo {
     pub created_at: SystemTime,
     pub modified_at: SystemTime,
     pub file_path: PathBuf,
+    /// SHA-256 of the stored content, if recorded
+    #[serde(default)]
+    pub content_hash: Option<String>,
+    /// Content type of the prompt (e.g. `application/json`), if set
+    #[serde(default)]
+    pub content_type: Option<String>,
+    /// Tags used to organize the prompt
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Example variable sets used to regression-test the prompt
+    #[serde(default)]
+    pub examples: Vec<HashMap<String, String>>,
+}
+
+/// Compute the hex-encoded SHA-256 hash of prompt content
+pub fn content_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
 }
 
 /// Metadata for a prompt collection
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct PromptMetadata {
-    version: String,
-    prompts: HashMap<String, PromptEntry>,
+pub(crate) struct PromptMetadata {
+    pub(crate) version: String,
+    pub(crate) prompts: HashMap<String, PromptEntry>,
 }
 
 /// Individual prompt entry in metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct PromptEntry {
-    name: String,
-    file_name: String,
-    created_at: SystemTime,
-    modified_at: SystemTime,
-    size: u64,
+pub(crate) struct PromptEntry {
+    pub(crate) name: String,
+    pub(crate) file_name: String,
+    pub(crate) created_at: SystemTime,
+    pub(crate) modified_at: SystemTime,
+    pub(crate) size: u64,
+    #[serde(default)]
+    pub(crate) content_hash: Option<String>,
+    #[serde(default)]
+    pub(crate) content_type: Option<String>,
+    #[serde(default)]
+    pub(crate) tags: Vec<String>,
+    #[serde(default)]
+    pub(crate) examples: Vec<HashMap<String, String>>,
+}
+
+impl PromptEntry {
+    /// Describe the entry as the info returned to callers
+    pub(crate) fn to_info(&self, file_path: PathBuf) -> PromptInfo {
+        PromptInfo {
+            name: self.name.clone(),
+            size: self.size,
+            created_at: self.created_at,
+            modified_at: self.modified_at,
+            file_path,
+            content_hash: self.content_hash.clone(),
+            content_type: self.content_type.clone(),
+            tags: self.tags.clone(),
+            examples: self.examples.clone(),
+        }
+    }
 }
 
 impl Default for PromptMetadata {
     fn default() -> Self {
         Self {
-            version: "1.0".to_string(),
+            version: METADATA_VERSION.to_string(),
             prompts: HashMap::new(),
         }
     }
 }
 
+/// Schema version of `metadata.json` written by this release
+///
+/// 1.1 added content hashes, content types, tags, and examples to entries.
+pub(crate) const METADATA_VERSION: &str = "1.1";
+
+/// Upgrade metadata read from disk to the current schema version
+///
+/// The upgraded version is written with the next metadata save. Versions
+/// newer than this release are rejected rather than risk dropping fields
+/// it doesn't know about.
+pub(crate) fn migrate_metadata(mut metadata: PromptMetadata) -> Result<PromptMetadata> {
+    match metadata.version.as_str() {
+        METADATA_VERSION => return Ok(metadata),
+        // Entry fields added in 1.1 are filled with their serde defaults
+        "1.0" => {}
+        other => {
+            return Err(PromptError::Storage(format!("Unsupported metadata version '{}'", other)));
+        }
+    }
+    
+    debug!("Migrated metadata from version {} to {}", metadata.version, METADATA_VERSION);
+    metadata.version = METADATA_VERSION.to_string();
+    Ok(metadata)
+}
+
+/// Base location used to resolve the prompts directory
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageBase {
+    /// Platform-specific config directory
+    #[default]
+    ConfigDir,
+    /// `prompts` directory next to the running executable
+    ExecutableDir,
+    /// Explicit directory
+    Custom(PathBuf),
+    /// `gamecode/prompts` under the system temp directory
+    TempDir,
+}
+
+impl StorageBase {
+    /// Resolve the prompts directory for this base
+    pub fn resolve(&self) -> Result<PathBuf> {
+        match self {
+            Self::ConfigDir => FileStorage::default_prompts_dir(),
+            Self::ExecutableDir => {
+                let exe = std::env::current_exe()
+                    .map_err(|e| PromptError::Storage(format!("Could not determine executable path: {}", e)))?;
+                Self::executable_prompts_dir(&exe)
+            }
+            Self::Custom(dir) => Ok(dir.clone()),
+            Self::TempDir => Ok(std::env::temp_dir().join("gamecode").join("prompts")),
+        }
+    }
+    
+    /// Get the prompts directory next to an executable
+    fn executable_prompts_dir(exe: &Path) -> Result<PathBuf> {
+        exe.parent()
+            .map(|dir| dir.join("prompts"))
+            .ok_or_else(|| PromptError::Storage(format!("Executable has no parent directory: {}", exe.display())))
+    }
+}
+
+/// Strategy for turning prompt names into file names
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NameSanitizer {
+    /// Keep ASCII letters, digits, `-` and `_`; replace everything else
+    #[default]
+    AsciiOnly,
+    /// Keep letters and digits from any script (NFC-normalized), replacing
+    /// path separators, control characters, and other punctuation
+    UnicodePreserving,
+    /// Transliterate to ASCII, then apply the ASCII-only rules
+    Transliterate,
+}
+
+impl NameSanitizer {
+    /// Sanitize a prompt name for use as a filename
+    pub fn sanitize(self, name: &str) -> String {
+        match self {
+            Self::AsciiOnly => FileStorage::sanitize_name(name),
+            Self::UnicodePreserving => name
+                .nfc()
+                .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+                .collect(),
+            Self::Transliterate => FileStorage::sanitize_name(&deunicode::deunicode(name)),
+        }
+    }
+}
+
+/// Reject names that are empty, `.` or `..`, or escape the directory as a path
+///
+/// The name is read as a relative path below the prompts directory before
+/// any sanitizing, so `../evil` and `/etc/passwd` fail with
+/// `PromptError::InvalidName` even though sanitizing would defuse them.
+pub(crate) fn check_prompt_name(name: &str) -> Result<()> {
+    let invalid = || PromptError::InvalidName(name.to_string());
+    if matches!(name.trim(), "" | "." | "..") {
+        return Err(invalid());
+    }
+    
+    let mut depth = 0usize;
+    for component in Path::new(name).components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir => depth = depth.checked_sub(1).ok_or_else(invalid)?,
+            Component::RootDir | Component::Prefix(_) => return Err(invalid()),
+        }
+    }
+    // Resolving to the prompts directory itself, e.g. `a/..`, names no file
+    if depth == 0 {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+/// Options for constructing a `FileStorage`
+#[derive(Debug, Clone)]
+pub struct FileStorageOptions {
+    /// Create missing parent directories of the prompts directory
+    pub create_parents: bool,
+    /// Default prompt written on initialization (uses the built-in one if None)
+    pub factory_default: Option<String>,
+    /// Archive the previous content to `history/` when a prompt is overwritten, without limit
+    pub keep_history: bool,
+    /// Copy overwritten prompts to `versions/<name>/<timestamp>.txt`, keeping only this many of each (0 disables)
+    ///
+    /// Independent of `keep_history`: with both set, each overwrite is
+    /// archived to both directories and only `versions/` is pruned.
+    pub keep_versions: usize,
+    /// Strategy for mapping prompt names to file names
+    pub name_sanitizer: NameSanitizer,
+    /// Write the factory default on first access instead of at construction
+    pub lazy_init: bool,
+    /// File extension for prompt files, without the leading dot
+    pub extension: String,
+    /// Separator that splits prompt names into subdirectories, e.g. `/` stores
+    /// `alice/coding` as `alice/coding.txt` (None flattens it into the file name)
+    pub namespace_separator: Option<char>,
+}
+
+impl Default for FileStorageOptions {
+    fn default() -> Self {
+        Self {
+            create_parents: true,
+            factory_default: None,
+            keep_history: false,
+            keep_versions: 0,
+            name_sanitizer: NameSanitizer::default(),
+            lazy_init: false,
+            extension: LEGACY_EXTENSION.to_string(),
+            namespace_separator: None,
+        }
+    }
+}
+
+/// Quiet period after which watched file changes are reported
+#[cfg(feature = "notify")]
+const WATCH_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(200);
+
+/// Extension used for prompt files before it became configurable
+const LEGACY_EXTENSION: &str = "txt";
+
+/// Suffix of compressed `keep_history` archives under `history/`
+const HISTORY_SUFFIX: &str = ".txt.gz";
+
+/// Suffix of plain `keep_versions` copies under `versions/`
+const VERSION_SUFFIX: &str = ".txt";
+
 /// File-based prompt storage implementation
+///
+/// Content is written as given; loads trim surrounding whitespace.
+#[derive(Clone)]
 pub struct FileStorage {
     prompts_dir: PathBuf,
     default_prompt_file: PathBuf,
     metadata_file: PathBuf,
+    history_dir: PathBuf,
+    versions_dir: PathBuf,
+    keep_history: bool,
+    keep_versions: usize,
+    name_sanitizer: NameSanitizer,
+    factory_default: String,
+    lazy_init: bool,
+    extension: String,
+    namespace_separator: Option<char>,
 }
 
 impl FileStorage {
@@ -83,14 +502,41 @@ impl FileStorage {
         Self::with_directory(prompts_dir)
     }
     
+    /// Create a file storage instance under the given base location
+    pub fn with_base(base: &StorageBase) -> Result<Self> {
+        Self::with_directory(base.resolve()?)
+    }
+    
     /// Create a file storage instance with custom directory
     pub fn with_directory<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        Self::with_options(dir, FileStorageOptions::default())
+    }
+    
+    /// Create a file storage instance with custom directory and options
+    pub fn with_options<P: AsRef<Path>>(dir: P, options: FileStorageOptions) -> Result<Self> {
         let prompts_dir = dir.as_ref().to_path_buf();
-        let default_prompt_file = prompts_dir.join("default.txt");
+        let extension = options.extension.trim_start_matches('.').to_string();
+        if extension.is_empty() {
+            return Err(PromptError::Config("Prompt file extension must not be empty".to_string()));
+        }
+        let default_prompt_file = prompts_dir.join(format!("default.{}", extension));
         let metadata_file = prompts_dir.join("metadata.json");
+        let history_dir = prompts_dir.join("history");
+        let versions_dir = prompts_dir.join("versions");
         
         // Create prompts directory if it doesn't exist
         if !prompts_dir.exists() {
+            if !options.create_parents {
+                let parent_exists = prompts_dir.parent()
+                    .is_none_or(|parent| parent.as_os_str().is_empty() || parent.is_dir());
+                if !parent_exists {
+                    return Err(PromptError::Config(format!(
+                        "Parent directory of {} does not exist",
+                        prompts_dir.display()
+                    )));
+                }
+            }
+            
             fs::create_dir_all(&prompts_dir)
                 .map_err(|e| PromptError::Storage(format!("Failed to create prompts directory: {}", e)))?;
             info!("Created prompts directory: {}", prompts_dir.display());
@@ -100,11 +546,21 @@ impl FileStorage {
             prompts_dir,
             default_prompt_file,
             metadata_file,
+            history_dir,
+            versions_dir,
+            keep_history: options.keep_history,
+            keep_versions: options.keep_versions,
+            name_sanitizer: options.name_sanitizer,
+            factory_default: options.factory_default
+                .unwrap_or_else(crate::PromptManager::factory_default_prompt),
+            lazy_init: options.lazy_init,
+            extension,
+            namespace_separator: options.namespace_separator,
         };
         
         // Initialize default prompt if it doesn't exist
-        if !storage.default_prompt_file.exists() {
-            storage.save_default(&crate::PromptManager::factory_default_prompt())?;
+        if !storage.lazy_init && !storage.default_file_path().exists() {
+            storage.save_default(&storage.factory_default)?;
         }
         
         Ok(storage)
@@ -131,9 +587,194 @@ impl FileStorage {
         Ok(config_dir.join("gamecode").join("prompts"))
     }
     
-    /// Get the file path for a named prompt
+    /// Get the file path a named prompt is written to
+    fn target_file_path(&self, name: &str) -> PathBuf {
+        let mut path = self.prompts_dir.join(self.relative_stem(name));
+        path.as_mut_os_string().push(format!(".{}", self.extension));
+        path
+    }
+    
+    /// Map a prompt name to its sanitized path below the prompts directory, without extension
+    ///
+    /// With a namespace separator each segment becomes a directory level.
+    fn relative_stem(&self, name: &str) -> PathBuf {
+        match self.namespace_separator {
+            Some(separator) => name.split(separator)
+                .map(|segment| self.name_sanitizer.sanitize(segment))
+                .collect(),
+            None => PathBuf::from(self.name_sanitizer.sanitize(name)),
+        }
+    }
+    
+    /// Check that a name maps to a file inside the prompts directory
+    ///
+    /// Besides the checks of `check_prompt_name`, namespaced names may not
+    /// have empty, `.` or `..` segments or a top-level `history` namespace
+    /// that would clash with archives, and the file's nearest existing
+    /// directory must canonicalize inside the prompts directory.
+    fn validate_name(&self, name: &str) -> Result<()> {
+        check_prompt_name(name)?;
+        
+        if let Some(separator) = self.namespace_separator {
+            let mut segments = name.split(separator);
+            let reserved = name.contains(separator) && matches!(segments.next(), Some("history" | "versions"));
+            if reserved || name.split(separator).any(|segment| matches!(segment, "" | "." | "..")) {
+                return Err(PromptError::InvalidName(name.to_string()));
+            }
+        }
+        
+        // A symlinked namespace directory could still point elsewhere
+        let target = self.target_file_path(name);
+        let Some(dir) = target.ancestors().skip(1).find(|dir| dir.exists()) else {
+            return Ok(());
+        };
+        if let (Ok(dir), Ok(root)) = (fs::canonicalize(dir), fs::canonicalize(&self.prompts_dir))
+            && !dir.starts_with(&root)
+        {
+            return Err(PromptError::InvalidName(name.to_string()));
+        }
+        Ok(())
+    }
+    
+    /// Get the file path for a named prompt after validating the name
+    ///
+    /// A file recorded in `metadata` under another name, e.g.
+    /// `greeting_prod` for `greeting@prod`, is not this prompt's, so it
+    /// reads as not found.
+    fn checked_file_path(&self, metadata: &PromptMetadata, name: &str) -> Result<PathBuf> {
+        self.validate_name(name)?;
+        let path = self.prompt_file_path(name);
+        if self.file_owner(metadata, name, &path).is_some() {
+            return Err(PromptError::PromptNotFound(name.to_string()));
+        }
+        Ok(path)
+    }
+    
+    /// Find another prompt whose metadata entry records the given file
+    ///
+    /// Distinct names can sanitize to the same file, e.g. `greeting@prod`
+    /// and `greeting_prod`; the file belongs to the name it was saved under.
+    fn file_owner<'a>(&self, metadata: &'a PromptMetadata, name: &str, path: &Path) -> Option<&'a str> {
+        let file_name = self.relative_file_name(path);
+        metadata.prompts.iter()
+            .find(|(key, entry)| *key != name && entry.file_name == file_name)
+            .map(|(key, _)| key.as_str())
+    }
+    
+    /// Fail if writing a prompt would overwrite a file owned by another name
+    fn check_file_owner(&self, metadata: &PromptMetadata, name: &str, path: &Path) -> Result<()> {
+        match self.file_owner(metadata, name, path) {
+            Some(owner) => Err(PromptError::InvalidName(format!("{} (maps to the same file as '{}')", name, owner))),
+            None => Ok(()),
+        }
+    }
+    
+    /// Path of a prompt file relative to the prompts directory, as stored in metadata
+    fn relative_file_name(&self, path: &Path) -> String {
+        let relative = path.strip_prefix(&self.prompts_dir).unwrap_or(path);
+        relative.components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+    
+    /// Prompt name for a file without a metadata entry
+    fn name_from_file(&self, path: &Path) -> String {
+        let file_name = self.relative_file_name(path);
+        let stem = file_name.rsplit_once('.').map_or(file_name.as_str(), |(stem, _)| stem);
+        match self.namespace_separator {
+            Some(separator) => stem.replace('/', &separator.to_string()),
+            None => stem.to_string(),
+        }
+    }
+    
+    /// List files that may hold prompts, including namespace subdirectories
+    ///
+    /// The default, profile, and metadata files are left out.
+    fn prompt_dir_files(&self) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        let mut dirs = vec![self.prompts_dir.clone()];
+        while let Some(dir) = dirs.pop() {
+            let entries = fs::read_dir(&dir)
+                .map_err(|e| PromptError::Storage(format!("Failed to read prompts directory: {}", e)))?;
+            for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+                if path.is_dir() {
+                    if self.namespace_separator.is_some() && path != self.history_dir && path != self.versions_dir {
+                        dirs.push(path);
+                    }
+                    continue;
+                }
+                let top_level = dir == self.prompts_dir;
+                let reserved = top_level && (path == self.metadata_file
+                    || (path.file_stem() == self.default_prompt_file.file_stem() && self.has_prompt_extension(&path))
+                    || self.profile_of(&path).is_some());
+                if path.is_file() && !reserved {
+                    files.push(path);
+                }
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+    
+    /// Get the file path for a named prompt, falling back to a legacy `.txt` file
     fn prompt_file_path(&self, name: &str) -> PathBuf {
-        self.prompts_dir.join(format!("{}.txt", Self::sanitize_name(name)))
+        let path = self.target_file_path(name);
+        Self::existing_or_legacy(path)
+    }
+    
+    /// Get the default prompt file path, falling back to a legacy `default.txt`
+    fn default_file_path(&self) -> PathBuf {
+        Self::existing_or_legacy(self.default_prompt_file.clone())
+    }
+    
+    /// Get the default prompt file path for a profile, falling back to a legacy `.txt` file
+    fn profile_default_path(&self, profile: &str) -> PathBuf {
+        Self::existing_or_legacy(self.profile_default_target(profile))
+    }
+    
+    /// Get the file path a profile's default prompt is written to
+    fn profile_default_target(&self, profile: &str) -> PathBuf {
+        self.prompts_dir.join(format!("default.{}.{}", profile, self.extension))
+    }
+    
+    /// Get the profile named by a `default.<profile>.<ext>` file, if it is one
+    fn profile_of(&self, path: &Path) -> Option<String> {
+        if !path.is_file() || !self.has_prompt_extension(path) {
+            return None;
+        }
+        path.file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.strip_prefix("default."))
+            .filter(|profile| !profile.is_empty())
+            .map(str::to_string)
+    }
+    
+    /// Use the `.txt` sibling of a path if only that one exists
+    fn existing_or_legacy(path: PathBuf) -> PathBuf {
+        if path.exists() {
+            return path;
+        }
+        let legacy = path.with_extension(LEGACY_EXTENSION);
+        if legacy.exists() { legacy } else { path }
+    }
+    
+    /// Remove a legacy `.txt` file superseded by a write to `path`
+    fn remove_legacy_file(&self, path: &Path) -> Result<()> {
+        let legacy = path.with_extension(LEGACY_EXTENSION);
+        if legacy != path && legacy.exists() {
+            fs::remove_file(&legacy)
+                .map_err(|e| PromptError::Storage(format!("Failed to remove {}: {}", legacy.display(), e)))?;
+            debug!("Removed superseded file {}", legacy.display());
+        }
+        Ok(())
+    }
+    
+    /// Check if a file in the prompts directory has a prompt file extension
+    fn has_prompt_extension(&self, path: &Path) -> bool {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext == self.extension || ext == LEGACY_EXTENSION)
     }
     
     /// Sanitize a prompt name for use as a filename
@@ -146,7 +787,136 @@ impl FileStorage {
             .collect()
     }
     
-    /// Load metadata from file
+    /// Get the directories a named prompt's archives live in, with their file suffix
+    ///
+    /// `keep_history` writes compressed `history/<name>/<timestamp>.txt.gz`
+    /// files and `keep_versions` plain `versions/<name>/<timestamp>.txt`
+    /// copies. Reads cover both, so switching settings keeps old archives.
+    fn archive_dirs(&self, name: &str) -> [(PathBuf, &'static str); 2] {
+        let stem = self.relative_stem(name);
+        [
+            (self.history_dir.join(&stem), HISTORY_SUFFIX),
+            (self.versions_dir.join(&stem), VERSION_SUFFIX),
+        ]
+    }
+    
+    /// Check if overwritten prompts are archived
+    fn archives_history(&self) -> bool {
+        self.keep_history || self.keep_versions > 0
+    }
+    
+    /// Archive the current content of a prompt before it is overwritten
+    ///
+    /// `keep_history` compresses it into `history/` and `keep_versions`
+    /// copies it to `versions/`, pruning that directory alone to the limit.
+    /// Both copies share a timestamp.
+    fn archive_version(&self, name: &str, file_path: &Path) -> Result<()> {
+        let content = fs::read(file_path)
+            .map_err(|e| PromptError::Storage(format!("Failed to read prompt '{}' for history: {}", name, e)))?;
+        
+        let [history, versions] = self.archive_dirs(name);
+        let mut targets = Vec::new();
+        if self.keep_history {
+            targets.push(history);
+        }
+        if self.keep_versions > 0 {
+            targets.push(versions.clone());
+        }
+        
+        let mut timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        while targets.iter().any(|(dir, suffix)| dir.join(format!("{}{}", timestamp, suffix)).exists()) {
+            timestamp += 1;
+        }
+        
+        for (dir, suffix) in targets {
+            fs::create_dir_all(&dir)
+                .map_err(|e| PromptError::Storage(format!("Failed to create history directory: {}", e)))?;
+            let version_path = dir.join(format!("{}{}", timestamp, suffix));
+            let archived = if suffix == HISTORY_SUFFIX {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(&content).and_then(|_| encoder.finish())
+            } else {
+                Ok(content.clone())
+            };
+            archived
+                .and_then(|bytes| fs::write(&version_path, bytes))
+                .map_err(|e| PromptError::Storage(format!("Failed to write history for '{}': {}", name, e)))?;
+            debug!("Archived prompt '{}' to {}", name, version_path.display());
+        }
+        
+        if self.keep_versions > 0 {
+            let copies: Vec<PathBuf> = self.history_versions(name)?
+                .into_iter()
+                .filter(|path| path.starts_with(&versions.0))
+                .collect();
+            self.remove_oldest(name, &copies, self.keep_versions)?;
+        }
+        Ok(())
+    }
+    
+    /// Delete all but the last `keep` of a prompt's archive files, returning how many went
+    fn remove_oldest(&self, name: &str, versions: &[PathBuf], keep: usize) -> Result<usize> {
+        let excess = versions.len().saturating_sub(keep);
+        for path in &versions[..excess] {
+            fs::remove_file(path)
+                .map_err(|e| PromptError::Storage(format!("Failed to prune history for '{}': {}", name, e)))?;
+        }
+        
+        if excess > 0 {
+            info!("Pruned {} historical versions of '{}'", excess, name);
+        }
+        Ok(excess)
+    }
+    
+    /// List a prompt's historical version files, oldest first
+    fn history_versions(&self, name: &str) -> Result<Vec<PathBuf>> {
+        Ok(self.history_entries(name)?.into_iter().map(|(_, path)| path).collect())
+    }
+    
+    /// List a prompt's historical versions with their timestamps, oldest first
+    fn history_entries(&self, name: &str) -> Result<Vec<(u128, PathBuf)>> {
+        self.validate_name(name)?;
+        let mut versions: Vec<(u128, PathBuf)> = Vec::new();
+        for (dir, suffix) in self.archive_dirs(name) {
+            if !dir.exists() {
+                continue;
+            }
+            let entries = fs::read_dir(&dir)
+                .map_err(|e| PromptError::Storage(format!("Failed to read history for '{}': {}", name, e)))?;
+            versions.extend(entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| {
+                    let path = entry.path();
+                    let timestamp = path.file_name()?.to_str()?.strip_suffix(suffix)?.parse().ok()?;
+                    Some((timestamp, path))
+                }));
+        }
+        versions.sort();
+        
+        Ok(versions)
+    }
+    
+    /// Write a file by staging it next to the target and renaming over it
+    ///
+    /// The staged `<file>.tmp` is flushed to disk before the rename, so a
+    /// crash leaves either the old or the new content in place, never a
+    /// truncated file. A stray `.tmp` left by an interrupted write is
+    /// simply overwritten next time.
+    fn write_atomic(path: &Path, content: &str) -> std::io::Result<()> {
+        let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+        tmp_name.push(".tmp");
+        let tmp_path = path.with_file_name(tmp_name);
+        
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)
+    }
+    
+    /// Load metadata from file, rebuilding it if corrupt and migrating older versions
     fn load_metadata(&self) -> Result<PromptMetadata> {
         if !self.metadata_file.exists() {
             return Ok(PromptMetadata::default());
@@ -155,74 +925,302 @@ impl FileStorage {
         let metadata_content = fs::read_to_string(&self.metadata_file)
             .map_err(|e| PromptError::Storage(format!("Failed to read metadata: {}", e)))?;
         
-        let metadata: PromptMetadata = serde_json::from_str(&metadata_content)?;
+        match serde_json::from_str(&metadata_content) {
+            Ok(metadata) => migrate_metadata(metadata),
+            Err(e) => {
+                warn!("Metadata file is corrupt ({}), rebuilding from prompt files", e);
+                let metadata = self.scan_metadata(&PromptMetadata::default())?;
+                self.save_metadata(&metadata)?;
+                Ok(metadata)
+            }
+        }
+    }
+    
+    /// Load metadata for a read, without rebuilding or writing a corrupt file
+    ///
+    /// A missing or corrupt file reads as empty; the next write repairs it.
+    fn peek_metadata(&self) -> PromptMetadata {
+        fs::read_to_string(&self.metadata_file).ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .and_then(|metadata| migrate_metadata(metadata).ok())
+            .unwrap_or_default()
+    }
+    
+    /// Watch the prompts directory for changes made outside the API
+    ///
+    /// Emits `Created`, `Modified`, and `Deleted` events named after the
+    /// affected prompt once a burst of writes settles, so one editor save
+    /// yields one event. Changes to the default prompt file and to
+    /// `metadata.json` produce `DefaultChanged` and `MetadataChanged`
+    /// instead. With a namespace separator, namespace subdirectories are
+    /// watched too. Watching stops after the receiver is dropped.
+    #[cfg(feature = "notify")]
+    pub fn watch(&self) -> Result<std::sync::mpsc::Receiver<crate::events::PromptChangeEvent>> {
+        use crate::watch::WatchedFile;
+        
+        let storage = self.clone();
+        let recursive = self.namespace_separator.is_some();
+        crate::watch::watch_directory(&self.prompts_dir, recursive, WATCH_DEBOUNCE, move |path| {
+            if path == storage.metadata_file {
+                return Some(WatchedFile::Metadata);
+            }
+            if !storage.has_prompt_extension(path)
+                || path.starts_with(&storage.history_dir)
+                || path.starts_with(&storage.versions_dir)
+            {
+                return None;
+            }
+            if path.parent() == Some(storage.prompts_dir.as_path()) {
+                let stem = path.file_stem().and_then(|s| s.to_str())?;
+                if stem == "default" {
+                    return Some(WatchedFile::Default);
+                }
+                if stem.starts_with("default.") {
+                    return None;
+                }
+            }
+            
+            let file_name = storage.relative_file_name(path);
+            let name = storage.peek_metadata().prompts.into_values()
+                .find(|entry| entry.file_name == file_name)
+                .map_or_else(|| storage.name_from_file(path), |entry| entry.name);
+            Some(WatchedFile::Prompt(name))
+        })
+    }
+    
+    /// Compare metadata with the prompt files on disk
+    ///
+    /// Reports entries whose file size or modification time no longer
+    /// match, entries without a file, and prompt files without an entry,
+    /// sorted by prompt name. Nothing is modified.
+    pub fn verify(&self) -> Result<Vec<Discrepancy>> {
+        let metadata = self.load_metadata()?;
+        let mut discrepancies = Vec::new();
+        
+        for entry in metadata.prompts.values() {
+            let kind = match fs::metadata(self.prompts_dir.join(&entry.file_name)) {
+                Err(_) => Some(DiscrepancyKind::MissingFile),
+                Ok(file) if file.len() != entry.size => {
+                    Some(DiscrepancyKind::SizeMismatch { recorded: entry.size, actual: file.len() })
+                }
+                Ok(file) => file.modified().ok()
+                    .filter(|actual| *actual != entry.modified_at)
+                    .map(|actual| DiscrepancyKind::ModifiedMismatch { recorded: entry.modified_at, actual }),
+            };
+            if let Some(kind) = kind {
+                discrepancies.push(Discrepancy { name: entry.name.clone(), kind });
+            }
+        }
+        
+        let tracked: Vec<&str> = metadata.prompts.values().map(|e| e.file_name.as_str()).collect();
+        for path in self.prompt_dir_files()? {
+            let file_name = self.relative_file_name(&path);
+            if self.has_prompt_extension(&path) && !tracked.contains(&file_name.as_str()) {
+                discrepancies.push(Discrepancy { name: self.name_from_file(&path), kind: DiscrepancyKind::UntrackedFile });
+            }
+        }
+        
+        discrepancies.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(discrepancies)
+    }
+    
+    /// Regenerate metadata from the prompt files in the prompts directory
+    ///
+    /// Every prompt file except the default gets an entry named after its
+    /// file stem, with size, timestamps, and hash read from disk. Tags and
+    /// content types survive for files that already had an entry; entries
+    /// without a file are dropped.
+    pub fn rebuild_metadata(&self) -> Result<()> {
+        let previous = self.load_metadata()?;
+        let metadata = self.scan_metadata(&previous)?;
+        self.save_metadata(&metadata)?;
+        
+        info!("Rebuilt metadata for {} prompts", metadata.prompts.len());
+        Ok(())
+    }
+    
+    /// Build metadata from prompt files, keeping details from previous entries
+    fn scan_metadata(&self, previous: &PromptMetadata) -> Result<PromptMetadata> {
+        let mut metadata = PromptMetadata::default();
+        
+        for path in self.prompt_dir_files()? {
+            if !self.has_prompt_extension(&path) {
+                continue;
+            }
+            let file_name = self.relative_file_name(&path);
+            
+            let bytes = fs::read(&path)
+                .map_err(|e| PromptError::Storage(format!("Failed to read {}: {}", path.display(), e)))?;
+            if looks_binary(&bytes) {
+                warn!("Skipping non-text file in prompts directory: {}", path.display());
+                continue;
+            }
+            let file_metadata = fs::metadata(&path)
+                .map_err(|e| PromptError::Storage(format!("Failed to read file metadata: {}", e)))?;
+            let modified_at = file_metadata.modified().unwrap_or_else(|_| SystemTime::now());
+            let content = String::from_utf8_lossy(&bytes);
+            
+            let known = previous.prompts.values().find(|entry| entry.file_name == file_name);
+            let name = known.map_or_else(|| self.name_from_file(&path), |entry| entry.name.clone());
+            let entry = PromptEntry {
+                name: name.clone(),
+                file_name,
+                created_at: known.map(|entry| entry.created_at)
+                    .unwrap_or_else(|| file_metadata.created().unwrap_or(modified_at)),
+                modified_at,
+                size: file_metadata.len(),
+                content_hash: Some(content_hash(&content)),
+                content_type: known.and_then(|entry| entry.content_type.clone()),
+                tags: known.map(|entry| entry.tags.clone()).unwrap_or_default(),
+                examples: known.map(|entry| entry.examples.clone()).unwrap_or_default(),
+            };
+            metadata.prompts.insert(name, entry);
+        }
+        
         Ok(metadata)
     }
     
     /// Save metadata to file
     fn save_metadata(&self, metadata: &PromptMetadata) -> Result<()> {
         let metadata_json = serde_json::to_string_pretty(metadata)?;
-        fs::write(&self.metadata_file, metadata_json)
+        Self::write_atomic(&self.metadata_file, &metadata_json)
             .map_err(|e| PromptError::Storage(format!("Failed to write metadata: {}", e)))?;
         Ok(())
     }
     
     /// Update metadata for a prompt
-    fn update_prompt_metadata(&self, name: &str, file_path: &Path) -> Result<()> {
+    fn update_prompt_metadata(&self, name: &str, file_path: &Path, content: &str) -> Result<()> {
         let mut metadata = self.load_metadata()?;
-        
+        self.record_prompt(&mut metadata, name, file_path, content)?;
+        self.save_metadata(&metadata)?;
+        Ok(())
+    }
+    
+    /// Record a prompt file in loaded metadata without saving it
+    fn record_prompt(&self, metadata: &mut PromptMetadata, name: &str, file_path: &Path, content: &str) -> Result<()> {
         let file_metadata = fs::metadata(file_path)
             .map_err(|e| PromptError::Storage(format!("Failed to read file metadata: {}", e)))?;
         
+        // A rebuild may have indexed this file under its stem
+        let file_name = self.relative_file_name(file_path);
+        metadata.prompts.retain(|key, entry| key == name || entry.file_name != file_name);
+        
+        let previous = metadata.prompts.get(name);
         let entry = PromptEntry {
             name: name.to_string(),
-            file_name: file_path.file_name()
-                .and_then(|s| s.to_str())
-                .unwrap_or("unknown")
-                .to_string(),
-            created_at: file_metadata.created().unwrap_or_else(|_| SystemTime::now()),
+            file_name,
+            created_at: previous.map(|e| e.created_at)
+                .unwrap_or_else(|| file_metadata.created().unwrap_or_else(|_| SystemTime::now())),
             modified_at: file_metadata.modified().unwrap_or_else(|_| SystemTime::now()),
             size: file_metadata.len(),
+            content_hash: Some(content_hash(content)),
+            content_type: previous.and_then(|e| e.content_type.clone()),
+            tags: previous.map(|e| e.tags.clone()).unwrap_or_default(),
+            examples: previous.map(|e| e.examples.clone()).unwrap_or_default(),
         };
         
         metadata.prompts.insert(name.to_string(), entry);
-        self.save_metadata(&metadata)?;
         Ok(())
     }
     
-    /// Remove prompt from metadata
-    fn remove_prompt_metadata(&self, name: &str) -> Result<()> {
-        let mut metadata = self.load_metadata()?;
-        metadata.prompts.remove(name);
-        self.save_metadata(&metadata)?;
-        Ok(())
+    /// Write a prompt file as `save_prompt` does, returning its path
+    fn write_prompt_file(&self, metadata: &PromptMetadata, name: &str, prompt: &str) -> Result<PathBuf> {
+        self.validate_name(name)?;
+        let existing = self.prompt_file_path(name);
+        let file_path = self.target_file_path(name);
+        self.check_file_owner(metadata, name, &file_path)?;
+        self.check_file_owner(metadata, name, &existing)?;
+        self.create_namespace_dir(&file_path)?;
+        
+        if self.archives_history() && existing.exists() {
+            self.archive_version(name, &existing)?;
+        }
+        
+        Self::write_atomic(&file_path, prompt)
+            .map_err(|e| PromptError::Storage(format!("Failed to write prompt '{}': {}", name, e)))?;
+        self.remove_legacy_file(&file_path)?;
+        Ok(file_path)
+    }
+    
+    /// Create the namespace directory a prompt file lives in
+    fn create_namespace_dir(&self, file_path: &Path) -> Result<()> {
+        match file_path.parent() {
+            Some(dir) if dir != self.prompts_dir => fs::create_dir_all(dir)
+                .map_err(|e| PromptError::Storage(format!("Failed to create namespace directory: {}", e))),
+            _ => Ok(()),
+        }
     }
 }
 
 impl PromptStorage for FileStorage {
     fn load_default(&self) -> Result<String> {
-        if !self.default_prompt_file.exists() {
-            debug!("Default prompt file not found, returning factory default");
-            return Ok(crate::PromptManager::factory_default_prompt());
+        let default_file = self.default_file_path();
+        if !default_file.exists() {
+            if self.lazy_init {
+                debug!("Materializing factory default on first access");
+                self.save_default(&self.factory_default)?;
+            } else {
+                debug!("Default prompt file not found, returning factory default");
+            }
+            return Ok(self.factory_default.trim().to_string());
         }
         
-        let prompt = fs::read_to_string(&self.default_prompt_file)
+        let prompt = fs::read_to_string(&default_file)
             .map_err(|e| PromptError::Storage(format!("Failed to read default prompt: {}", e)))?;
         
-        debug!("Loaded default prompt from {}", self.default_prompt_file.display());
+        debug!("Loaded default prompt from {}", default_file.display());
         Ok(prompt.trim().to_string())
     }
     
     fn save_default(&self, prompt: &str) -> Result<()> {
-        fs::write(&self.default_prompt_file, prompt.trim())
+        Self::write_atomic(&self.default_prompt_file, prompt)
             .map_err(|e| PromptError::Storage(format!("Failed to write default prompt: {}", e)))?;
+        self.remove_legacy_file(&self.default_prompt_file)?;
         
         info!("Saved default prompt to {}", self.default_prompt_file.display());
         Ok(())
     }
     
+    fn load_profile_default(&self, profile: &str) -> Result<Option<String>> {
+        let path = self.profile_default_path(profile);
+        if !path.exists() {
+            return Ok(None);
+        }
+        
+        let prompt = fs::read_to_string(&path)
+            .map_err(|e| PromptError::Storage(format!("Failed to read default for profile '{}': {}", profile, e)))?;
+        debug!("Loaded default for profile '{}' from {}", profile, path.display());
+        Ok(Some(prompt.trim().to_string()))
+    }
+    
+    fn save_profile_default(&self, profile: &str, prompt: &str) -> Result<()> {
+        let path = self.profile_default_target(profile);
+        Self::write_atomic(&path, prompt)
+            .map_err(|e| PromptError::Storage(format!("Failed to write default for profile '{}': {}", profile, e)))?;
+        self.remove_legacy_file(&path)?;
+        
+        info!("Saved default for profile '{}' to {}", profile, path.display());
+        Ok(())
+    }
+    
+    fn list_profiles(&self) -> Result<Vec<String>> {
+        let entries = fs::read_dir(&self.prompts_dir)
+            .map_err(|e| PromptError::Storage(format!("Failed to read prompts directory: {}", e)))?;
+        let mut profiles: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| self.profile_of(&entry.path()))
+            .collect();
+        profiles.sort();
+        profiles.dedup();
+        Ok(profiles)
+    }
+    
     fn load_prompt(&self, name: &str) -> Result<String> {
-        let file_path = self.prompt_file_path(name);
+        Ok(self.load_prompt_raw(name)?.trim().to_string())
+    }
+    
+    fn load_prompt_raw(&self, name: &str) -> Result<String> {
+        let file_path = self.checked_file_path(&self.peek_metadata(), name)?;
         
         if !file_path.exists() {
             return Err(PromptError::PromptNotFound(name.to_string()));
@@ -232,22 +1230,53 @@ impl PromptStorage for FileStorage {
             .map_err(|e| PromptError::Storage(format!("Failed to read prompt '{}': {}", name, e)))?;
         
         debug!("Loaded prompt '{}' from {}", name, file_path.display());
-        Ok(prompt.trim().to_string())
+        Ok(prompt)
     }
     
     fn save_prompt(&self, name: &str, prompt: &str) -> Result<()> {
-        let file_path = self.prompt_file_path(name);
-        
-        fs::write(&file_path, prompt.trim())
-            .map_err(|e| PromptError::Storage(format!("Failed to write prompt '{}': {}", name, e)))?;
+        let file_path = self.write_prompt_file(&self.load_metadata()?, name, prompt)?;
         
         // Update metadata
-        self.update_prompt_metadata(name, &file_path)?;
+        self.update_prompt_metadata(name, &file_path, prompt)?;
         
         info!("Saved prompt '{}' to {}", name, file_path.display());
         Ok(())
     }
     
+    fn save_prompts(&self, entries: &[(String, String)]) -> Result<Vec<Result<()>>> {
+        let mut metadata = self.load_metadata()?;
+        let results: Vec<Result<()>> = entries.iter()
+            .map(|(name, prompt)| {
+                let file_path = self.write_prompt_file(&metadata, name, prompt)?;
+                self.record_prompt(&mut metadata, name, &file_path, prompt)
+            })
+            .collect();
+        self.save_metadata(&metadata)?;
+        
+        info!("Saved {} of {} prompts", results.iter().filter(|r| r.is_ok()).count(), entries.len());
+        Ok(results)
+    }
+    
+    fn delete_prompts(&self, names: &[String]) -> Result<Vec<Result<()>>> {
+        let mut metadata = self.load_metadata()?;
+        let results: Vec<Result<()>> = names.iter()
+            .map(|name| {
+                let file_path = self.checked_file_path(&metadata, name)?;
+                if !file_path.exists() {
+                    return Err(PromptError::PromptNotFound(name.to_string()));
+                }
+                fs::remove_file(&file_path)
+                    .map_err(|e| PromptError::Storage(format!("Failed to delete prompt '{}': {}", name, e)))?;
+                metadata.prompts.remove(name);
+                Ok(())
+            })
+            .collect();
+        self.save_metadata(&metadata)?;
+        
+        info!("Deleted {} of {} prompts", results.iter().filter(|r| r.is_ok()).count(), names.len());
+        Ok(results)
+    }
+    
     fn list_prompts(&self) -> Result<Vec<String>> {
         let metadata = self.load_metadata()?;
         let mut prompts: Vec<String> = metadata.prompts.keys().cloned().collect();
@@ -258,7 +1287,8 @@ impl PromptStorage for FileStorage {
     }
     
     fn delete_prompt(&self, name: &str) -> Result<()> {
-        let file_path = self.prompt_file_path(name);
+        let mut metadata = self.load_metadata()?;
+        let file_path = self.checked_file_path(&metadata, name)?;
         
         if !file_path.exists() {
             return Err(PromptError::PromptNotFound(name.to_string()));
@@ -268,32 +1298,341 @@ impl PromptStorage for FileStorage {
             .map_err(|e| PromptError::Storage(format!("Failed to delete prompt '{}': {}", name, e)))?;
         
         // Remove from metadata
-        self.remove_prompt_metadata(name)?;
+        metadata.prompts.remove(name);
+        self.save_metadata(&metadata)?;
         
         info!("Deleted prompt '{}'", name);
         Ok(())
     }
     
     fn prompt_exists(&self, name: &str) -> bool {
-        self.prompt_file_path(name).exists()
+        self.checked_file_path(&self.peek_metadata(), name).is_ok_and(|path| path.exists())
     }
     
     fn get_prompt_info(&self, name: &str) -> Result<PromptInfo> {
         let metadata = self.load_metadata()?;
         
         if let Some(entry) = metadata.prompts.get(name) {
-            Ok(PromptInfo {
-                name: entry.name.clone(),
-                size: entry.size,
-                created_at: entry.created_at,
-                modified_at: entry.modified_at,
-                file_path: self.prompt_file_path(name),
-            })
+            Ok(entry.to_info(self.prompt_file_path(name)))
         } else {
             Err(PromptError::PromptNotFound(name.to_string()))
         }
     }
-}
+    
+    fn refresh_prompt_info(&self, name: &str) -> Result<PromptInfo> {
+        let mut info = self.get_prompt_info(name)?;
+        let file_metadata = match fs::metadata(&info.file_path) {
+            Ok(file_metadata) => file_metadata,
+            Err(_) => return Err(PromptError::PromptNotFound(name.to_string())),
+        };
+        let modified_at = file_metadata.modified().unwrap_or(info.modified_at);
+        if file_metadata.len() == info.size && modified_at == info.modified_at {
+            return Ok(info);
+        }
+        
+        // Best effort: the stat result is returned even if metadata can't be updated
+        let refreshed = fs::read_to_string(&info.file_path)
+            .map_err(|e| PromptError::Storage(format!("Failed to read prompt '{}': {}", name, e)))
+            .and_then(|content| self.update_prompt_metadata(name, &info.file_path, &content));
+        match refreshed {
+            Ok(()) => {
+                debug!("Refreshed metadata of prompt '{}' from disk", name);
+                self.get_prompt_info(name)
+            }
+            Err(e) => {
+                warn!("Failed to refresh metadata of prompt '{}': {}", name, e);
+                info.size = file_metadata.len();
+                info.modified_at = modified_at;
+                Ok(info)
+            }
+        }
+    }
+    
+    fn set_content_type(&self, name: &str, content_type: &str) -> Result<()> {
+        let mut metadata = self.load_metadata()?;
+        let entry = metadata.prompts.get_mut(name)
+            .ok_or_else(|| PromptError::PromptNotFound(name.to_string()))?;
+        entry.content_type = Some(content_type.to_string());
+        self.save_metadata(&metadata)?;
+        
+        debug!("Set content type of prompt '{}' to {}", name, content_type);
+        Ok(())
+    }
+    
+    fn set_tags(&self, name: &str, tags: Vec<String>) -> Result<()> {
+        let mut metadata = self.load_metadata()?;
+        let entry = metadata.prompts.get_mut(name)
+            .ok_or_else(|| PromptError::PromptNotFound(name.to_string()))?;
+        
+        let mut normalized: Vec<String> = Vec::new();
+        for tag in tags {
+            let tag = tag.trim();
+            if !tag.is_empty() && !normalized.iter().any(|t| t == tag) {
+                normalized.push(tag.to_string());
+            }
+        }
+        entry.tags = normalized;
+        self.save_metadata(&metadata)?;
+        
+        debug!("Set tags on prompt '{}'", name);
+        Ok(())
+    }
+    
+    fn set_examples(&self, name: &str, examples: Vec<HashMap<String, String>>) -> Result<()> {
+        let mut metadata = self.load_metadata()?;
+        let entry = metadata.prompts.get_mut(name)
+            .ok_or_else(|| PromptError::PromptNotFound(name.to_string()))?;
+        entry.examples = examples;
+        self.save_metadata(&metadata)?;
+        
+        debug!("Set examples on prompt '{}'", name);
+        Ok(())
+    }
+    
+    fn swap_prompts(&self, a: &str, b: &str) -> Result<()> {
+        let mut metadata = self.load_metadata()?;
+        let path_a = self.checked_file_path(&metadata, a)?;
+        let path_b = self.checked_file_path(&metadata, b)?;
+        
+        for (name, path) in [(a, &path_a), (b, &path_b)] {
+            if !path.exists() {
+                return Err(PromptError::PromptNotFound(name.to_string()));
+            }
+        }
+        if path_a == path_b {
+            return Ok(());
+        }
+        
+        let content_a = fs::read_to_string(&path_a)
+            .map_err(|e| PromptError::Storage(format!("Failed to read prompt '{}': {}", a, e)))?;
+        let content_b = fs::read_to_string(&path_b)
+            .map_err(|e| PromptError::Storage(format!("Failed to read prompt '{}': {}", b, e)))?;
+        
+        // Each file is replaced by rename, so neither is ever missing
+        Self::write_atomic(&path_a, &content_b)
+            .map_err(|e| PromptError::Storage(format!("Failed to write prompt '{}': {}", a, e)))?;
+        Self::write_atomic(&path_b, &content_a)
+            .map_err(|e| PromptError::Storage(format!("Failed to write prompt '{}': {}", b, e)))?;
+        
+        let entry_a = metadata.prompts.get(a).cloned();
+        let entry_b = metadata.prompts.get(b).cloned();
+        let now = SystemTime::now();
+        for (name, other) in [(a, entry_b), (b, entry_a)] {
+            if let (Some(entry), Some(other)) = (metadata.prompts.get_mut(name), other) {
+                entry.size = other.size;
+                entry.content_hash = other.content_hash;
+                entry.content_type = other.content_type;
+                entry.modified_at = now;
+            }
+        }
+        self.save_metadata(&metadata)?;
+        
+        info!("Swapped prompts '{}' and '{}'", a, b);
+        Ok(())
+    }
+    
+    fn rename_prompt(&self, old: &str, new: &str) -> Result<()> {
+        let mut metadata = self.load_metadata()?;
+        let old_path = self.checked_file_path(&metadata, old)?;
+        if !old_path.exists() || !metadata.prompts.contains_key(old) {
+            return Err(PromptError::PromptNotFound(old.to_string()));
+        }
+        
+        // Distinct names can sanitize to the same file, e.g. "a/b" and "a_b"
+        self.validate_name(new)?;
+        let new_path = self.target_file_path(new);
+        let collides = new_path != old_path && (new_path.exists() || self.prompt_file_path(new).exists());
+        if metadata.prompts.contains_key(new) || collides {
+            return Err(PromptError::PromptAlreadyExists(new.to_string()));
+        }
+        
+        self.create_namespace_dir(&new_path)?;
+        fs::rename(&old_path, &new_path)
+            .map_err(|e| PromptError::Storage(format!("Failed to rename prompt '{}': {}", old, e)))?;
+        
+        for ((old_history, _), (new_history, _)) in self.archive_dirs(old).into_iter().zip(self.archive_dirs(new)) {
+            if old_history == new_history || !old_history.exists() {
+                continue;
+            }
+            if let Some(parent) = new_history.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| PromptError::Storage(format!("Failed to move history of '{}': {}", old, e)))?;
+            }
+            fs::rename(&old_history, &new_history)
+                .map_err(|e| PromptError::Storage(format!("Failed to move history of '{}': {}", old, e)))?;
+        }
+        
+        if let Some(mut entry) = metadata.prompts.remove(old) {
+            entry.name = new.to_string();
+            entry.file_name = self.relative_file_name(&new_path);
+            metadata.prompts.insert(new.to_string(), entry);
+        }
+        self.save_metadata(&metadata)?;
+        
+        info!("Renamed prompt '{}' to '{}'", old, new);
+        Ok(())
+    }
+    
+    fn copy_prompt(&self, source: &str, dest: &str, overwrite: bool) -> Result<()> {
+        let mut metadata = self.load_metadata()?;
+        let source_path = self.checked_file_path(&metadata, source)?;
+        let Some(source_entry) = metadata.prompts.get(source).cloned().filter(|_| source_path.exists()) else {
+            return Err(PromptError::PromptNotFound(source.to_string()));
+        };
+        
+        self.validate_name(dest)?;
+        let dest_path = self.target_file_path(dest);
+        let existing = self.prompt_file_path(dest);
+        let taken = metadata.prompts.contains_key(dest) || existing.exists();
+        if dest_path == source_path || existing == source_path || (taken && !overwrite) {
+            return Err(PromptError::PromptAlreadyExists(dest.to_string()));
+        }
+        self.check_file_owner(&metadata, dest, &dest_path)?;
+        self.check_file_owner(&metadata, dest, &existing)?;
+        
+        if self.archives_history() && existing.exists() {
+            self.archive_version(dest, &existing)?;
+        }
+        
+        let content = fs::read_to_string(&source_path)
+            .map_err(|e| PromptError::Storage(format!("Failed to read prompt '{}': {}", source, e)))?;
+        self.create_namespace_dir(&dest_path)?;
+        Self::write_atomic(&dest_path, &content)
+            .map_err(|e| PromptError::Storage(format!("Failed to write prompt '{}': {}", dest, e)))?;
+        self.remove_legacy_file(&dest_path)?;
+        
+        let now = SystemTime::now();
+        let entry = PromptEntry {
+            name: dest.to_string(),
+            file_name: self.relative_file_name(&dest_path),
+            created_at: now,
+            modified_at: now,
+            tags: Vec::new(),
+            ..source_entry
+        };
+        metadata.prompts.insert(dest.to_string(), entry);
+        self.save_metadata(&metadata)?;
+        
+        info!("Copied prompt '{}' to '{}'", source, dest);
+        Ok(())
+    }
+    
+    fn reconcile(&self) -> Result<ReconcileReport> {
+        let mut report = ReconcileReport::default();
+        let known = self.load_metadata()?;
+        let known_files: Vec<&str> = known.prompts.values().map(|e| e.file_name.as_str()).collect();
+        
+        for path in self.prompt_dir_files()? {
+            let bytes = fs::read(&path)
+                .map_err(|e| PromptError::Storage(format!("Failed to read {}: {}", path.display(), e)))?;
+            if looks_binary(&bytes) {
+                warn!("Skipping non-text file in prompts directory: {}", path.display());
+                report.skipped.push(path);
+                continue;
+            }
+            
+            if !self.has_prompt_extension(&path) {
+                continue;
+            }
+            let file_name = self.relative_file_name(&path);
+            let name = self.name_from_file(&path);
+            if known_files.contains(&file_name.as_str()) {
+                continue;
+            }
+            
+            let content = String::from_utf8_lossy(&bytes);
+            self.update_prompt_metadata(&name, &path, &content)?;
+            info!("Reconciled untracked prompt '{}'", name);
+            report.added.push(name.to_string());
+        }
+        
+        Ok(report)
+    }
+    
+    fn clean_temp_files(&self) -> Result<Vec<PathBuf>> {
+        let mut paths: Vec<PathBuf> = self.prompt_dir_files()?
+            .into_iter()
+            .filter(|path| !self.has_prompt_extension(path))
+            .filter(|path| {
+                let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+                let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+                TEMP_EXTENSIONS.contains(&extension) || file_name.ends_with('~')
+            })
+            .collect();
+        paths.sort();
+        
+        for path in &paths {
+            fs::remove_file(path)
+                .map_err(|e| PromptError::Storage(format!("Failed to remove {}: {}", path.display(), e)))?;
+            debug!("Removed temporary file {}", path.display());
+        }
+        
+        if !paths.is_empty() {
+            info!("Removed {} temporary files from prompts directory", paths.len());
+        }
+        Ok(paths)
+    }
+    
+    fn vacuum(&self) -> Result<usize> {
+        let mut metadata = self.load_metadata()?;
+        let before = metadata.prompts.len();
+        metadata.prompts.retain(|name, entry| {
+            let exists = self.prompts_dir.join(&entry.file_name).exists();
+            if !exists {
+                debug!("Dropping metadata for '{}' with missing file {}", name, entry.file_name);
+            }
+            exists
+        });
+        
+        let removed = before - metadata.prompts.len();
+        if removed > 0 {
+            self.save_metadata(&metadata)?;
+            info!("Removed {} dangling metadata entries", removed);
+        }
+        Ok(removed)
+    }
+    
+    fn prune_history(&self, name: &str, keep: usize) -> Result<usize> {
+        self.remove_oldest(name, &self.history_versions(name)?, keep)
+    }
+    
+    fn history_size(&self, name: &str) -> Result<u64> {
+        self.history_versions(name)?
+            .iter()
+            .map(|path| {
+                fs::metadata(path)
+                    .map(|m| m.len())
+                    .map_err(|e| PromptError::Storage(format!("Failed to read history for '{}': {}", name, e)))
+            })
+            .sum()
+    }
+    
+    fn list_versions(&self, name: &str) -> Result<Vec<u128>> {
+        let mut timestamps: Vec<u128> = self.history_entries(name)?.into_iter().map(|(timestamp, _)| timestamp).collect();
+        // With both archives enabled, each version is stored twice
+        timestamps.dedup();
+        Ok(timestamps)
+    }
+    
+    fn load_version(&self, name: &str, timestamp: u128) -> Result<String> {
+        let Some((_, path)) = self.history_entries(name)?.into_iter().find(|(t, _)| *t == timestamp) else {
+            return Err(PromptError::PromptNotFound(format!("{} (version {})", name, timestamp)));
+        };
+        let read_error = |e: std::io::Error| {
+            PromptError::Storage(format!("Failed to read version {} of '{}': {}", timestamp, name, e))
+        };
+        let bytes = fs::read(&path).map_err(read_error)?;
+        
+        let mut content = String::new();
+        if path.to_string_lossy().ends_with(HISTORY_SUFFIX) {
+            GzDecoder::new(&bytes[..]).read_to_string(&mut content).map_err(read_error)?;
+        } else {
+            content = String::from_utf8(bytes)
+                .map_err(|e| PromptError::Storage(format!("Failed to read version {} of '{}': {}", timestamp, name, e)))?;
+        }
+        Ok(content)
+    }
+}
 
 impl Default for FileStorage {
     fn default() -> Self {
@@ -301,6 +1640,222 @@ impl Default for FileStorage {
     }
 }
 
+/// Key the default prompt is stored under in `MemoryStorage`
+///
+/// The NUL byte keeps it from clashing with any real prompt name.
+const MEMORY_DEFAULT_KEY: &str = "\0default";
+
+/// In-memory storage for tests and ephemeral use
+///
+/// Nothing is persisted; the library lives as long as the storage does.
+pub struct MemoryStorage {
+    prompts: Mutex<HashMap<String, (String, PromptInfo)>>,
+    profile_defaults: Mutex<BTreeMap<String, String>>,
+}
+
+impl MemoryStorage {
+    /// Create an in-memory storage holding the factory default prompt
+    pub fn new() -> Self {
+        let storage = Self {
+            prompts: Mutex::new(HashMap::new()),
+            profile_defaults: Mutex::new(BTreeMap::new()),
+        };
+        storage.put(MEMORY_DEFAULT_KEY, &crate::PromptManager::factory_default_prompt());
+        storage
+    }
+    
+    /// Lock the prompt map
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, (String, PromptInfo)>> {
+        self.prompts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    
+    /// Store content, keeping creation time, content type, tags, and examples of an existing entry
+    fn put(&self, key: &str, prompt: &str) {
+        let content = prompt.trim().to_string();
+        let now = SystemTime::now();
+        let mut prompts = self.lock();
+        let previous = prompts.remove(key).map(|(_, info)| info);
+        let info = PromptInfo {
+            name: key.to_string(),
+            size: content.len() as u64,
+            created_at: previous.as_ref().map_or(now, |info| info.created_at),
+            modified_at: now,
+            file_path: PathBuf::new(),
+            content_hash: Some(content_hash(&content)),
+            content_type: previous.as_ref().and_then(|info| info.content_type.clone()),
+            tags: previous.as_ref().map(|info| info.tags.clone()).unwrap_or_default(),
+            examples: previous.map(|info| info.examples).unwrap_or_default(),
+        };
+        prompts.insert(key.to_string(), (content, info));
+    }
+    
+    /// Apply a change to a prompt's metadata
+    fn update_info<F: FnOnce(&mut PromptInfo)>(&self, name: &str, update: F) -> Result<()> {
+        let mut prompts = self.lock();
+        let (_, info) = prompts.get_mut(name)
+            .filter(|_| name != MEMORY_DEFAULT_KEY)
+            .ok_or_else(|| PromptError::PromptNotFound(name.to_string()))?;
+        update(info);
+        Ok(())
+    }
+}
+
+impl Default for MemoryStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PromptStorage for MemoryStorage {
+    fn load_default(&self) -> Result<String> {
+        Ok(self.lock()
+            .get(MEMORY_DEFAULT_KEY)
+            .map(|(content, _)| content.clone())
+            .unwrap_or_default())
+    }
+    
+    fn save_default(&self, prompt: &str) -> Result<()> {
+        self.put(MEMORY_DEFAULT_KEY, prompt);
+        Ok(())
+    }
+    
+    fn load_profile_default(&self, profile: &str) -> Result<Option<String>> {
+        let defaults = self.profile_defaults.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(defaults.get(profile).cloned())
+    }
+    
+    fn save_profile_default(&self, profile: &str, prompt: &str) -> Result<()> {
+        let mut defaults = self.profile_defaults.lock().unwrap_or_else(|e| e.into_inner());
+        defaults.insert(profile.to_string(), prompt.trim().to_string());
+        Ok(())
+    }
+    
+    fn list_profiles(&self) -> Result<Vec<String>> {
+        let defaults = self.profile_defaults.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(defaults.keys().cloned().collect())
+    }
+    
+    fn load_prompt(&self, name: &str) -> Result<String> {
+        if name == MEMORY_DEFAULT_KEY {
+            return Err(PromptError::PromptNotFound(name.to_string()));
+        }
+        self.lock()
+            .get(name)
+            .map(|(content, _)| content.clone())
+            .ok_or_else(|| PromptError::PromptNotFound(name.to_string()))
+    }
+    
+    fn save_prompt(&self, name: &str, prompt: &str) -> Result<()> {
+        if name == MEMORY_DEFAULT_KEY {
+            return Err(PromptError::InvalidPrompt("Prompt name is reserved".to_string()));
+        }
+        self.put(name, prompt);
+        Ok(())
+    }
+    
+    fn list_prompts(&self) -> Result<Vec<String>> {
+        let mut prompts: Vec<String> = self.lock()
+            .keys()
+            .filter(|name| *name != MEMORY_DEFAULT_KEY)
+            .cloned()
+            .collect();
+        prompts.sort();
+        Ok(prompts)
+    }
+    
+    fn delete_prompt(&self, name: &str) -> Result<()> {
+        if name == MEMORY_DEFAULT_KEY || self.lock().remove(name).is_none() {
+            return Err(PromptError::PromptNotFound(name.to_string()));
+        }
+        Ok(())
+    }
+    
+    fn prompt_exists(&self, name: &str) -> bool {
+        name != MEMORY_DEFAULT_KEY && self.lock().contains_key(name)
+    }
+    
+    fn get_prompt_info(&self, name: &str) -> Result<PromptInfo> {
+        if name == MEMORY_DEFAULT_KEY {
+            return Err(PromptError::PromptNotFound(name.to_string()));
+        }
+        self.lock()
+            .get(name)
+            .map(|(_, info)| info.clone())
+            .ok_or_else(|| PromptError::PromptNotFound(name.to_string()))
+    }
+    
+    fn set_content_type(&self, name: &str, content_type: &str) -> Result<()> {
+        self.update_info(name, |info| info.content_type = Some(content_type.to_string()))
+    }
+    
+    fn swap_prompts(&self, a: &str, b: &str) -> Result<()> {
+        let mut prompts = self.lock();
+        let entry = |name: &str| {
+            prompts
+                .get(name)
+                .filter(|_| name != MEMORY_DEFAULT_KEY)
+                .map(|(content, info)| (content.clone(), info.content_type.clone()))
+                .ok_or_else(|| PromptError::PromptNotFound(name.to_string()))
+        };
+        let (content_a, type_a) = entry(a)?;
+        let (content_b, type_b) = entry(b)?;
+        let now = SystemTime::now();
+        for (name, content, content_type) in [(a, content_b, type_b), (b, content_a, type_a)] {
+            if let Some((stored, info)) = prompts.get_mut(name) {
+                info.size = content.len() as u64;
+                info.content_hash = Some(content_hash(&content));
+                info.content_type = content_type;
+                info.modified_at = now;
+                *stored = content;
+            }
+        }
+        Ok(())
+    }
+    
+    fn rename_prompt(&self, old: &str, new: &str) -> Result<()> {
+        if !self.prompt_exists(old) {
+            return Err(PromptError::PromptNotFound(old.to_string()));
+        }
+        if new == MEMORY_DEFAULT_KEY || self.prompt_exists(new) {
+            return Err(PromptError::PromptAlreadyExists(new.to_string()));
+        }
+        
+        let mut prompts = self.lock();
+        if let Some((content, mut info)) = prompts.remove(old) {
+            info.name = new.to_string();
+            prompts.insert(new.to_string(), (content, info));
+        }
+        Ok(())
+    }
+    
+    fn copy_prompt(&self, source: &str, dest: &str, overwrite: bool) -> Result<()> {
+        let content = self.load_prompt(source)?;
+        let source_info = self.get_prompt_info(source)?;
+        if source == dest || dest == MEMORY_DEFAULT_KEY || (!overwrite && self.prompt_exists(dest)) {
+            return Err(PromptError::PromptAlreadyExists(dest.to_string()));
+        }
+        
+        let now = SystemTime::now();
+        let info = PromptInfo {
+            name: dest.to_string(),
+            created_at: now,
+            modified_at: now,
+            tags: Vec::new(),
+            ..source_info
+        };
+        self.lock().insert(dest.to_string(), (content, info));
+        Ok(())
+    }
+    
+    fn set_tags(&self, name: &str, tags: Vec<String>) -> Result<()> {
+        self.update_info(name, |info| info.tags = tags)
+    }
+    
+    fn set_examples(&self, name: &str, examples: Vec<HashMap<String, String>>) -> Result<()> {
+        self.update_info(name, |info| info.examples = examples)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -345,6 +1900,7 @@ mod tests {
         let info = storage.get_prompt_info(prompt_name).unwrap();
         assert_eq!(info.name, prompt_name);
         assert_eq!(info.size, prompt_content.len() as u64);
+        assert_eq!(info.content_hash, Some(content_hash(prompt_content)));
         
         // Delete prompt
         storage.delete_prompt(prompt_name).unwrap();
@@ -361,4 +1917,722 @@ mod tests {
         assert!(result.chars().all(|c| c == '_'));
         assert_eq!(result.len(), "спеціальні символи".chars().count());
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_executable_dir_storage() {
+        let temp_dir = TempDir::new().unwrap();
+        let exe = temp_dir.path().join("bundle").join("app");
+        
+        let prompts_dir = StorageBase::executable_prompts_dir(&exe).unwrap();
+        assert_eq!(prompts_dir, temp_dir.path().join("bundle").join("prompts"));
+        
+        let storage = FileStorage::with_directory(&prompts_dir).unwrap();
+        storage.save_prompt("portable", "Stored next to the binary").unwrap();
+        assert!(temp_dir.path().join("bundle").join("prompts").join("portable.txt").exists());
+        
+        let resolved = StorageBase::ExecutableDir.resolve().unwrap();
+        assert_eq!(resolved.file_name().unwrap(), "prompts");
+    }
+    
+    #[test]
+    fn test_custom_storage_base() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = StorageBase::Custom(temp_dir.path().join("custom"));
+        
+        let storage = FileStorage::with_base(&base).unwrap();
+        storage.save_prompt("custom", "Custom location").unwrap();
+        assert!(temp_dir.path().join("custom").join("custom.txt").exists());
+    }
+    
+    #[test]
+    fn test_create_parents_modes() {
+        let temp_dir = TempDir::new().unwrap();
+        let nested = temp_dir.path().join("missing").join("deep").join("prompts");
+        
+        let strict = FileStorageOptions { create_parents: false, ..FileStorageOptions::default() };
+        let result = FileStorage::with_options(&nested, strict.clone());
+        assert!(matches!(result, Err(PromptError::Config(_))));
+        assert!(!temp_dir.path().join("missing").exists());
+        
+        // Only the final component is created in strict mode
+        let shallow = temp_dir.path().join("prompts");
+        FileStorage::with_options(&shallow, strict).unwrap();
+        assert!(shallow.is_dir());
+        
+        FileStorage::with_options(&nested, FileStorageOptions::default()).unwrap();
+        assert!(nested.is_dir());
+    }
+    
+    #[test]
+    fn test_swap_prompts() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = FileStorage::with_directory(temp_dir.path()).unwrap();
+        storage.save_prompt("active", "Current version").unwrap();
+        storage.save_prompt("staging", "Next version, longer").unwrap();
+        
+        storage.swap_prompts("active", "staging").unwrap();
+        assert_eq!(storage.load_prompt("active").unwrap(), "Next version, longer");
+        assert_eq!(storage.load_prompt("staging").unwrap(), "Current version");
+        
+        let info = storage.get_prompt_info("active").unwrap();
+        assert_eq!(info.size, "Next version, longer".len() as u64);
+        assert_eq!(info.content_hash, Some(content_hash("Next version, longer")));
+        assert!(!temp_dir.path().join("active.txt.tmp").exists());
+        
+        assert!(matches!(
+            storage.swap_prompts("active", "missing"),
+            Err(PromptError::PromptNotFound(name)) if name == "missing"
+        ));
+    }
+    
+    #[test]
+    fn test_history_prune_and_size() {
+        let temp_dir = TempDir::new().unwrap();
+        let options = FileStorageOptions { keep_history: true, ..FileStorageOptions::default() };
+        let storage = FileStorage::with_options(temp_dir.path(), options).unwrap();
+        
+        for i in 0..6 {
+            storage.save_prompt("evolving", &format!("Version {}", i)).unwrap();
+        }
+        assert_eq!(storage.history_versions("evolving").unwrap().len(), 5);
+        assert!(storage.history_size("evolving").unwrap() > 0);
+        
+        let before = storage.history_versions("evolving").unwrap();
+        assert_eq!(storage.prune_history("evolving", 3).unwrap(), 2);
+        let after = storage.history_versions("evolving").unwrap();
+        assert_eq!(after, before[2..]);
+        assert!(before[..2].iter().all(|path| !path.exists()));
+        
+        // Newest archived version is the one before the current content
+        let compressed = fs::read(after.last().unwrap()).unwrap();
+        let mut decoded = String::new();
+        std::io::Read::read_to_string(&mut flate2::read::GzDecoder::new(&compressed[..]), &mut decoded).unwrap();
+        assert_eq!(decoded, "Version 4");
+        assert_eq!(storage.load_prompt("evolving").unwrap(), "Version 5");
+    }
+    
+    #[test]
+    fn test_keep_history_and_versions_together() {
+        let temp_dir = TempDir::new().unwrap();
+        let options = FileStorageOptions { keep_history: true, keep_versions: 2, ..FileStorageOptions::default() };
+        let storage = FileStorage::with_options(temp_dir.path(), options).unwrap();
+        for version in 1..=5 {
+            storage.save_prompt("both", &format!("Version {}", version)).unwrap();
+        }
+        
+        let count = |dir: &str| fs::read_dir(temp_dir.path().join(dir).join("both")).unwrap().count();
+        assert_eq!(count("history"), 4);
+        assert_eq!(count("versions"), 2);
+        
+        let versions = storage.list_versions("both").unwrap();
+        assert_eq!(versions.len(), 4);
+        assert_eq!(storage.load_version("both", versions[0]).unwrap(), "Version 1");
+        assert_eq!(storage.load_version("both", versions[3]).unwrap(), "Version 4");
+    }
+    
+    #[test]
+    fn test_keep_versions_limits_history() {
+        let temp_dir = TempDir::new().unwrap();
+        let options = FileStorageOptions { keep_versions: 2, ..FileStorageOptions::default() };
+        let storage = FileStorage::with_options(temp_dir.path(), options).unwrap();
+        
+        for i in 0..5 {
+            storage.save_prompt("evolving", &format!("Version {}", i)).unwrap();
+        }
+        let versions = storage.list_versions("evolving").unwrap();
+        assert_eq!(versions.len(), 2);
+        let copy = temp_dir.path().join("versions").join("evolving").join(format!("{}.txt", versions[1]));
+        assert_eq!(fs::read_to_string(copy).unwrap(), "Version 3");
+        assert!(!temp_dir.path().join("history").exists());
+        assert_eq!(storage.load_version("evolving", versions[0]).unwrap(), "Version 2");
+        assert_eq!(storage.load_version("evolving", versions[1]).unwrap(), "Version 3");
+        assert!(matches!(storage.load_version("evolving", 1), Err(PromptError::PromptNotFound(_))));
+    }
+    
+    #[test]
+    fn test_history_disabled_by_default() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = FileStorage::with_directory(temp_dir.path()).unwrap();
+        storage.save_prompt("plain", "First").unwrap();
+        storage.save_prompt("plain", "Second").unwrap();
+        
+        assert!(!temp_dir.path().join("history").exists());
+        assert!(!temp_dir.path().join("versions").exists());
+        assert_eq!(storage.history_size("plain").unwrap(), 0);
+        assert!(storage.list_versions("plain").unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_name_sanitizer_strategies() {
+        let name = "спеціальні символи";
+        
+        let preserved = NameSanitizer::UnicodePreserving.sanitize(name);
+        assert_eq!(preserved, "спеціальні_символи");
+        assert_ne!(preserved, NameSanitizer::UnicodePreserving.sanitize("інші символи"));
+        assert_eq!(NameSanitizer::UnicodePreserving.sanitize("a/b\\c\u{0}"), "a_b_c_");
+        
+        // Decomposed and composed forms map to the same file
+        assert_eq!(
+            NameSanitizer::UnicodePreserving.sanitize("cafe\u{301}"),
+            NameSanitizer::UnicodePreserving.sanitize("caf\u{e9}")
+        );
+        
+        let transliterated = NameSanitizer::Transliterate.sanitize(name);
+        assert!(transliterated.starts_with("spetsial"));
+        assert!(transliterated.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'));
+    }
+    
+    #[test]
+    fn test_unicode_names_stored_distinctly() {
+        let temp_dir = TempDir::new().unwrap();
+        let options = FileStorageOptions {
+            name_sanitizer: NameSanitizer::UnicodePreserving,
+            ..FileStorageOptions::default()
+        };
+        let storage = FileStorage::with_options(temp_dir.path(), options).unwrap();
+        storage.save_prompt("спеціальні символи", "Перший").unwrap();
+        storage.save_prompt("інші символи", "Другий").unwrap();
+        
+        assert_eq!(storage.load_prompt("спеціальні символи").unwrap(), "Перший");
+        assert_eq!(storage.load_prompt("інші символи").unwrap(), "Другий");
+        assert!(temp_dir.path().join("спеціальні_символи.txt").exists());
+    }
+    
+    #[test]
+    fn test_lazy_init_defers_default() {
+        let temp_dir = TempDir::new().unwrap();
+        let options = FileStorageOptions { lazy_init: true, ..FileStorageOptions::default() };
+        let storage = FileStorage::with_options(temp_dir.path(), options).unwrap();
+        let default_file = temp_dir.path().join("default.txt");
+        assert!(!default_file.exists());
+        
+        let prompt = storage.load_default().unwrap();
+        assert_eq!(prompt, crate::PromptManager::factory_default_prompt());
+        assert!(default_file.exists());
+        
+        // save_default also materializes it
+        let temp_dir = TempDir::new().unwrap();
+        let options = FileStorageOptions { lazy_init: true, ..FileStorageOptions::default() };
+        let storage = FileStorage::with_options(temp_dir.path(), options).unwrap();
+        storage.save_default("Custom").unwrap();
+        assert_eq!(fs::read_to_string(temp_dir.path().join("default.txt")).unwrap(), "Custom");
+    }
+    
+    #[test]
+    fn test_reconcile_skips_binary_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = FileStorage::with_directory(temp_dir.path()).unwrap();
+        storage.save_prompt("tracked", "Already known").unwrap();
+        fs::write(temp_dir.path().join("dropped.txt"), "Copied in by hand\n").unwrap();
+        fs::write(temp_dir.path().join("logo.png"), [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, 0, 0]).unwrap();
+        fs::write(temp_dir.path().join("latin1.txt"), [b'c', b'a', b'f', 0xe9]).unwrap();
+        
+        let report = storage.reconcile().unwrap();
+        assert_eq!(report.added, vec!["dropped"]);
+        assert_eq!(report.skipped, vec![temp_dir.path().join("latin1.txt"), temp_dir.path().join("logo.png")]);
+        
+        assert_eq!(storage.list_prompts().unwrap(), vec!["dropped", "tracked"]);
+        assert_eq!(storage.load_prompt("dropped").unwrap(), "Copied in by hand");
+        assert!(storage.reconcile().unwrap().added.is_empty());
+    }
+    
+    #[test]
+    fn test_looks_binary() {
+        assert!(!looks_binary("Plain prompt\n\twith tabs".as_bytes()));
+        assert!(!looks_binary("Юнікод".as_bytes()));
+        assert!(looks_binary(b"nul\0byte"));
+        assert!(looks_binary(&[0x01, 0x02, 0x03, b'a']));
+    }
+    
+    #[test]
+    fn test_configured_extension() {
+        let temp_dir = TempDir::new().unwrap();
+        let options = FileStorageOptions { extension: "md".to_string(), ..FileStorageOptions::default() };
+        let storage = FileStorage::with_options(temp_dir.path(), options).unwrap();
+        assert!(temp_dir.path().join("default.md").exists());
+        assert!(!temp_dir.path().join("default.txt").exists());
+        
+        storage.save_prompt("notes", "# Heading").unwrap();
+        assert!(temp_dir.path().join("notes.md").exists());
+        assert_eq!(storage.load_prompt("notes").unwrap(), "# Heading");
+        assert_eq!(storage.list_prompts().unwrap(), vec!["notes"]);
+        assert!(storage.prompt_exists("notes"));
+        
+        storage.delete_prompt("notes").unwrap();
+        assert!(!temp_dir.path().join("notes.md").exists());
+        assert!(storage.list_prompts().unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_legacy_txt_files_still_load() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = FileStorage::with_directory(temp_dir.path()).unwrap();
+        storage.save_default("Legacy default").unwrap();
+        storage.save_prompt("old", "Saved as txt").unwrap();
+        
+        let options = FileStorageOptions { extension: ".md".to_string(), ..FileStorageOptions::default() };
+        let storage = FileStorage::with_options(temp_dir.path(), options).unwrap();
+        assert!(!temp_dir.path().join("default.md").exists());
+        assert_eq!(storage.load_default().unwrap(), "Legacy default");
+        assert_eq!(storage.load_prompt("old").unwrap(), "Saved as txt");
+        
+        // Saving again migrates the file to the configured extension
+        storage.save_prompt("old", "Saved as md").unwrap();
+        assert!(temp_dir.path().join("old.md").exists());
+        assert!(!temp_dir.path().join("old.txt").exists());
+        assert_eq!(storage.load_prompt("old").unwrap(), "Saved as md");
+        
+        fs::write(temp_dir.path().join("dropped.md"), "Hand written").unwrap();
+        assert_eq!(storage.reconcile().unwrap().added, vec!["dropped"]);
+    }
+    
+    #[test]
+    fn test_rename_prompt() {
+        let temp_dir = TempDir::new().unwrap();
+        let options = FileStorageOptions { keep_history: true, ..FileStorageOptions::default() };
+        let storage = FileStorage::with_options(temp_dir.path(), options).unwrap();
+        storage.save_prompt("tpyo", "First").unwrap();
+        storage.save_prompt("tpyo", "Second").unwrap();
+        storage.set_tags("tpyo", vec!["draft".to_string()]).unwrap();
+        let created = storage.get_prompt_info("tpyo").unwrap().created_at;
+        
+        storage.rename_prompt("tpyo", "typo").unwrap();
+        assert_eq!(storage.list_prompts().unwrap(), vec!["typo"]);
+        assert_eq!(storage.load_prompt("typo").unwrap(), "Second");
+        assert!(!temp_dir.path().join("tpyo.txt").exists());
+        let info = storage.get_prompt_info("typo").unwrap();
+        assert_eq!(info.created_at, created);
+        assert_eq!(info.tags, vec!["draft"]);
+        assert_eq!(storage.history_versions("typo").unwrap().len(), 1);
+        
+        assert!(matches!(storage.rename_prompt("tpyo", "other"), Err(PromptError::PromptNotFound(_))));
+    }
+    
+    #[test]
+    fn test_rename_prompt_conflicts() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = FileStorage::with_directory(temp_dir.path()).unwrap();
+        storage.save_prompt("a_b", "Underscore").unwrap();
+        storage.save_prompt("c", "Plain").unwrap();
+        
+        assert!(matches!(storage.rename_prompt("c", "a_b"), Err(PromptError::PromptAlreadyExists(_))));
+        assert!(matches!(storage.rename_prompt("c", "a/b"), Err(PromptError::PromptAlreadyExists(_))));
+        assert_eq!(storage.load_prompt("c").unwrap(), "Plain");
+        assert_eq!(storage.load_prompt("a_b").unwrap(), "Underscore");
+    }
+    
+    #[test]
+    fn test_copy_prompt() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = FileStorage::with_directory(temp_dir.path()).unwrap();
+        storage.save_prompt("coding", "Write good code").unwrap();
+        storage.set_tags("coding", vec!["dev".to_string()]).unwrap();
+        storage.save_prompt("other", "Something else").unwrap();
+        let source = storage.get_prompt_info("coding").unwrap();
+        
+        storage.copy_prompt("coding", "coding-v2", false).unwrap();
+        assert_eq!(storage.load_prompt("coding-v2").unwrap(), "Write good code");
+        let copy = storage.get_prompt_info("coding-v2").unwrap();
+        assert_eq!(copy.size, source.size);
+        assert_eq!(copy.content_hash, source.content_hash);
+        assert!(copy.created_at >= source.created_at);
+        assert!(copy.tags.is_empty());
+        
+        assert!(matches!(storage.copy_prompt("missing", "x", false), Err(PromptError::PromptNotFound(_))));
+        assert!(matches!(storage.copy_prompt("coding", "other", false), Err(PromptError::PromptAlreadyExists(_))));
+        assert_eq!(storage.load_prompt("other").unwrap(), "Something else");
+        
+        storage.copy_prompt("coding", "other", true).unwrap();
+        assert_eq!(storage.load_prompt("other").unwrap(), "Write good code");
+        assert_eq!(storage.load_prompt("coding").unwrap(), "Write good code");
+    }
+    
+    #[test]
+    fn test_memory_storage_basic_operations() {
+        let storage = MemoryStorage::new();
+        
+        assert!(!storage.load_default().unwrap().is_empty());
+        storage.save_default("Custom default prompt").unwrap();
+        assert_eq!(storage.load_default().unwrap(), "Custom default prompt");
+        assert!(storage.list_prompts().unwrap().is_empty());
+        
+        storage.save_prompt("test_prompt", "This is a test prompt").unwrap();
+        assert_eq!(storage.load_prompt("test_prompt").unwrap(), "This is a test prompt");
+        assert!(storage.prompt_exists("test_prompt"));
+        assert!(!storage.prompt_exists("nonexistent"));
+        assert_eq!(storage.list_prompts().unwrap(), vec!["test_prompt"]);
+        
+        let info = storage.get_prompt_info("test_prompt").unwrap();
+        assert_eq!(info.size, "This is a test prompt".len() as u64);
+        assert_eq!(info.content_hash, Some(content_hash("This is a test prompt")));
+        
+        storage.set_tags("test_prompt", vec!["t".to_string()]).unwrap();
+        storage.save_prompt("test_prompt", "Updated").unwrap();
+        let updated = storage.get_prompt_info("test_prompt").unwrap();
+        assert_eq!(updated.created_at, info.created_at);
+        assert_eq!(updated.tags, vec!["t"]);
+        
+        storage.rename_prompt("test_prompt", "renamed").unwrap();
+        storage.copy_prompt("renamed", "copy", false).unwrap();
+        assert_eq!(storage.list_prompts().unwrap(), vec!["copy", "renamed"]);
+        
+        storage.delete_prompt("renamed").unwrap();
+        assert!(matches!(storage.load_prompt("renamed"), Err(PromptError::PromptNotFound(_))));
+        assert!(matches!(storage.delete_prompt("renamed"), Err(PromptError::PromptNotFound(_))));
+        assert!(matches!(storage.load_prompt(MEMORY_DEFAULT_KEY), Err(PromptError::PromptNotFound(_))));
+    }
+    
+    #[test]
+    fn test_recovers_from_stray_tmp_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = FileStorage::with_directory(temp_dir.path()).unwrap();
+        storage.save_prompt("notes", "Complete content").unwrap();
+        let created = storage.get_prompt_info("notes").unwrap().created_at;
+        
+        // Simulate writes killed before the rename
+        fs::write(temp_dir.path().join("notes.txt.tmp"), "Compl").unwrap();
+        fs::write(temp_dir.path().join("metadata.json.tmp"), "{\"version\": ").unwrap();
+        
+        assert_eq!(storage.load_prompt("notes").unwrap(), "Complete content");
+        assert_eq!(storage.list_prompts().unwrap(), vec!["notes"]);
+        assert!(storage.reconcile().unwrap().added.is_empty());
+        
+        storage.save_prompt("notes", "Rewritten").unwrap();
+        assert_eq!(storage.load_prompt("notes").unwrap(), "Rewritten");
+        assert_eq!(storage.get_prompt_info("notes").unwrap().created_at, created);
+        assert!(!temp_dir.path().join("notes.txt.tmp").exists());
+        assert!(!temp_dir.path().join("metadata.json.tmp").exists());
+    }
+    
+    #[test]
+    fn test_rebuild_after_deleted_or_corrupt_metadata() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = FileStorage::with_directory(temp_dir.path()).unwrap();
+        storage.save_prompt("alpha", "First").unwrap();
+        storage.save_prompt("beta", "Second").unwrap();
+        
+        fs::remove_file(temp_dir.path().join("metadata.json")).unwrap();
+        assert!(storage.list_prompts().unwrap().is_empty());
+        storage.rebuild_metadata().unwrap();
+        assert_eq!(storage.list_prompts().unwrap(), vec!["alpha", "beta"]);
+        
+        fs::write(temp_dir.path().join("metadata.json"), "{\"version\": \"1.0\", \"prom").unwrap();
+        assert_eq!(storage.list_prompts().unwrap(), vec!["alpha", "beta"]);
+        let info = storage.get_prompt_info("beta").unwrap();
+        assert_eq!(info.size, 6);
+        assert_eq!(info.content_hash, Some(content_hash("Second")));
+    }
+    
+    #[test]
+    fn test_rebuild_metadata_adds_missing_prompts() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = FileStorage::with_directory(temp_dir.path()).unwrap();
+        storage.save_prompt("tracked", "Known").unwrap();
+        storage.set_tags("tracked", vec!["keep".to_string()]).unwrap();
+        fs::write(temp_dir.path().join("synced.txt"), "Arrived via sync").unwrap();
+        assert_eq!(storage.list_prompts().unwrap(), vec!["tracked"]);
+        
+        storage.rebuild_metadata().unwrap();
+        assert_eq!(storage.list_prompts().unwrap(), vec!["synced", "tracked"]);
+        assert_eq!(storage.load_prompt("synced").unwrap(), "Arrived via sync");
+        assert_eq!(storage.get_prompt_info("tracked").unwrap().tags, vec!["keep"]);
+    }
+    
+    #[test]
+    fn test_refresh_prompt_info_after_external_edit() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = FileStorage::with_directory(temp_dir.path()).unwrap();
+        storage.save_prompt("notes", "short").unwrap();
+        
+        let path = storage.get_prompt_info("notes").unwrap().file_path;
+        fs::write(&path, "a considerably longer body").unwrap();
+        
+        assert_eq!(storage.get_prompt_info("notes").unwrap().size, 5);
+        let refreshed = storage.refresh_prompt_info("notes").unwrap();
+        assert_eq!(refreshed.size, 26);
+        assert_eq!(refreshed.content_hash, Some(content_hash("a considerably longer body")));
+        // The refresh is written back to metadata
+        assert_eq!(storage.get_prompt_info("notes").unwrap().size, 26);
+    }
+    
+    #[cfg(feature = "notify")]
+    #[test]
+    fn test_watch_reports_external_changes() {
+        use crate::events::ChangeKind;
+        use std::time::Duration;
+        
+        let temp_dir = TempDir::new().unwrap();
+        let storage = FileStorage::with_directory(temp_dir.path()).unwrap();
+        let events = storage.watch().unwrap();
+        let next = || events.recv_timeout(Duration::from_secs(5)).unwrap();
+        let path = temp_dir.path().join("edited.txt");
+        
+        fs::write(&path, "first").unwrap();
+        let event = next();
+        assert_eq!((event.name.as_str(), event.kind), ("edited", ChangeKind::Created));
+        
+        // A burst of writes is reported once
+        for body in ["second", "third", "fourth"] {
+            fs::write(&path, body).unwrap();
+        }
+        assert_eq!(next().kind, ChangeKind::Modified);
+        assert!(events.recv_timeout(Duration::from_millis(500)).is_err());
+        
+        fs::write(temp_dir.path().join("default.txt"), "New default").unwrap();
+        assert_eq!(next().kind, ChangeKind::DefaultChanged);
+        
+        fs::remove_file(&path).unwrap();
+        let event = next();
+        assert_eq!((event.name.as_str(), event.kind), ("edited", ChangeKind::Deleted));
+    }
+    
+    #[cfg(feature = "notify")]
+    #[test]
+    fn test_watch_reports_namespaced_changes() {
+        use crate::events::ChangeKind;
+        use std::time::Duration;
+        
+        let temp_dir = TempDir::new().unwrap();
+        let options = FileStorageOptions { namespace_separator: Some('/'), ..FileStorageOptions::default() };
+        let storage = FileStorage::with_options(temp_dir.path(), options).unwrap();
+        storage.save_prompt("alice/coding", "Original").unwrap();
+        let events = storage.watch().unwrap();
+        let next = || events.recv_timeout(Duration::from_secs(5)).unwrap();
+        
+        fs::write(temp_dir.path().join("alice").join("coding.txt"), "Edited by hand").unwrap();
+        let event = next();
+        assert_eq!((event.name.as_str(), event.kind), ("alice/coding", ChangeKind::Modified));
+        
+        fs::create_dir(temp_dir.path().join("bob")).unwrap();
+        fs::write(temp_dir.path().join("bob").join("notes.txt"), "New").unwrap();
+        let event = next();
+        assert_eq!((event.name.as_str(), event.kind), ("bob/notes", ChangeKind::Created));
+    }
+    
+    #[test]
+    fn test_clean_temp_files_in_namespaces() {
+        let temp_dir = TempDir::new().unwrap();
+        let options = FileStorageOptions { namespace_separator: Some('/'), ..FileStorageOptions::default() };
+        let storage = FileStorage::with_options(temp_dir.path(), options).unwrap();
+        storage.save_prompt("alice/coding", "Keep me").unwrap();
+        let leftover = temp_dir.path().join("alice").join("coding.txt.tmp");
+        fs::write(&leftover, "partial write").unwrap();
+        
+        assert_eq!(storage.clean_temp_files().unwrap(), vec![leftover.clone()]);
+        assert!(!leftover.exists());
+        assert_eq!(storage.load_prompt("alice/coding").unwrap(), "Keep me");
+    }
+    
+    #[test]
+    fn test_verify_reports_discrepancies() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = FileStorage::with_directory(temp_dir.path()).unwrap();
+        storage.save_prompt("intact", "Untouched").unwrap();
+        storage.save_prompt("tampered", "Original").unwrap();
+        storage.save_prompt("removed", "Gone soon").unwrap();
+        assert!(storage.verify().unwrap().is_empty());
+        
+        fs::write(temp_dir.path().join("tampered.txt"), "Edited in an editor").unwrap();
+        fs::remove_file(temp_dir.path().join("removed.txt")).unwrap();
+        fs::write(temp_dir.path().join("stray.txt"), "Dropped in by hand").unwrap();
+        
+        let discrepancies = storage.verify().unwrap();
+        let kinds: Vec<(&str, &DiscrepancyKind)> = discrepancies.iter()
+            .map(|d| (d.name.as_str(), &d.kind))
+            .collect();
+        assert_eq!(kinds, vec![
+            ("removed", &DiscrepancyKind::MissingFile),
+            ("stray", &DiscrepancyKind::UntrackedFile),
+            ("tampered", &DiscrepancyKind::SizeMismatch { recorded: 8, actual: 19 }),
+        ]);
+    }
+    
+    #[test]
+    fn test_namespaced_prompts() {
+        let temp_dir = TempDir::new().unwrap();
+        let options = FileStorageOptions { namespace_separator: Some('/'), ..FileStorageOptions::default() };
+        let storage = FileStorage::with_options(temp_dir.path(), options).unwrap();
+        
+        storage.save_prompt("alice/coding", "Alice's coding prompt").unwrap();
+        storage.save_prompt("alice_coding", "Flat prompt").unwrap();
+        storage.save_prompt("bob/coding", "Bob's coding prompt").unwrap();
+        assert!(temp_dir.path().join("alice").join("coding.txt").is_file());
+        assert!(temp_dir.path().join("alice_coding.txt").is_file());
+        
+        assert_eq!(storage.load_prompt("alice/coding").unwrap(), "Alice's coding prompt");
+        assert_eq!(storage.load_prompt("alice_coding").unwrap(), "Flat prompt");
+        assert_eq!(storage.list_prompts().unwrap(), vec!["alice/coding", "alice_coding", "bob/coding"]);
+        
+        // Rebuilt metadata recovers namespaced names from the directory layout
+        fs::write(temp_dir.path().join("metadata.json"), "{\"version\": ").unwrap();
+        assert_eq!(storage.list_prompts().unwrap(), vec!["alice/coding", "alice_coding", "bob/coding"]);
+        assert!(storage.verify().unwrap().is_empty());
+        
+        for name in ["../evil", "alice/../evil", "alice//coding", "history/coding", "versions/coding"] {
+            assert!(matches!(storage.save_prompt(name, "Nope"), Err(PromptError::InvalidName(_))), "{}", name);
+        }
+    }
+    
+    #[test]
+    fn test_path_traversal_names_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let prompts_dir = temp_dir.path().join("prompts");
+        let storage = FileStorage::with_directory(&prompts_dir).unwrap();
+        fs::write(temp_dir.path().join("secret.txt"), "Outside the prompts directory").unwrap();
+        
+        assert!(matches!(storage.save_prompt("../evil", "Escaped"), Err(PromptError::InvalidName(_))));
+        assert!(!temp_dir.path().join("evil.txt").exists());
+        assert!(matches!(storage.load_prompt(".."), Err(PromptError::InvalidName(_))));
+        assert!(matches!(storage.load_prompt("../secret"), Err(PromptError::InvalidName(_))));
+        for name in ["", ".", "a/..", "/etc/passwd"] {
+            assert!(matches!(storage.save_prompt(name, "Nope"), Err(PromptError::InvalidName(_))), "{:?}", name);
+        }
+        assert!(!storage.prompt_exists("../secret"));
+        assert!(storage.list_prompts().unwrap().is_empty());
+        
+        // Names that stay inside the directory are still sanitized as before
+        storage.save_prompt("notes/../draft", "Kept inside").unwrap();
+        assert_eq!(storage.load_prompt("notes/../draft").unwrap(), "Kept inside");
+    }
+    
+    #[test]
+    fn test_metadata_migrates_from_1_0() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = FileStorage::with_directory(temp_dir.path()).unwrap();
+        fs::write(temp_dir.path().join("legacy.txt"), "Written by 1.0").unwrap();
+        fs::write(temp_dir.path().join("metadata.json"), r#"{
+            "version": "1.0",
+            "prompts": {
+                "legacy": {
+                    "name": "legacy",
+                    "file_name": "legacy.txt",
+                    "created_at": {"secs_since_epoch": 1700000000, "nanos_since_epoch": 0},
+                    "modified_at": {"secs_since_epoch": 1700000000, "nanos_since_epoch": 0},
+                    "size": 14
+                }
+            }
+        }"#).unwrap();
+        
+        assert_eq!(storage.list_prompts().unwrap(), vec!["legacy"]);
+        let info = storage.get_prompt_info("legacy").unwrap();
+        assert_eq!(info.size, 14);
+        assert!(info.tags.is_empty());
+        
+        storage.set_tags("legacy", vec!["old".to_string()]).unwrap();
+        let saved: serde_json::Value = serde_json::from_str(&fs::read_to_string(temp_dir.path().join("metadata.json")).unwrap()).unwrap();
+        assert_eq!(saved["version"], METADATA_VERSION);
+        assert_eq!(saved["prompts"]["legacy"]["tags"][0], "old");
+        assert_eq!(storage.load_prompt("legacy").unwrap(), "Written by 1.0");
+        
+        fs::write(temp_dir.path().join("metadata.json"), r#"{"version": "9.0", "prompts": {}}"#).unwrap();
+        assert!(matches!(storage.list_prompts(), Err(PromptError::Storage(ref msg)) if msg.contains("9.0")));
+    }
+    
+    /// Backend implementing only the required `PromptStorage` methods
+    struct MinimalStorage(MemoryStorage);
+    
+    impl PromptStorage for MinimalStorage {
+        fn load_default(&self) -> Result<String> {
+            self.0.load_default()
+        }
+        
+        fn save_default(&self, prompt: &str) -> Result<()> {
+            self.0.save_default(prompt)
+        }
+        
+        fn load_prompt(&self, name: &str) -> Result<String> {
+            self.0.load_prompt(name)
+        }
+        
+        fn save_prompt(&self, name: &str, prompt: &str) -> Result<()> {
+            self.0.save_prompt(name, prompt)
+        }
+        
+        fn list_prompts(&self) -> Result<Vec<String>> {
+            self.0.list_prompts()
+        }
+        
+        fn delete_prompt(&self, name: &str) -> Result<()> {
+            self.0.delete_prompt(name)
+        }
+        
+        fn prompt_exists(&self, name: &str) -> bool {
+            self.0.prompt_exists(name)
+        }
+        
+        fn get_prompt_info(&self, name: &str) -> Result<PromptInfo> {
+            self.0.get_prompt_info(name)
+        }
+    }
+    
+    #[test]
+    fn test_default_trait_methods() {
+        let storage = MinimalStorage(MemoryStorage::new());
+        storage.save_prompt("a", "First").unwrap();
+        storage.save_prompt("b", "Second").unwrap();
+        
+        storage.swap_prompts("a", "b").unwrap();
+        assert_eq!(storage.load_prompt("a").unwrap(), "Second");
+        assert_eq!(storage.load_prompt("b").unwrap(), "First");
+        assert!(matches!(storage.swap_prompts("a", "missing"), Err(PromptError::PromptNotFound(_))));
+        
+        storage.rename_prompt("a", "renamed").unwrap();
+        assert_eq!(storage.load_prompt("renamed").unwrap(), "Second");
+        assert!(!storage.prompt_exists("a"));
+        assert!(matches!(storage.rename_prompt("renamed", "b"), Err(PromptError::PromptAlreadyExists(_))));
+        storage.rename_prompt("renamed", "a").unwrap();
+        
+        storage.copy_prompt("a", "c", false).unwrap();
+        assert_eq!(storage.load_prompt("c").unwrap(), "Second");
+        assert!(matches!(storage.copy_prompt("b", "c", false), Err(PromptError::PromptAlreadyExists(_))));
+        storage.copy_prompt("b", "c", true).unwrap();
+        assert_eq!(storage.load_prompt("c").unwrap(), "First");
+        
+        let err = storage.set_content_type("a", "text/markdown").unwrap_err();
+        assert!(matches!(err, PromptError::Storage(ref msg) if msg.contains("not supported")));
+        let err = storage.set_tags("a", vec!["draft".to_string()]).unwrap_err();
+        assert!(matches!(err, PromptError::Storage(ref msg) if msg.contains("not supported")));
+        let err = storage.set_examples("a", vec![HashMap::new()]).unwrap_err();
+        assert!(matches!(err, PromptError::Storage(ref msg) if msg.contains("not supported")));
+    }
+    
+    #[test]
+    fn test_reads_leave_corrupt_metadata_alone() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = FileStorage::with_directory(temp_dir.path()).unwrap();
+        for name in ["one", "two", "three"] {
+            storage.save_prompt(name, "Content").unwrap();
+        }
+        
+        let metadata_path = temp_dir.path().join("metadata.json");
+        fs::write(&metadata_path, "{ not json").unwrap();
+        assert_eq!(storage.load_prompt("one").unwrap(), "Content");
+        assert!(storage.prompt_exists("two"));
+        assert_eq!(fs::read_to_string(&metadata_path).unwrap(), "{ not json");
+        
+        // Writes rebuild it once and check every name against it
+        let results = storage.delete_prompts(&["one".to_string(), "two".to_string()]).unwrap();
+        assert!(results.iter().all(|result| result.is_ok()));
+        assert_eq!(storage.list_prompts().unwrap(), vec!["three"]);
+    }
+    
+    #[test]
+    fn test_memory_swap_races_delete_without_panic() {
+        let storage = std::sync::Arc::new(MemoryStorage::new());
+        for _ in 0..200 {
+            storage.save_prompt("a", "First").unwrap();
+            storage.save_prompt("b", "Second").unwrap();
+            let deleter = {
+                let storage = storage.clone();
+                std::thread::spawn(move || storage.delete_prompt("b"))
+            };
+            match storage.swap_prompts("a", "b") {
+                Ok(()) | Err(PromptError::PromptNotFound(_)) => {}
+                Err(e) => panic!("unexpected error: {}", e),
+            }
+            deleter.join().unwrap().unwrap();
+        }
+        assert!(matches!(storage.swap_prompts("a", "b"), Err(PromptError::PromptNotFound(_))));
+        assert!(storage.prompt_exists("a"));
+    }
+}
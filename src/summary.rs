@@ -0,0 +1,20 @@
+//! Overview of a prompt library
+
+use std::time::SystemTime;
+
+/// Aggregate statistics about a prompt library
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LibrarySummary {
+    /// Number of named prompts
+    pub total_prompts: usize,
+    /// Combined size of all named prompts in bytes
+    pub total_size: u64,
+    /// Name and size of the largest prompt
+    pub largest: Option<(String, u64)>,
+    /// Name and modification time of the most recently modified prompt
+    pub most_recently_modified: Option<(String, SystemTime)>,
+    /// Number of distinct tags, compared case-insensitively
+    pub tag_count: usize,
+    /// Whether the default prompt differs from the factory default
+    pub default_customized: bool,
+}
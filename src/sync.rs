@@ -0,0 +1,41 @@
+//! Planning synchronization between prompt libraries
+
+/// Differences between two prompt libraries
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncPlan {
+    /// Prompts present only in this library (candidates to push)
+    pub only_in_self: Vec<String>,
+    /// Prompts present only in the other library (candidates to pull)
+    pub only_in_other: Vec<String>,
+    /// Prompts present in both with different content
+    pub differing: Vec<String>,
+}
+
+impl SyncPlan {
+    /// Check if the libraries are already in sync
+    pub fn is_empty(&self) -> bool {
+        self.only_in_self.is_empty() && self.only_in_other.is_empty() && self.differing.is_empty()
+    }
+}
+
+/// Changes made by syncing a library to a bundle
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    /// Prompts created from the bundle
+    pub added: Vec<String>,
+    /// Prompts whose content or tags were replaced
+    pub updated: Vec<String>,
+    /// Prompts removed because the bundle doesn't contain them
+    pub deleted: Vec<String>,
+    /// Prompts that already matched the bundle
+    pub unchanged: Vec<String>,
+    /// Whether the default prompt was replaced
+    pub default_updated: bool,
+}
+
+impl SyncReport {
+    /// Check if the library already matched the bundle
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.deleted.is_empty() && !self.default_updated
+    }
+}
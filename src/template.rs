@@ -1,12 +1,79 @@
 use crate::error::{PromptError, Result};
-use handlebars::{Handlebars, Helper, HelperResult, Output, RenderContext};
+use handlebars::template::{BlockParam, HelperTemplate, Parameter, TemplateElement};
+use handlebars::{Handlebars, Helper, HelperDef, HelperResult, Output, Path, RenderContext, Renderable};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use tracing::debug;
 
+/// Marker appended to template lines containing block tags
+const BLOCK_LINE_MARKER: char = '\u{E000}';
+
+/// How much of a template to keep when rendering
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RenderMode {
+    /// Render everything as written
+    #[default]
+    Normal,
+    /// Drop `{{#optional}}` sections and HTML comments, and collapse whitespace
+    Compact,
+}
+
+/// Options controlling post-processing of rendered output
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    /// Remove lines left empty because a block on them rendered nothing
+    pub collapse_empty_blocks: bool,
+    /// Normal or compact rendering
+    pub mode: RenderMode,
+}
+
+/// Per-call overrides for template rendering
+///
+/// Fields left as `None` use the shared engine's settings.
+#[derive(Debug, Clone, Default)]
+pub struct RenderConfig {
+    /// Override strict mode (error on missing variables)
+    pub strict_mode: Option<bool>,
+    /// Custom open/close delimiters used instead of `{{`/`}}`
+    pub delimiters: Option<(String, String)>,
+    /// Text substituted for variables the caller didn't provide
+    ///
+    /// Applied before rendering, so strict mode won't report these
+    /// variables as missing.
+    pub missing_placeholder: Option<String>,
+}
+
+impl RenderConfig {
+    /// Check if any override is set
+    pub fn has_overrides(&self) -> bool {
+        self.strict_mode.is_some() || self.delimiters.is_some() || self.missing_placeholder.is_some()
+    }
+    
+    /// Translate custom delimiters into handlebars syntax
+    ///
+    /// Literal `{{` in the source is escaped so only the custom delimiters
+    /// are interpreted.
+    pub fn normalize_template(&self, template: &str) -> String {
+        match &self.delimiters {
+            Some((open, close)) => template
+                .replace("{{", "\\{{")
+                .replace(open.as_str(), "{{")
+                .replace(close.as_str(), "}}"),
+            None => template.to_string(),
+        }
+    }
+}
+
+/// Helpers treated as non-deterministic by default
+pub const DEFAULT_NONDETERMINISTIC_HELPERS: &[&str] = &["now", "env", "random", "uuid", "timestamp"];
+
 /// Template engine for prompt variable substitution
 pub struct TemplateEngine {
     handlebars: Handlebars<'static>,
+    nondeterministic_helpers: HashSet<String>,
+    custom_helpers: BTreeSet<String>,
+    glossary: HashMap<String, String>,
+    registered_defaults: HashMap<String, HashMap<String, String>>,
 }
 
 impl TemplateEngine {
@@ -14,34 +81,362 @@ impl TemplateEngine {
     pub fn new() -> Self {
         let mut handlebars = Handlebars::new();
         
-        // Register custom helpers
-        handlebars.register_helper("upper", Box::new(upper_helper));
-        handlebars.register_helper("lower", Box::new(lower_helper));
-        handlebars.register_helper("capitalize", Box::new(capitalize_helper));
-        handlebars.register_helper("default", Box::new(default_helper));
-        
         // Configure handlebars
         handlebars.set_strict_mode(false); // Allow missing variables
         
-        Self { handlebars }
+        let mut engine = Self {
+            handlebars,
+            nondeterministic_helpers: DEFAULT_NONDETERMINISTIC_HELPERS.iter().map(|h| h.to_string()).collect(),
+            custom_helpers: BTreeSet::new(),
+            glossary: HashMap::new(),
+            registered_defaults: HashMap::new(),
+        };
+        
+        // Register custom helpers
+        engine.register_helper("upper", Box::new(upper_helper));
+        engine.register_helper("lower", Box::new(lower_helper));
+        engine.register_helper("capitalize", Box::new(capitalize_helper));
+        engine.register_helper("default", Box::new(default_helper));
+        engine.register_helper("join", Box::new(join_helper));
+        engine.register_helper("truncate", Box::new(truncate_helper));
+        engine.register_helper("count_matches", Box::new(count_matches_helper));
+        engine.register_helper("replace", Box::new(replace_helper));
+        engine.register_helper("ternary", Box::new(ternary_helper));
+        engine.register_helper("get", Box::new(get_helper));
+        engine.register_helper("block", Box::new(block_helper));
+        engine.register_helper("defaults", Box::new(block_helper));
+        engine.register_helper("optional", Box::new(block_helper));
+        engine.register_glossary(HashMap::new());
+        
+        engine
+    }
+    
+    /// Fail renders that reference undefined variables instead of rendering them empty
+    pub fn set_strict_variables(&mut self, strict: bool) {
+        self.handlebars.set_strict_mode(strict);
+    }
+    
+    /// Add glossary terms for the `define` helper
+    ///
+    /// `{{define "term"}}` renders the term's definition, or the term
+    /// itself if it isn't defined. Terms already in the glossary are
+    /// replaced.
+    pub fn register_glossary(&mut self, terms: HashMap<String, String>) {
+        self.glossary.extend(terms);
+        let glossary = self.glossary.clone();
+        self.register_helper(
+            "define",
+            Box::new(
+                move |h: &Helper, _: &Handlebars, _: &handlebars::Context, _: &mut RenderContext, out: &mut dyn Output| -> HelperResult {
+                    let term = h.param(0)
+                        .and_then(|p| p.value().as_str())
+                        .unwrap_or("");
+                    out.write(glossary.get(term).map_or(term, String::as_str))?;
+                    Ok(())
+                },
+            ),
+        );
+    }
+    
+    /// Register a helper in addition to the handlebars built-ins
+    pub fn register_helper(&mut self, name: &str, helper: Box<dyn HelperDef + Send + Sync + 'static>) {
+        self.handlebars.register_helper(name, helper);
+        self.custom_helpers.insert(name.to_string());
+    }
+    
+    /// Check if a helper beyond the handlebars built-ins is registered
+    pub fn has_helper(&self, name: &str) -> bool {
+        self.custom_helpers.contains(name)
+    }
+    
+    /// Registered helpers beyond the handlebars built-ins a template uses, sorted
+    pub fn helpers_used(&self, template: &str) -> Vec<String> {
+        let mut used = BTreeSet::new();
+        let mut rest = template;
+        
+        while let Some(start) = rest.find("{{") {
+            let Some(end) = rest[start..].find("}}") else {
+                break;
+            };
+            let expression = &rest[start + 2..start + end];
+            used.extend(
+                expression
+                    .split(|c: char| c.is_whitespace() || c == '(' || c == ')')
+                    .map(|token| token.trim_start_matches(['{', '~', '#', '/']))
+                    .filter(|token| self.custom_helpers.contains(*token))
+                    .map(str::to_string),
+            );
+            rest = &rest[start + end + 2..];
+        }
+        
+        used.into_iter().collect()
     }
     
     /// Render a template with variables
+    ///
+    /// Defaults declared inline with `{{#defaults name="value"}}...{{/defaults}}`
+    /// apply to variables the caller didn't provide. The block's tags are
+    /// stripped from the output and its content is rendered as usual.
     pub fn render(&self, template: &str, variables: &HashMap<String, String>) -> Result<String> {
+        Self::render_in(&self.handlebars, template, variables)
+    }
+    
+    /// Render a template with an arbitrary JSON context
+    ///
+    /// Arrays and nested objects pass through to handlebars, so templates
+    /// can iterate with `{{#each items}}` and use dotted paths. Inline
+    /// `{{#defaults}}` are not applied.
+    pub fn render_value(&self, template: &str, context: &Value) -> Result<String> {
+        let rendered = self.handlebars.render_template(template, context)?;
+        debug!("Rendered template with a JSON context");
+        Ok(rendered)
+    }
+    
+    /// Render a template with the given registry
+    fn render_in(handlebars: &Handlebars<'static>, template: &str, variables: &HashMap<String, String>) -> Result<String> {
+        let variables = &with_inline_defaults(template, variables);
+        
         // Convert HashMap to serde_json::Value for handlebars
         let context: Value = variables.iter()
             .map(|(k, v)| (k.clone(), Value::String(v.clone())))
             .collect::<serde_json::Map<String, Value>>()
             .into();
         
-        let rendered = self.handlebars.render_template(template, &context)?;
+        let rendered = handlebars.render_template(template, &context)?;
         debug!("Rendered template with {} variables", variables.len());
         Ok(rendered)
     }
     
+    /// Render a template with variables and post-processing options
+    ///
+    /// In compact mode `{{#optional}}...{{/optional}}` sections are dropped,
+    /// HTML comments are removed, runs of whitespace within a line collapse
+    /// to one space, and blank lines are removed.
+    pub fn render_with_options(
+        &self,
+        template: &str,
+        variables: &HashMap<String, String>,
+        options: &RenderOptions,
+    ) -> Result<String> {
+        match options.mode {
+            RenderMode::Normal => self.render_collapsing(&self.handlebars, template, variables, options),
+            RenderMode::Compact => {
+                let mut handlebars = self.handlebars.clone();
+                handlebars.register_helper("optional", Box::new(omit_helper));
+                let rendered = self.render_collapsing(&handlebars, template, variables, options)?;
+                Ok(compact(&rendered))
+            }
+        }
+    }
+    
+    /// Render with a registry, collapsing empty block lines if requested
+    fn render_collapsing(
+        &self,
+        handlebars: &Handlebars<'static>,
+        template: &str,
+        variables: &HashMap<String, String>,
+        options: &RenderOptions,
+    ) -> Result<String> {
+        if !options.collapse_empty_blocks {
+            return Self::render_in(handlebars, template, variables);
+        }
+        
+        // Mark lines holding block tags so we can tell which output lines
+        // are empty only because their block rendered nothing
+        let marked = template.split('\n')
+            .map(|line| {
+                if has_block_tag(line) {
+                    format!("{}{}", line, BLOCK_LINE_MARKER)
+                } else {
+                    line.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        
+        let rendered = Self::render_in(handlebars, &marked, variables)?;
+        let collapsed = rendered.split('\n')
+            .filter_map(|line| {
+                if !line.contains(BLOCK_LINE_MARKER) {
+                    return Some(line.to_string());
+                }
+                let stripped = line.replace(BLOCK_LINE_MARKER, "");
+                (!stripped.trim().is_empty()).then_some(stripped)
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        
+        Ok(collapsed)
+    }
+    
+    /// Render a template with per-call configuration overrides
+    ///
+    /// A transient copy of the registry (keeping registered helpers) is
+    /// used when overrides are present, so the shared engine is never
+    /// mutated.
+    pub fn render_with_config(
+        &self,
+        template: &str,
+        variables: &HashMap<String, String>,
+        config: &RenderConfig,
+    ) -> Result<String> {
+        if !config.has_overrides() {
+            return self.render(template, variables);
+        }
+        
+        let template = config.normalize_template(template);
+        let mut variables = with_inline_defaults(&template, variables);
+        if let Some(placeholder) = &config.missing_placeholder {
+            for name in self.extract_variables(&template)? {
+                variables.entry(name).or_insert_with(|| placeholder.clone());
+            }
+        }
+        
+        let mut handlebars = self.handlebars.clone();
+        if let Some(strict) = config.strict_mode {
+            handlebars.set_strict_mode(strict);
+        }
+        
+        let context: Value = variables.iter()
+            .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+            .collect::<serde_json::Map<String, Value>>()
+            .into();
+        let rendered = handlebars.render_template(&template, &context)?;
+        debug!("Rendered template with {} variables and config overrides", variables.len());
+        Ok(rendered)
+    }
+    
+    /// Approximate the rendered length of a template in characters
+    ///
+    /// Sums the literal text and the lengths of provided variables
+    /// referenced by each expression (missing variables count as zero)
+    /// without rendering. The estimate is exact for plain variables and the
+    /// case helpers; block content is counted once regardless of
+    /// conditions, and quoted helper arguments count as zero, so expect
+    /// templates using blocks or `default` to differ by those amounts. The
+    /// estimate never decreases when a variable value grows.
+    pub fn estimate_rendered_length(&self, template: &str, variables: &HashMap<String, String>) -> usize {
+        let mut length = 0;
+        let mut rest = template;
+        
+        while let Some(start) = rest.find("{{") {
+            length += rest[..start].chars().count();
+            let Some(end) = rest[start..].find("}}") else {
+                rest = &rest[start..];
+                break;
+            };
+            
+            let expression = rest[start + 2..start + end].trim_matches(|c| c == '{' || c == '~').trim();
+            let is_tag = expression.starts_with(['#', '/', '^', '!', '>']) || expression.starts_with("else");
+            if !is_tag {
+                length += expression.split_whitespace()
+                    .map(|token| token.trim_matches(|c| c == '(' || c == ')'))
+                    .filter_map(|token| variables.get(token))
+                    .map(|value| value.chars().count())
+                    .sum::<usize>();
+            }
+            
+            rest = rest[start + end + 2..].trim_start_matches('}');
+        }
+        
+        length + rest.chars().count()
+    }
+    
+    /// Replace the set of helpers treated as non-deterministic
+    pub fn set_nondeterministic_helpers<I, S>(&mut self, helpers: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.nondeterministic_helpers = helpers.into_iter().map(Into::into).collect();
+    }
+    
+    /// Mark an additional helper as non-deterministic
+    pub fn add_nondeterministic_helper(&mut self, name: &str) {
+        self.nondeterministic_helpers.insert(name.to_string());
+    }
+    
+    /// Check if a template always renders the same output for the same variables
+    ///
+    /// Returns false if any expression uses a helper from the
+    /// non-deterministic set (by default `now`, `env`, `random`, `uuid`,
+    /// and `timestamp`), including inside subexpressions.
+    pub fn is_deterministic(&self, template: &str) -> bool {
+        let mut rest = template;
+        
+        while let Some(start) = rest.find("{{") {
+            let Some(end) = rest[start..].find("}}") else {
+                break;
+            };
+            let expression = &rest[start + 2..start + end];
+            let uses_nondeterministic = expression
+                .split(|c: char| c.is_whitespace() || c == '(' || c == ')')
+                .map(|token| token.trim_start_matches(['{', '~', '#', '/']))
+                .any(|token| self.nondeterministic_helpers.contains(token));
+            if uses_nondeterministic {
+                return false;
+            }
+            rest = &rest[start + end + 2..];
+        }
+        
+        true
+    }
+    
+    /// Register a partial usable from templates as `{{> name}}`
+    pub fn register_partial(&mut self, name: &str, template: &str) -> Result<()> {
+        self.handlebars.register_partial(name, template)?;
+        Ok(())
+    }
+    
+    /// Compile a template once and keep it for [`render_registered`](Self::render_registered)
+    ///
+    /// Syntax errors are reported now as `PromptError::TemplateSyntax`, the
+    /// same as [`validate_template`](Self::validate_template).
+    /// Registering a name again replaces the template. Registered templates
+    /// share the namespace of partials.
+    pub fn register_template(&mut self, name: &str, source: &str) -> Result<()> {
+        self.handlebars.register_template_string(name, source)
+            .map_err(|e| syntax_error(&e))?;
+        self.registered_defaults.insert(name.to_string(), parse_inline_defaults(source));
+        debug!("Registered template '{}'", name);
+        Ok(())
+    }
+    
+    /// Render a template compiled by [`register_template`](Self::register_template)
+    pub fn render_registered(&self, name: &str, variables: &HashMap<String, String>) -> Result<String> {
+        let mut merged = self.registered_defaults.get(name).cloned().unwrap_or_default();
+        merged.extend(variables.iter().map(|(k, v)| (k.clone(), v.clone())));
+        
+        let context: Value = merged.into_iter()
+            .map(|(k, v)| (k, Value::String(v)))
+            .collect::<serde_json::Map<String, Value>>()
+            .into();
+        let rendered = self.handlebars.render(name, &context)?;
+        debug!("Rendered registered template '{}'", name);
+        Ok(rendered)
+    }
+    
     /// Validate a template for syntax errors
+    ///
+    /// Parse failures are reported as `PromptError::TemplateSyntax` with the
+    /// line and column handlebars points at; other problems found while
+    /// rendering with an empty context are `PromptError::TemplateValidation`.
     pub fn validate_template(&self, template: &str) -> Result<()> {
-        match self.handlebars.render_template(template, &Value::Object(serde_json::Map::new())) {
+        if let Err(e) = handlebars::Template::compile(template) {
+            return Err(syntax_error(&e));
+        }
+        
+        // The trial render has no variables, so it must not be strict
+        let empty = Value::Object(serde_json::Map::new());
+        let rendered = if self.handlebars.strict_mode() {
+            let mut lenient = self.handlebars.clone();
+            lenient.set_strict_mode(false);
+            lenient.render_template(template, &empty)
+        } else {
+            self.handlebars.render_template(template, &empty)
+        };
+        
+        match rendered {
             Ok(_) => Ok(()),
             Err(e) => {
                 // All render errors during validation indicate template issues
@@ -51,51 +446,48 @@ impl TemplateEngine {
     }
     
     /// Extract variable names from a template
+    ///
+    /// Walks the parsed template, so variables used as helper arguments,
+    /// block conditions, subexpressions, and triple-stash output are all
+    /// found, and helper names are not. Nested paths are reported whole
+    /// with dots, e.g. `user.email`. References inside `{{#each}}` and
+    /// `{{#with}}` blocks are relative to the item and are skipped, apart
+    /// from `../name` and `@root.name` references back to the top level.
+    /// `this` and locals like `@index` are never reported.
     pub fn extract_variables(&self, template: &str) -> Result<Vec<String>> {
-        let mut variables = Vec::new();
-        
-        // Parse the template to extract variable names
-        // This is a simple implementation - handlebars doesn't expose the AST directly
-        let mut chars = template.chars().peekable();
-        let mut in_variable = false;
-        let mut current_var = String::new();
-        let mut brace_count: i32 = 0;
-        
-        while let Some(ch) = chars.next() {
-            if ch == '{' {
-                brace_count += 1;
-                if brace_count == 2 {
-                    in_variable = true;
-                    current_var.clear();
-                }
-            } else if ch == '}' {
-                if in_variable && brace_count == 2 {
-                    in_variable = false;
-                    brace_count = 0;
-                    
-                    // Clean up variable name (remove helpers, etc.)
-                    let var_name = current_var.trim().split_whitespace().next().unwrap_or("");
-                    if !var_name.is_empty() && !var_name.starts_with('#') && !var_name.starts_with('/') {
-                        variables.push(var_name.to_string());
-                    }
-                } else {
-                    brace_count = brace_count.saturating_sub(1);
-                }
-            } else if in_variable {
-                current_var.push(ch);
-            } else {
-                brace_count = 0;
-            }
-        }
-        
-        // Remove duplicates and sort
-        variables.sort();
-        variables.dedup();
-        
+        let variables = self.variable_positions(template)?;
         debug!("Extracted {} variables from template", variables.len());
+        Ok(variables.into_keys().collect())
+    }
+    
+    /// Map each variable a template uses to the line and column of its first use
+    fn variable_positions(&self, template: &str) -> Result<BTreeMap<String, (usize, usize)>> {
+        let parsed = handlebars::template::Template::compile(template)?;
+        let mut variables = BTreeMap::new();
+        let mut walker = VariableWalker {
+            helpers: &self.custom_helpers,
+            variables: &mut variables,
+            locals: Vec::new(),
+            position: (0, 0),
+        };
+        walker.template(&parsed, 0);
         Ok(variables)
     }
     
+    /// Find the variables a template uses that weren't provided, with positions
+    ///
+    /// Each entry gives the 1-based line and column of the tag where the
+    /// variable is first used. Entries are sorted by position.
+    pub fn check_variables_detailed(&self, template: &str, provided: &HashMap<String, String>) -> Result<Vec<MissingVar>> {
+        let mut missing: Vec<MissingVar> = self.variable_positions(template)?
+            .into_iter()
+            .filter(|(name, _)| !provided.contains_key(name))
+            .map(|(name, (line, column))| MissingVar { name, line, column })
+            .collect();
+        missing.sort_by_key(|var| (var.line, var.column));
+        Ok(missing)
+    }
+    
     /// Check if a template has all required variables
     pub fn check_variables(&self, template: &str, provided: &HashMap<String, String>) -> Result<Vec<String>> {
         let required = self.extract_variables(template)?;
@@ -106,6 +498,111 @@ impl TemplateEngine {
         
         Ok(missing)
     }
+    
+    /// Pick out the available variables a template references
+    ///
+    /// Uses the same walk as [`extract_variables`](Self::extract_variables),
+    /// so helper arguments and block conditions count while helper names
+    /// like `upper` or `if` do not. Dotted paths keep their root variable.
+    /// A template that fails to parse references nothing.
+    pub fn required_subset(&self, template: &str, available: &HashMap<String, String>) -> HashMap<String, String> {
+        let referenced: BTreeSet<String> = match self.variable_positions(template) {
+            Ok(variables) => variables.into_keys()
+                .filter_map(|path| path.split('.').next().map(str::to_string))
+                .collect(),
+            Err(e) => {
+                debug!("No variables required by unparseable template: {}", e);
+                BTreeSet::new()
+            }
+        };
+        
+        available.iter()
+            .filter(|(name, _)| referenced.contains(name.as_str()))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect()
+    }
+    
+    /// Check that every variable name is a plain identifier
+    ///
+    /// Names may contain only ASCII letters, digits, and underscores; keys
+    /// with spaces or dots would otherwise be looked up as paths and
+    /// silently render nothing. The offending names are returned sorted in
+    /// `PromptError::InvalidVariableNames`.
+    pub fn validate_variable_names(variables: &HashMap<String, String>) -> Result<()> {
+        let mut invalid: Vec<String> = variables.keys()
+            .filter(|name| !is_identifier(name))
+            .cloned()
+            .collect();
+        if invalid.is_empty() {
+            return Ok(());
+        }
+        
+        invalid.sort();
+        Err(PromptError::InvalidVariableNames(invalid))
+    }
+    
+    /// Remap variable names to identifiers by replacing invalid characters with `_`
+    ///
+    /// `user name` becomes `user_name`. When two names map to the same
+    /// identifier, the value of the name that sorts first is kept.
+    pub fn sanitize_variable_names(variables: &HashMap<String, String>) -> HashMap<String, String> {
+        let mut names: Vec<&String> = variables.keys().collect();
+        names.sort();
+        
+        let mut sanitized = HashMap::new();
+        for name in names {
+            let mut identifier: String = name.chars()
+                .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+                .collect();
+            if identifier.is_empty() {
+                identifier.push('_');
+            }
+            sanitized.entry(identifier).or_insert_with(|| variables[name].clone());
+        }
+        sanitized
+    }
+    
+    /// Flatten a JSON object into template variables
+    ///
+    /// Strings are used as-is, numbers and booleans are stringified, null
+    /// becomes an empty string, and nested objects or arrays are JSON-encoded.
+    /// Non-object values produce an empty map.
+    pub fn flatten_json_vars(value: &Value) -> HashMap<String, String> {
+        let Some(object) = value.as_object() else {
+            return HashMap::new();
+        };
+        
+        object.iter()
+            .map(|(k, v)| {
+                let flattened = match v {
+                    Value::String(s) => s.clone(),
+                    Value::Null => String::new(),
+                    Value::Bool(_) | Value::Number(_) | Value::Array(_) | Value::Object(_) => v.to_string(),
+                };
+                (k.clone(), flattened)
+            })
+            .collect()
+    }
+    
+    /// Inflate template variables back into a JSON object
+    ///
+    /// Values that parse as a JSON object or array are restored to their
+    /// nested form; everything else stays a string, so numbers and booleans
+    /// flattened by `flatten_json_vars` come back as strings.
+    pub fn inflate_json_vars(variables: &HashMap<String, String>) -> Value {
+        variables.iter()
+            .map(|(k, v)| {
+                let trimmed = v.trim_start();
+                let inflated = if trimmed.starts_with('{') || trimmed.starts_with('[') {
+                    serde_json::from_str(v).unwrap_or_else(|_| Value::String(v.clone()))
+                } else {
+                    Value::String(v.clone())
+                };
+                (k.clone(), inflated)
+            })
+            .collect::<serde_json::Map<String, Value>>()
+            .into()
+    }
 }
 
 impl Default for TemplateEngine {
@@ -114,6 +611,189 @@ impl Default for TemplateEngine {
     }
 }
 
+/// Parse `name="value"` pairs declared in `{{#defaults ...}}` tags
+pub fn parse_inline_defaults(template: &str) -> HashMap<String, String> {
+    let mut defaults = HashMap::new();
+    let mut rest = template;
+    
+    while let Some(start) = rest.find("{{#defaults") {
+        let params_start = start + "{{#defaults".len();
+        let Some(end) = rest[params_start..].find("}}") else {
+            break;
+        };
+        let mut params = rest[params_start..params_start + end].chars().peekable();
+        
+        loop {
+            while params.next_if(|c| c.is_whitespace()).is_some() {}
+            let key: String = std::iter::from_fn(|| params.next_if(|&c| c != '=' && !c.is_whitespace())).collect();
+            if key.is_empty() || params.next() != Some('=') {
+                break;
+            }
+            let value: String = match params.next_if(|&c| c == '"' || c == '\'') {
+                Some(quote) => {
+                    let value = std::iter::from_fn(|| params.next_if(|&c| c != quote)).collect();
+                    params.next();
+                    value
+                }
+                None => std::iter::from_fn(|| params.next_if(|c| !c.is_whitespace())).collect(),
+            };
+            defaults.insert(key, value);
+        }
+        
+        rest = &rest[params_start + end..];
+    }
+    
+    defaults
+}
+
+/// Merge inline template defaults beneath the provided variables
+fn with_inline_defaults(template: &str, variables: &HashMap<String, String>) -> HashMap<String, String> {
+    let mut merged = parse_inline_defaults(template);
+    merged.extend(variables.iter().map(|(k, v)| (k.clone(), v.clone())));
+    merged
+}
+
+/// A variable a template uses but the caller didn't provide
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingVar {
+    /// Variable name, with dots for nested paths
+    pub name: String,
+    /// 1-based line of the first tag using the variable
+    pub line: usize,
+    /// 1-based column of that tag
+    pub column: usize,
+}
+
+/// Collects top-level variable references from a parsed template
+struct VariableWalker<'a> {
+    helpers: &'a BTreeSet<String>,
+    /// Variables found, with the position of their first use
+    variables: &'a mut BTreeMap<String, (usize, usize)>,
+    /// Block parameter names in scope, e.g. `item` in `{{#each items as |item|}}`
+    locals: Vec<String>,
+    /// Position of the element being visited
+    position: (usize, usize),
+}
+
+impl VariableWalker<'_> {
+    /// Visit every element, `depth` being the number of enclosing item scopes
+    fn template(&mut self, template: &handlebars::template::Template, depth: usize) {
+        for (i, element) in template.elements.iter().enumerate() {
+            if let Some(mapping) = template.mapping.get(i) {
+                self.position = (mapping.0, mapping.1);
+            }
+            self.element(element, depth);
+        }
+    }
+    
+    fn element(&mut self, element: &TemplateElement, depth: usize) {
+        match element {
+            TemplateElement::Expression(expression) | TemplateElement::HtmlExpression(expression) => {
+                let is_helper = !expression.params.is_empty()
+                    || !expression.hash.is_empty()
+                    || expression.name.as_name().is_some_and(|name| self.helpers.contains(name));
+                if !is_helper {
+                    self.parameter(&expression.name, depth);
+                }
+                self.arguments(expression, depth);
+            }
+            TemplateElement::HelperBlock(block) => {
+                let name = block.name.as_name().unwrap_or_default();
+                let is_section = block.params.is_empty() && block.hash.is_empty() && !self.helpers.contains(name);
+                if is_section {
+                    self.parameter(&block.name, depth);
+                }
+                self.arguments(block, depth);
+                
+                let scoped = is_section || matches!(name, "each" | "with");
+                let locals = self.locals.len();
+                if let Some(block_param) = &block.block_param {
+                    let names = match block_param {
+                        BlockParam::Single(name) => vec![name],
+                        BlockParam::Pair((first, second)) => vec![first, second],
+                        _ => Vec::new(),
+                    };
+                    self.locals.extend(names.into_iter().filter_map(|p| p.as_name()).map(str::to_string));
+                }
+                if let Some(body) = &block.template {
+                    self.template(body, if scoped { depth + 1 } else { depth });
+                }
+                self.locals.truncate(locals);
+                if let Some(inverse) = &block.inverse {
+                    self.template(inverse, depth);
+                }
+            }
+            _ => {}
+        }
+    }
+    
+    /// Visit the positional and hash arguments of a helper
+    fn arguments(&mut self, helper: &HelperTemplate, depth: usize) {
+        for parameter in helper.params.iter().chain(helper.hash.values()) {
+            self.parameter(parameter, depth);
+        }
+    }
+    
+    fn parameter(&mut self, parameter: &Parameter, depth: usize) {
+        match parameter {
+            Parameter::Subexpression(subexpression) => self.element(subexpression.as_element(), depth),
+            Parameter::Name(name) => self.reference(name, depth),
+            Parameter::Path(Path::Relative((_, raw))) => self.reference(raw, depth),
+            _ => {}
+        }
+    }
+    
+    /// Record a reference if it resolves against the top-level context
+    fn reference(&mut self, raw: &str, depth: usize) {
+        let path = if let Some(rooted) = raw.strip_prefix("@root.").or_else(|| raw.strip_prefix("@root/")) {
+            rooted.replace('/', ".")
+        } else {
+            let mut rest = raw;
+            let mut up = 0;
+            while let Some(parent) = rest.strip_prefix("../") {
+                rest = parent;
+                up += 1;
+            }
+            if up != depth {
+                return;
+            }
+            let rest = ["this.", "this/", "./"].iter()
+                .find_map(|prefix| rest.strip_prefix(prefix))
+                .unwrap_or(rest);
+            rest.replace('/', ".")
+        };
+        
+        let root = path.split('.').next().unwrap_or_default();
+        let is_local = self.locals.iter().any(|local| local == root);
+        if !path.is_empty() && path != "this" && path != "." && !path.starts_with('@') && !is_local {
+            self.variables.entry(path).or_insert(self.position);
+        }
+    }
+}
+
+/// Convert a handlebars parse error to `PromptError::TemplateSyntax`
+fn syntax_error(error: &handlebars::TemplateError) -> PromptError {
+    let (line, column) = error.pos().unwrap_or((0, 0));
+    PromptError::TemplateSyntax {
+        message: error.reason().to_string(),
+        line,
+        column,
+    }
+}
+
+/// Check if a name is a non-empty run of ASCII letters, digits, and underscores
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Check if a template line opens, closes, or continues a block
+fn has_block_tag(line: &str) -> bool {
+    line.match_indices("{{").any(|(i, _)| {
+        let tag = line[i + 2..].trim_start_matches('~');
+        tag.starts_with('#') || tag.starts_with('/') || tag.starts_with('^') || tag.starts_with("else")
+    })
+}
+
 // Custom Handlebars helpers
 
 /// Convert text to uppercase
@@ -195,6 +875,238 @@ fn default_helper(
     Ok(())
 }
 
+/// Join array elements with a separator, `", "` if none is given
+///
+/// Accepts a JSON array or, for string variables, a JSON-encoded array.
+/// Writes nothing for missing or non-array values. String elements are
+/// written as-is and other elements as JSON.
+fn join_helper(
+    h: &Helper,
+    _: &Handlebars,
+    _: &handlebars::Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let Some(value) = h.param(0).map(|p| p.value()) else {
+        return Ok(());
+    };
+    let separator = h.param(1)
+        .and_then(|p| p.value().as_str())
+        .unwrap_or(", ");
+    
+    let parsed = match value {
+        Value::String(s) => serde_json::from_str::<Value>(s).ok(),
+        _ => None,
+    };
+    let Some(Value::Array(items)) = parsed.as_ref().or(Some(value)) else {
+        return Ok(());
+    };
+    
+    let joined = items.iter()
+        .map(|item| match item {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(separator);
+    out.write(&joined)?;
+    Ok(())
+}
+
+/// Cut text to at most N characters, appending `…` if anything was cut
+///
+/// Counts characters rather than bytes, so multibyte text is never split.
+/// Without a numeric limit the text is written unchanged.
+fn truncate_helper(
+    h: &Helper,
+    _: &Handlebars,
+    _: &handlebars::Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let value = h.param(0)
+        .and_then(|p| p.value().as_str())
+        .unwrap_or("");
+    let limit = h.param(1).and_then(|p| match p.value() {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    });
+    
+    match limit.and_then(|limit| value.char_indices().nth(limit as usize)) {
+        Some((end, _)) => {
+            out.write(&value[..end])?;
+            out.write("…")?;
+        }
+        None => out.write(value)?,
+    }
+    Ok(())
+}
+
+/// Replace every occurrence of one substring with another
+///
+/// Non-string text renders empty; an empty `from` leaves the text unchanged.
+fn replace_helper(
+    h: &Helper,
+    _: &Handlebars,
+    _: &handlebars::Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let Some(text) = h.param(0).and_then(|p| p.value().as_str()) else {
+        return Ok(());
+    };
+    let from = h.param(1).and_then(|p| p.value().as_str()).unwrap_or("");
+    let to = h.param(2).and_then(|p| p.value().as_str()).unwrap_or("");
+    
+    if from.is_empty() {
+        out.write(text)?;
+    } else {
+        out.write(&text.replace(from, to))?;
+    }
+    Ok(())
+}
+
+/// Write the second param if the first is truthy, else the third
+///
+/// Non-empty strings, arrays, and objects, non-zero numbers, and `true`
+/// are truthy; missing values and `null` are not.
+fn ternary_helper(
+    h: &Helper,
+    _: &Handlebars,
+    _: &handlebars::Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let truthy = match h.param(0).map(|p| p.value()) {
+        Some(Value::Bool(b)) => *b,
+        Some(Value::String(s)) => !s.is_empty(),
+        Some(Value::Number(n)) => n.as_f64().is_some_and(|n| n != 0.0),
+        Some(Value::Array(items)) => !items.is_empty(),
+        Some(Value::Object(map)) => !map.is_empty(),
+        Some(Value::Null) | None => false,
+    };
+    
+    let chosen = h.param(if truthy { 1 } else { 2 }).map(|p| p.value());
+    match chosen {
+        Some(Value::String(s)) => out.write(s)?,
+        Some(Value::Null) | None => {}
+        Some(other) => out.write(&other.to_string())?,
+    }
+    Ok(())
+}
+
+/// Count non-overlapping occurrences of a needle in text
+///
+/// Writes `0` for an empty needle or missing text.
+fn count_matches_helper(
+    h: &Helper,
+    _: &Handlebars,
+    _: &handlebars::Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let text = h.param(0)
+        .and_then(|p| p.value().as_str())
+        .unwrap_or("");
+    let needle = h.param(1)
+        .and_then(|p| p.value().as_str())
+        .unwrap_or("");
+    
+    let count = if needle.is_empty() { 0 } else { text.matches(needle).count() };
+    out.write(&count.to_string())?;
+    Ok(())
+}
+
+/// Look up a dotted path in a JSON value or JSON-encoded string variable
+///
+/// Numeric segments index into arrays, so `items.0.title` works. Writes
+/// nothing if the path is missing or leads to an object or array.
+fn get_helper(
+    h: &Helper,
+    _: &Handlebars,
+    _: &handlebars::Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let Some(value) = h.param(0).map(|p| p.value()) else {
+        return Ok(());
+    };
+    let path = h.param(1)
+        .and_then(|p| p.value().as_str())
+        .unwrap_or("");
+    
+    let parsed = match value {
+        Value::String(s) => serde_json::from_str::<Value>(s).ok(),
+        _ => None,
+    };
+    let root = parsed.as_ref().unwrap_or(value);
+    
+    match lookup_json_path(root, path) {
+        Some(Value::String(s)) => out.write(s)?,
+        Some(scalar @ (Value::Number(_) | Value::Bool(_))) => out.write(&scalar.to_string())?,
+        _ => {}
+    }
+    
+    Ok(())
+}
+
+/// Follow a dotted path through nested objects and arrays
+fn lookup_json_path<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .filter(|segment| !segment.is_empty())
+        .try_fold(value, |current, segment| match current {
+            Value::Object(map) => map.get(segment),
+            Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        })
+}
+
+/// Render the default content of an inheritance block
+fn block_helper<'reg, 'rc>(
+    h: &Helper<'rc>,
+    r: &'reg Handlebars<'reg>,
+    ctx: &'rc handlebars::Context,
+    rc: &mut RenderContext<'reg, 'rc>,
+    out: &mut dyn Output,
+) -> HelperResult {
+    if let Some(template) = h.template() {
+        template.render(r, ctx, rc, out)?;
+    }
+    Ok(())
+}
+
+/// Drop the content of a block entirely
+fn omit_helper(
+    _: &Helper,
+    _: &Handlebars,
+    _: &handlebars::Context,
+    _: &mut RenderContext,
+    _: &mut dyn Output,
+) -> HelperResult {
+    Ok(())
+}
+
+/// Strip HTML comments, collapse whitespace, and remove blank lines
+fn compact(text: &str) -> String {
+    let mut uncommented = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("<!--") {
+        uncommented.push_str(&rest[..start]);
+        rest = match rest[start..].find("-->") {
+            Some(end) => &rest[start + end + 3..],
+            None => "",
+        };
+    }
+    uncommented.push_str(rest);
+    
+    uncommented.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -305,4 +1217,449 @@ Your experience level is beginner."#;
         
         assert_eq!(result, expected);
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_flatten_json_vars() {
+        let value = serde_json::json!({
+            "name": "Alice",
+            "age": 30,
+            "languages": ["rust", "go"],
+            "active": true,
+            "nickname": null
+        });
+        
+        let vars = TemplateEngine::flatten_json_vars(&value);
+        assert_eq!(vars.len(), 5);
+        assert_eq!(vars["name"], "Alice");
+        assert_eq!(vars["age"], "30");
+        assert_eq!(vars["languages"], r#"["rust","go"]"#);
+        assert_eq!(vars["active"], "true");
+        assert_eq!(vars["nickname"], "");
+        
+        assert!(TemplateEngine::flatten_json_vars(&serde_json::json!([1, 2])).is_empty());
+    }
+    
+    #[test]
+    fn test_inflate_json_vars() {
+        let value = serde_json::json!({
+            "name": "Alice",
+            "languages": ["rust", "go"],
+            "profile": {"level": "senior"}
+        });
+        
+        let vars = TemplateEngine::flatten_json_vars(&value);
+        assert_eq!(TemplateEngine::inflate_json_vars(&vars), value);
+        
+        let mut vars = HashMap::new();
+        vars.insert("broken".to_string(), "[not json".to_string());
+        let inflated = TemplateEngine::inflate_json_vars(&vars);
+        assert_eq!(inflated["broken"], "[not json");
+    }
+    
+    #[test]
+    fn test_collapse_empty_blocks() {
+        let engine = TemplateEngine::new();
+        let vars = HashMap::new();
+        let template = "You are an assistant.\n{{#if verbose}}Explain in detail.{{/if}}\nBe accurate.\n\nBe kind.";
+        
+        let preserved = engine.render_with_options(template, &vars, &RenderOptions::default()).unwrap();
+        assert_eq!(preserved, "You are an assistant.\n\nBe accurate.\n\nBe kind.");
+        
+        let options = RenderOptions { collapse_empty_blocks: true, ..RenderOptions::default() };
+        let collapsed = engine.render_with_options(template, &vars, &options).unwrap();
+        assert_eq!(collapsed, "You are an assistant.\nBe accurate.\n\nBe kind.");
+        
+        let mut vars = HashMap::new();
+        vars.insert("verbose".to_string(), "yes".to_string());
+        let rendered = engine.render_with_options(template, &vars, &options).unwrap();
+        assert_eq!(rendered, "You are an assistant.\nExplain in detail.\nBe accurate.\n\nBe kind.");
+    }
+    
+    #[test]
+    fn test_render_config_strict_vs_lenient() {
+        let engine = TemplateEngine::new();
+        let mut vars = HashMap::new();
+        vars.insert("name".to_string(), "Alice".to_string());
+        let template = "Hello {{name}}, you speak {{language}}.";
+        
+        let lenient = RenderConfig { strict_mode: Some(false), ..RenderConfig::default() };
+        let result = engine.render_with_config(template, &vars, &lenient).unwrap();
+        assert_eq!(result, "Hello Alice, you speak .");
+        
+        let strict = RenderConfig { strict_mode: Some(true), ..RenderConfig::default() };
+        assert!(engine.render_with_config(template, &vars, &strict).is_err());
+        
+        // Shared engine is left lenient
+        assert_eq!(engine.render(template, &vars).unwrap(), "Hello Alice, you speak .");
+        
+        let placeholder = RenderConfig {
+            strict_mode: Some(true),
+            missing_placeholder: Some("?".to_string()),
+            ..RenderConfig::default()
+        };
+        let result = engine.render_with_config(template, &vars, &placeholder).unwrap();
+        assert_eq!(result, "Hello Alice, you speak ?.");
+    }
+    
+    #[test]
+    fn test_render_config_custom_delimiters() {
+        let engine = TemplateEngine::new();
+        let mut vars = HashMap::new();
+        vars.insert("name".to_string(), "alice".to_string());
+        
+        let config = RenderConfig {
+            delimiters: Some(("<%".to_string(), "%>".to_string())),
+            ..RenderConfig::default()
+        };
+        let result = engine.render_with_config("Hi <%capitalize name%>, keep {{braces}}.", &vars, &config).unwrap();
+        assert_eq!(result, "Hi Alice, keep {{braces}}.");
+    }
+    
+    #[test]
+    fn test_estimate_rendered_length() {
+        let engine = TemplateEngine::new();
+        let mut vars = HashMap::new();
+        vars.insert("name".to_string(), "Ada".to_string());
+        vars.insert("language".to_string(), "rust".to_string());
+        
+        // Exact for plain variables and case helpers
+        let template = "Hello {{name}}, you write {{upper language}}. {{{name}}}";
+        let actual = engine.render(template, &vars).unwrap().chars().count();
+        assert_eq!(engine.estimate_rendered_length(template, &vars), actual);
+        
+        // Quoted defaults are uncounted, so within their length
+        let template = "You are a {{default role \"assistant\"}} for {{name}}.";
+        let actual = engine.render(template, &vars).unwrap().chars().count();
+        let estimate = engine.estimate_rendered_length(template, &vars);
+        assert!(actual - estimate <= "assistant".len());
+        
+        // Monotonic in variable length
+        let mut longer = vars.clone();
+        longer.insert("name".to_string(), "Ada Lovelace".to_string());
+        assert!(engine.estimate_rendered_length(template, &longer) > estimate);
+    }
+    
+    #[test]
+    fn test_inline_defaults() {
+        let engine = TemplateEngine::new();
+        let template = "{{#defaults role=\"assistant\" tone='very formal'}}\nYou are a {{role}}. Be {{tone}}.\n{{/defaults}}";
+        
+        let defaults = parse_inline_defaults(template);
+        assert_eq!(defaults["role"], "assistant");
+        assert_eq!(defaults["tone"], "very formal");
+        
+        let result = engine.render(template, &HashMap::new()).unwrap();
+        assert_eq!(result, "You are a assistant. Be very formal.\n");
+        
+        let mut vars = HashMap::new();
+        vars.insert("role".to_string(), "reviewer".to_string());
+        let result = engine.render(template, &vars).unwrap();
+        assert_eq!(result, "You are a reviewer. Be very formal.\n");
+    }
+    
+    #[test]
+    fn test_is_deterministic() {
+        let mut engine = TemplateEngine::new();
+        
+        assert!(!engine.is_deterministic("Today is {{now}}."));
+        assert!(!engine.is_deterministic("Seed: {{upper (random)}}"));
+        assert!(engine.is_deterministic("Hello {{capitalize name}}, the time is now."));
+        
+        engine.add_nondeterministic_helper("weather");
+        assert!(!engine.is_deterministic("It is {{weather}} outside."));
+        
+        engine.set_nondeterministic_helpers(["weather"]);
+        assert!(engine.is_deterministic("Today is {{now}}."));
+    }
+    
+    #[test]
+    fn test_register_partial() {
+        let mut engine = TemplateEngine::new();
+        engine.register_partial("greeting", "Hello, {{name}}!").unwrap();
+        
+        let mut vars = HashMap::new();
+        vars.insert("name".to_string(), "Ada".to_string());
+        assert_eq!(engine.render("{{> greeting}} Welcome.", &vars).unwrap(), "Hello, Ada! Welcome.");
+    }
+    
+    #[test]
+    fn test_compact_mode() {
+        let engine = TemplateEngine::new();
+        let mut vars = HashMap::new();
+        vars.insert("task".to_string(), "sort a list".to_string());
+        let template = "You   are a  coder.\n\n<!-- internal note -->\nTask: {{task}}\n{{#optional}}Explain your reasoning in detail.{{/optional}}\n";
+        
+        let normal = engine.render_with_options(template, &vars, &RenderOptions::default()).unwrap();
+        assert_eq!(normal, "You   are a  coder.\n\n<!-- internal note -->\nTask: sort a list\nExplain your reasoning in detail.\n");
+        
+        let options = RenderOptions { mode: RenderMode::Compact, ..RenderOptions::default() };
+        let compacted = engine.render_with_options(template, &vars, &options).unwrap();
+        assert_eq!(compacted, "You are a coder.\nTask: sort a list");
+    }
+    
+    #[test]
+    fn test_get_helper() {
+        let engine = TemplateEngine::new();
+        let mut vars = HashMap::new();
+        vars.insert("user".to_string(), r#"{"profile": {"name": "Ada", "age": 36}}"#.to_string());
+        vars.insert("order".to_string(), r#"{"items": [{"title": "Book"}, {"title": "Lamp"}]}"#.to_string());
+        
+        assert_eq!(engine.render(r#"{{get user "profile.name"}}"#, &vars).unwrap(), "Ada");
+        assert_eq!(engine.render(r#"{{get user "profile.age"}}"#, &vars).unwrap(), "36");
+        assert_eq!(engine.render(r#"{{get order "items.1.title"}}"#, &vars).unwrap(), "Lamp");
+        assert_eq!(engine.render(r#"[{{get user "profile.email"}}]"#, &vars).unwrap(), "[]");
+        assert_eq!(engine.render(r#"[{{get order "items.5.title"}}]"#, &vars).unwrap(), "[]");
+        assert_eq!(engine.render(r#"[{{get missing "a.b"}}]"#, &vars).unwrap(), "[]");
+    }
+    
+    #[test]
+    fn test_join_helper() {
+        let engine = TemplateEngine::new();
+        let context = serde_json::json!({
+            "languages": ["Rust", "Go", "Python"],
+            "single": ["Rust"],
+            "empty": [],
+            "versions": [1, 2],
+            "name": "Ada"
+        });
+        let render = |template: &str| engine.render_value(template, &context).unwrap();
+        
+        assert_eq!(render("{{join languages}}"), "Rust, Go, Python");
+        assert_eq!(render(r#"{{join languages " | "}}"#), "Rust | Go | Python");
+        assert_eq!(render(r#"{{join single " | "}}"#), "Rust");
+        assert_eq!(render("[{{join empty}}]"), "[]");
+        assert_eq!(render(r#"{{join versions "."}}"#), "1.2");
+        assert_eq!(render("[{{join name}}]"), "[]");
+        assert_eq!(render("[{{join missing}}]"), "[]");
+        
+        // String variables holding a JSON array work too
+        let mut vars = HashMap::new();
+        vars.insert("tags".to_string(), r#"["a", "b"]"#.to_string());
+        assert_eq!(engine.render(r#"{{join tags "+"}}"#, &vars).unwrap(), "a+b");
+    }
+    
+    #[test]
+    fn test_truncate_helper() {
+        let engine = TemplateEngine::new();
+        let render = |template: &str, bio: &str| {
+            let mut vars = HashMap::new();
+            vars.insert("bio".to_string(), bio.to_string());
+            engine.render(template, &vars).unwrap()
+        };
+        
+        assert_eq!(render("{{truncate bio 5}}", "Hello world"), "Hello…");
+        assert_eq!(render("{{truncate bio 5}}", "Hello"), "Hello");
+        assert_eq!(render("{{truncate bio 6}}", "Hello"), "Hello");
+        assert_eq!(render("{{truncate bio 3}}", "🦀🦀🦀🦀"), "🦀🦀🦀…");
+        assert_eq!(render("{{truncate bio 2}}", "héllo"), "hé…");
+        assert_eq!(render("{{truncate bio 0}}", "abc"), "…");
+        assert_eq!(render("{{truncate bio}}", "unchanged"), "unchanged");
+        assert_eq!(render(r#"{{truncate bio "lots"}}"#, "unchanged"), "unchanged");
+    }
+    
+    #[test]
+    fn test_register_template() {
+        let mut engine = TemplateEngine::new();
+        engine.register_template("greet", "{{#defaults tone=\"warm\"}}Hello {{upper name}}, {{tone}} regards{{/defaults}}").unwrap();
+        
+        let mut vars = HashMap::new();
+        vars.insert("name".to_string(), "ada".to_string());
+        assert_eq!(engine.render_registered("greet", &vars).unwrap(), "Hello ADA, warm regards");
+        vars.insert("tone".to_string(), "kind".to_string());
+        assert_eq!(engine.render_registered("greet", &vars).unwrap(), "Hello ADA, kind regards");
+        
+        let err = engine.register_template("broken", "Hi\n{{#if x}}never closed").unwrap_err();
+        assert!(matches!(err, PromptError::TemplateSyntax { line: 2, .. }), "{:?}", err);
+        assert!(engine.render_registered("broken", &vars).is_err());
+    }
+    
+    #[test]
+    fn test_count_matches_helper() {
+        let engine = TemplateEngine::new();
+        let render = |template: &str, text: &str| {
+            let mut vars = HashMap::new();
+            vars.insert("text".to_string(), text.to_string());
+            engine.render(template, &vars).unwrap()
+        };
+        
+        assert_eq!(render(r#"{{count_matches text "TODO"}}"#, "TODO: a\nTODO: b\nDONE\nTODO"), "3");
+        assert_eq!(render(r#"{{count_matches text "TODO"}}"#, "all done"), "0");
+        assert_eq!(render(r#"{{count_matches text ""}}"#, "anything"), "0");
+        assert_eq!(render(r#"{{count_matches text "aa"}}"#, "aaaa a"), "2");
+        assert_eq!(render(r#"{{count_matches missing "x"}}"#, ""), "0");
+    }
+    
+    #[test]
+    fn test_replace_helper() {
+        let engine = TemplateEngine::new();
+        let mut vars = HashMap::new();
+        vars.insert("title".to_string(), "senior_staff_engineer".to_string());
+        
+        let render = |template: &str| engine.render(template, &vars).unwrap();
+        assert_eq!(render(r#"{{replace title "_" " "}}"#), "senior staff engineer");
+        assert_eq!(render(r#"{{upper (replace title "_" " ")}}"#), "SENIOR STAFF ENGINEER");
+        assert_eq!(render(r#"{{replace title "" "-"}}"#), "senior_staff_engineer");
+        assert_eq!(render(r#"{{replace missing "_" " "}}"#), "");
+        assert_eq!(engine.render_value(r#"{{replace n "1" "2"}}"#, &serde_json::json!({"n": 1})).unwrap(), "");
+    }
+    
+    #[test]
+    fn test_ternary_helper() {
+        let engine = TemplateEngine::new();
+        let render = |context: serde_json::Value| {
+            engine.render_value(r#"You are a {{ternary verbose "detailed" "concise"}} assistant"#, &context).unwrap()
+        };
+        
+        assert_eq!(render(serde_json::json!({"verbose": "yes"})), "You are a detailed assistant");
+        assert_eq!(render(serde_json::json!({"verbose": ""})), "You are a concise assistant");
+        assert_eq!(render(serde_json::json!({"verbose": true})), "You are a detailed assistant");
+        assert_eq!(render(serde_json::json!({"verbose": false})), "You are a concise assistant");
+        assert_eq!(render(serde_json::json!({"verbose": 0})), "You are a concise assistant");
+        assert_eq!(render(serde_json::json!({"verbose": 2.5})), "You are a detailed assistant");
+        assert_eq!(render(serde_json::json!({})), "You are a concise assistant");
+        assert_eq!(engine.render(r#"{{ternary true "on" "off"}}/{{ternary false "on" "off"}}"#, &HashMap::new()).unwrap(), "on/off");
+    }
+    
+    #[test]
+    fn test_helpers_used() {
+        let mut engine = TemplateEngine::new();
+        engine.register_helper("shout", Box::new(upper_helper));
+        
+        let template = "{{#if name}}{{shout name}}{{/if}} {{default (lower tone) \"calm\"}} {{name}}";
+        assert_eq!(engine.helpers_used(template), vec!["default", "lower", "shout"]);
+        assert!(engine.has_helper("shout"));
+        assert!(!engine.has_helper("if"));
+    }
+    
+    #[test]
+    fn test_validation_error_position() {
+        let engine = TemplateEngine::new();
+        let template = "Hello {{name}}\nBroken {{#if ready}} here {{/each}}\nEnd";
+        
+        match engine.validate_template(template) {
+            Err(PromptError::TemplateSyntax { line, column, .. }) => {
+                assert_eq!(line, 2);
+                assert!(column > 0);
+            }
+            other => panic!("expected a syntax error, got {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn test_validate_variable_names() {
+        let mut vars = HashMap::new();
+        vars.insert("user_name".to_string(), "Ada".to_string());
+        vars.insert("item2".to_string(), "Lamp".to_string());
+        assert!(TemplateEngine::validate_variable_names(&vars).is_ok());
+        
+        vars.insert("first name".to_string(), "Ada".to_string());
+        vars.insert("user.email".to_string(), "ada@example.com".to_string());
+        match TemplateEngine::validate_variable_names(&vars) {
+            Err(PromptError::InvalidVariableNames(names)) => assert_eq!(names, vec!["first name", "user.email"]),
+            other => panic!("expected invalid names, got {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn test_sanitize_variable_names() {
+        let mut vars = HashMap::new();
+        vars.insert("first name".to_string(), "Ada".to_string());
+        vars.insert("user.email".to_string(), "ada@example.com".to_string());
+        vars.insert("ok".to_string(), "yes".to_string());
+        
+        let sanitized = TemplateEngine::sanitize_variable_names(&vars);
+        assert!(TemplateEngine::validate_variable_names(&sanitized).is_ok());
+        assert_eq!(sanitized["first_name"], "Ada");
+        assert_eq!(sanitized["user_email"], "ada@example.com");
+        assert_eq!(sanitized["ok"], "yes");
+    }
+    
+    #[test]
+    fn test_glossary_define() {
+        let mut engine = TemplateEngine::new();
+        let vars = HashMap::new();
+        assert_eq!(engine.render(r#"{{define "idempotent"}}"#, &vars).unwrap(), "idempotent");
+        
+        engine.register_glossary(HashMap::from([
+            ("idempotent".to_string(), "safe to repeat".to_string()),
+            ("pure".to_string(), "free of side effects".to_string()),
+        ]));
+        let template = r#"Calls are {{define "idempotent"}}, helpers are {{define "pure"}}, data is {{define "immutable"}}."#;
+        assert_eq!(
+            engine.render(template, &vars).unwrap(),
+            "Calls are safe to repeat, helpers are free of side effects, data is immutable."
+        );
+    }
+    
+    #[test]
+    fn test_required_subset() {
+        let engine = TemplateEngine::new();
+        let available: HashMap<String, String> = [("name", "Ada"), ("role", "admin"), ("email", "a@x"), ("team", "core"), ("upper", "x"), ("if", "y")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        
+        let subset = engine.required_subset("Hi {{upper name}}{{#if role}}, {{role}}{{/if}} ({{default nick \"team\"}})", &available);
+        let mut keys: Vec<&String> = subset.keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["name", "role"]);
+        assert_eq!(subset["name"], "Ada");
+        
+        let subset = engine.required_subset("{{#each items}}{{team}}{{/each}} {{email.domain}}", &available);
+        let mut keys: Vec<&String> = subset.keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["email"]);
+        assert!(engine.required_subset("{{#if name}}", &available).is_empty());
+    }
+    
+    #[test]
+    fn test_render_value_with_list() {
+        let engine = TemplateEngine::new();
+        let context = serde_json::json!({
+            "title": "Rules",
+            "rules": ["Be concise", "Cite sources"],
+            "author": {"name": "Ada"}
+        });
+        
+        let rendered = engine
+            .render_value("{{title}} by {{author.name}}:\n{{#each rules}}- {{this}}\n{{/each}}", &context)
+            .unwrap();
+        assert_eq!(rendered, "Rules by Ada:\n- Be concise\n- Cite sources\n");
+    }
+    
+    #[test]
+    fn test_extract_variables_from_ast() {
+        let engine = TemplateEngine::new();
+        let extract = |template: &str| engine.extract_variables(template).unwrap();
+        
+        assert_eq!(extract("{{upper name}}"), vec!["name"]);
+        assert_eq!(extract("{{#each items}}{{this}}{{/each}}"), vec!["items"]);
+        assert_eq!(extract("Contact {{user.email}}"), vec!["user.email"]);
+        assert_eq!(extract("{{{raw}}} {{default (lower tone) \"calm\"}}"), vec!["raw", "tone"]);
+        assert_eq!(
+            extract("{{#if draft}}{{title}}{{else}}{{fallback}}{{/if}}"),
+            vec!["draft", "fallback", "title"]
+        );
+        assert_eq!(
+            extract("{{#each rows as |row|}}{{row.id}} {{field}} {{../prefix}} {{@index}}{{/each}}"),
+            vec!["prefix", "rows"]
+        );
+        assert_eq!(extract("{{#with author}}{{name}} {{@root.site}}{{/with}}"), vec!["author", "site"]);
+        assert!(engine.extract_variables("{{#if x}}unclosed").is_err());
+    }
+    
+    #[test]
+    fn test_check_variables_detailed() {
+        let engine = TemplateEngine::new();
+        let template = "Hello {{name}},\n  you work on {{project}}.\n{{#if urgent}}Hurry, {{name}}!{{/if}}";
+        let mut provided = HashMap::new();
+        provided.insert("project".to_string(), "gamecode".to_string());
+        
+        let missing = engine.check_variables_detailed(template, &provided).unwrap();
+        assert_eq!(missing, vec![
+            MissingVar { name: "name".to_string(), line: 1, column: 7 },
+            MissingVar { name: "urgent".to_string(), line: 3, column: 1 },
+        ]);
+        assert_eq!(engine.check_variables(template, &provided).unwrap(), vec!["name", "urgent"]);
+    }
+}
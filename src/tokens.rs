@@ -0,0 +1,113 @@
+//! Approximate token estimation and token-bounded chunking
+
+/// Estimate the number of model tokens in text
+///
+/// Each whitespace-separated word counts as one token per four
+/// alphanumeric characters (at least one), plus one token per punctuation
+/// or symbol character. This tracks common BPE tokenizers to within
+/// roughly 25% for English prose and code; it is not exact.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.split_whitespace()
+        .map(|word| {
+            let (alnum, other) = word.chars().fold((0usize, 0usize), |(alnum, other), c| {
+                if c.is_alphanumeric() {
+                    (alnum + 1, other)
+                } else {
+                    (alnum, other + 1)
+                }
+            });
+            alnum.div_ceil(4) + other
+        })
+        .sum()
+}
+
+/// Split text into chunks that each fit within a token budget
+///
+/// Paragraphs (separated by blank lines) are packed greedily and never
+/// split unless a single paragraph exceeds the budget, in which case it is
+/// split on sentence boundaries, and as a last resort on words.
+pub fn chunk_text(text: &str, max_tokens: usize) -> Vec<String> {
+    let paragraphs = text.split("\n\n").map(str::trim).filter(|p| !p.is_empty());
+
+    let mut pieces = Vec::new();
+    for paragraph in paragraphs {
+        if estimate_tokens(paragraph) <= max_tokens {
+            pieces.push(paragraph.to_string());
+        } else {
+            pieces.extend(pack(split_sentences(paragraph), max_tokens, " "));
+        }
+    }
+
+    pack(pieces, max_tokens, "\n\n")
+}
+
+/// Greedily pack pieces into strings within the budget, splitting
+/// oversized pieces on words
+fn pack(pieces: Vec<String>, max_tokens: usize, separator: &str) -> Vec<String> {
+    let mut packed = Vec::new();
+    let mut current = String::new();
+
+    for piece in pieces {
+        let parts = if estimate_tokens(&piece) > max_tokens {
+            pack(piece.split_whitespace().map(str::to_string).collect(), max_tokens, " ")
+        } else {
+            vec![piece]
+        };
+
+        for part in parts {
+            let candidate = if current.is_empty() {
+                part.clone()
+            } else {
+                format!("{}{}{}", current, separator, part)
+            };
+            if estimate_tokens(&candidate) <= max_tokens || current.is_empty() {
+                current = candidate;
+            } else {
+                packed.push(std::mem::replace(&mut current, part));
+            }
+        }
+    }
+    if !current.is_empty() {
+        packed.push(current);
+    }
+    packed
+}
+
+/// Split a paragraph into sentences, keeping terminal punctuation
+fn split_sentences(paragraph: &str) -> Vec<String> {
+    let mut sentences = Vec::new();
+    let mut current = String::new();
+    let mut chars = paragraph.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        current.push(c);
+        let at_boundary = matches!(c, '.' | '!' | '?')
+            && chars.peek().is_none_or(|next| next.is_whitespace());
+        if at_boundary {
+            sentences.push(current.trim().to_string());
+            current.clear();
+        }
+    }
+    if !current.trim().is_empty() {
+        sentences.push(current.trim().to_string());
+    }
+    sentences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("Hello world"), 4);
+        assert_eq!(estimate_tokens("Hi, you!"), 4);
+    }
+
+    #[test]
+    fn test_split_sentences() {
+        let sentences = split_sentences("First one. Second one! Third? Trailing");
+        assert_eq!(sentences, vec!["First one.", "Second one!", "Third?", "Trailing"]);
+    }
+}
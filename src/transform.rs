@@ -0,0 +1,79 @@
+//! Line-based transform pipeline for loaded prompts
+
+use std::fmt;
+
+/// A single line transform step
+pub type LineTransform = Box<dyn Fn(&str) -> String + Send + Sync>;
+
+/// A whole-prompt transform applied before saving
+pub type SaveTransform = Box<dyn Fn(&str) -> String + Send + Sync>;
+
+/// Ordered chain of line transforms applied to prompt content
+///
+/// Each line of the prompt is passed through every step in the order the
+/// steps were added, with the output of one step feeding the next. Order
+/// matters: stripping comments before normalizing whitespace removes the
+/// whitespace that preceded the comment, while the reverse order leaves it
+/// behind.
+#[derive(Default)]
+pub struct TransformPipeline {
+    steps: Vec<LineTransform>,
+}
+
+impl TransformPipeline {
+    /// Create an empty pipeline
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a step, returning the pipeline for chaining
+    pub fn then<F>(mut self, step: F) -> Self
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        self.push(step);
+        self
+    }
+
+    /// Append a step to the end of the pipeline
+    pub fn push<F>(&mut self, step: F)
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        self.steps.push(Box::new(step));
+    }
+
+    /// Number of steps in the pipeline
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Check if the pipeline has no steps
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Apply every step to each line of the text
+    pub fn apply(&self, text: &str) -> String {
+        if self.steps.is_empty() {
+            return text.to_string();
+        }
+
+        text.lines()
+            .map(|line| {
+                self.steps
+                    .iter()
+                    .fold(line.to_string(), |acc, step| step(&acc))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl fmt::Debug for TransformPipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TransformPipeline")
+            .field("steps", &self.steps.len())
+            .finish()
+    }
+}
@@ -0,0 +1,145 @@
+//! Watching a prompts directory for changes made outside the API
+
+use crate::error::{PromptError, Result};
+use crate::events::{ChangeKind, PromptChangeEvent};
+use notify::{RecursiveMode, Watcher};
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// What a file in the watched directory holds
+pub(crate) enum WatchedFile {
+    Prompt(String),
+    Default,
+    Metadata,
+}
+
+/// Watch a directory, sending one event per changed file once changes settle
+///
+/// Raw filesystem events are collected until `debounce` passes without
+/// any, so an editor's burst of writes for one save yields one event.
+/// Whether a prompt was created, modified, or deleted is decided by
+/// comparing the files present before and after. `classify` maps a file
+/// path to what it holds; files it returns `None` for are ignored. With
+/// `recursive`, files in subdirectories are watched too.
+pub(crate) fn watch_directory<F>(dir: &Path, recursive: bool, debounce: Duration, classify: F) -> Result<Receiver<PromptChangeEvent>>
+where
+    F: Fn(&Path) -> Option<WatchedFile> + Send + 'static,
+{
+    let (raw_tx, raw_rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(raw_tx)
+        .map_err(|e| PromptError::Storage(format!("Failed to create watcher: {}", e)))?;
+    let mode = if recursive { RecursiveMode::Recursive } else { RecursiveMode::NonRecursive };
+    watcher.watch(dir, mode)
+        .map_err(|e| PromptError::Storage(format!("Failed to watch {}: {}", dir.display(), e)))?;
+
+    let dir = dir.to_path_buf();
+    // Events may carry the canonical path, e.g. through a symlinked temp dir
+    let canonical = dir.canonicalize().unwrap_or_else(|_| dir.clone());
+    let mut present = HashSet::new();
+    collect_files(&dir, recursive, &mut present)
+        .map_err(|e| PromptError::Storage(format!("Failed to read prompts directory: {}", e)))?;
+
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        // The watcher lives as long as the thread, which ends when the receiver is dropped
+        let _watcher = watcher;
+        let mut pending = BTreeSet::new();
+        loop {
+            match raw_rx.recv_timeout(debounce) {
+                Ok(Ok(event)) => {
+                    let event: notify::Event = event;
+                    // Reads, including our own metadata lookups, change nothing
+                    if event.kind.is_access() {
+                        continue;
+                    }
+                    pending.extend(event.paths.iter().filter_map(|path| {
+                        let relative = path.strip_prefix(&dir).or_else(|_| path.strip_prefix(&canonical)).ok()?;
+                        (recursive || relative.components().count() == 1).then(|| dir.join(relative))
+                    }));
+                }
+                Ok(Err(e)) => warn!("Error watching {}: {}", dir.display(), e),
+                Err(RecvTimeoutError::Timeout) => {
+                    let changed = std::mem::take(&mut pending);
+                    if !flush(changed, recursive, &mut present, &classify, &tx) {
+                        break;
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        debug!("Stopped watching {}", dir.display());
+    });
+
+    Ok(rx)
+}
+
+/// Add the files in a directory to `files`, descending into subdirectories if `recursive`
+fn collect_files(dir: &Path, recursive: bool, files: &mut HashSet<PathBuf>) -> std::io::Result<()> {
+    for path in fs::read_dir(dir)?.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+        if path.is_dir() {
+            if recursive {
+                collect_files(&path, recursive, files)?;
+            }
+        } else if path.is_file() {
+            files.insert(path);
+        }
+    }
+    Ok(())
+}
+
+/// Send events for settled changes, returning false once nobody is listening
+fn flush<F>(mut changed: BTreeSet<PathBuf>, recursive: bool, present: &mut HashSet<PathBuf>, classify: &F, tx: &Sender<PromptChangeEvent>) -> bool
+where
+    F: Fn(&Path) -> Option<WatchedFile>,
+{
+    // A removed directory may be reported without the files it held
+    let removed: Vec<PathBuf> = present.iter()
+        .filter(|file| !file.exists() && file.ancestors().skip(1).any(|dir| changed.contains(dir)))
+        .cloned()
+        .collect();
+    changed.extend(removed);
+
+    // Files written into a new directory before it was watched raise no events of their own
+    let mut added = HashSet::new();
+    for dir in changed.iter().filter(|path| recursive && path.is_dir()) {
+        if let Err(e) = collect_files(dir, true, &mut added) {
+            warn!("Failed to read {}: {}", dir.display(), e);
+        }
+    }
+    changed.extend(added.into_iter().filter(|file| !present.contains(file)));
+
+    for path in changed {
+        let existed = present.contains(&path);
+        let exists = path.is_file();
+        if exists {
+            present.insert(path.clone());
+        } else {
+            present.remove(&path);
+        }
+
+        let event = match classify(&path) {
+            Some(WatchedFile::Prompt(name)) => {
+                let kind = match (existed, exists) {
+                    (false, true) => ChangeKind::Created,
+                    (true, true) => ChangeKind::Modified,
+                    (true, false) => ChangeKind::Deleted,
+                    (false, false) => continue,
+                };
+                PromptChangeEvent { name, kind }
+            }
+            Some(WatchedFile::Default) => PromptChangeEvent { name: String::new(), kind: ChangeKind::DefaultChanged },
+            Some(WatchedFile::Metadata) => PromptChangeEvent { name: String::new(), kind: ChangeKind::MetadataChanged },
+            None => continue,
+        };
+
+        debug!("Detected external change: {:?}", event);
+        if tx.send(event).is_err() {
+            return false;
+        }
+    }
+    true
+}